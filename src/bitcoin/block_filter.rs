@@ -0,0 +1,336 @@
+use std::collections::HashSet;
+use crate::bitcoin::datatypes::{Block, Script, UInt256};
+use crate::bitcoin::script::opcode::OP_RETURN;
+use crate::bitcoin::serialize::{
+   Medium,
+   Serializer as BitcoinSerializer,
+   Deserializer as BitcoinDeserializer,
+};
+
+pub const BLOCK_FILTER_TYPE_BASIC:u8 = 0;
+pub const BASIC_FILTER_P:u8 = 19;
+pub const BASIC_FILTER_M:u32 = 784931;
+
+fn dhash(data:&[u8]) -> UInt256 {
+   UInt256::new(&crate::ui::create_dhash256().u8_to_u8(data)[..])
+}
+
+struct BitWriter {
+   buf:  Vec<u8>,
+   bits: usize,
+}
+impl BitWriter {
+   fn new(buf:Vec<u8>) -> Self {
+      BitWriter { buf:buf, bits:0 }
+   }
+   fn write(&mut self, v:u64, nbits:usize) {
+      for i in (0..nbits).rev() {
+         if self.bits % 8 == 0 {
+            self.buf.push(0);
+         }
+         if (v >> i) & 1 == 1 {
+            let last = self.buf.len() - 1;
+            self.buf[last] |= 0x80 >> (self.bits % 8);
+         }
+         self.bits += 1;
+      }
+   }
+}
+
+struct BitReader<'a> {
+   buf: &'a [u8],
+   pos: usize,
+}
+impl <'a> BitReader<'a> {
+   fn read(&mut self, nbits:usize) -> crate::Result<u64> {
+      let mut v = 0u64;
+      for _ in 0..nbits {
+         let byte = match self.buf.get(self.pos / 8) {
+            Some(b) => *b,
+            None => crate::raise_protocol_error!("unexpected end of the filter"),
+         };
+         v = (v << 1) | ((byte >> (7 - self.pos % 8)) & 1) as u64;
+         self.pos += 1;
+      }
+      Ok(v)
+   }
+}
+
+/// Golomb-coded set (BIP158, blockfilter.cpp: GCSFilter)
+#[derive(Debug,Clone)]
+pub struct GcsFilter {
+   k0:      u64,
+   k1:      u64,
+   p:       u8,
+   m:       u32,
+   n:       u32,
+   encoded: Vec<u8>,
+}
+
+impl GcsFilter {
+   pub fn new<T:AsRef<[u8]>>(k0:u64, k1:u64, p:u8, m:u32, elements:&[T]) -> Self {
+      let mut ret = GcsFilter { k0:k0, k1:k1, p:p, m:m, n:elements.len() as u32, encoded:Vec::new() };
+      let mut hashes:Vec<u64> = elements.iter().map(|e| ret.hash_to_range(e.as_ref())).collect();
+      hashes.sort();
+
+      let mut buf = Vec::new();
+      let _ = BitcoinSerializer::new(&Medium::default().set_net()).serialize_var_int(&mut buf, ret.n as u64);
+      let mut w = BitWriter::new(buf);
+      let mut last = 0u64;
+      for h in hashes {
+         let delta = h - last;
+         // golomb-rice: the quotient in unary and the remainder in p bits
+         for _ in 0..(delta >> p) {
+            w.write(1, 1);
+         }
+         w.write(0, 1);
+         w.write(delta, p as usize);
+         last = h;
+      }
+      ret.encoded = w.buf;
+      ret
+   }
+
+   /// Checks that the filter has N elements and no excess data.
+   pub fn from_encoded(k0:u64, k1:u64, p:u8, m:u32, encoded:Vec<u8>) -> crate::Result<Self> {
+      let mut ret = GcsFilter { k0:k0, k1:k1, p:p, m:m, n:0, encoded:encoded };
+      ret.n = {
+         let (n, mut r) = ret.reader()?;
+         for _ in 0..n {
+            let _ = ret.read_delta(&mut r)?;
+         }
+         if (r.pos + 7) / 8 != r.buf.len() {
+            crate::raise_protocol_error!("filter has excess data");
+         }
+         n
+      };
+      Ok(ret)
+   }
+
+   pub fn n(&self) -> u32 { self.n }
+   pub fn encoded(&self) -> &[u8] { &self.encoded[..] }
+
+   fn f(&self) -> u64 {
+      self.n as u64 * self.m as u64
+   }
+
+   /// maps the siphash uniformly into [0, N*M)
+   fn hash_to_range(&self, element:&[u8]) -> u64 {
+      let h = crate::crypto::siphash24(self.k0, self.k1, element);
+      ((h as u128 * self.f() as u128) >> 64) as u64
+   }
+
+   fn reader(&self) -> crate::Result<(u32, BitReader<'_>)> {
+      let mut rs = &self.encoded[..];
+      let mut n = 0u64;
+      let _ = BitcoinDeserializer::new(&Medium::default().set_net()).deserialize_var_int(&mut rs, &mut n)?;
+      if (std::u32::MAX as u64) < n {
+         crate::raise_protocol_error!(format!("too many elements: {}", n));
+      }
+      Ok((n as u32, BitReader { buf:rs, pos:0 }))
+   }
+
+   fn read_delta(&self, r:&mut BitReader) -> crate::Result<u64> {
+      let mut q = 0u64;
+      while r.read(1)? == 1 {
+         q += 1;
+      }
+      let rem = r.read(self.p as usize)?;
+      Ok((q << self.p) + rem)
+   }
+
+   pub fn match_one(&self, element:&[u8]) -> crate::Result<bool> {
+      self.match_any(&[element])
+   }
+
+   pub fn match_any<T:AsRef<[u8]>>(&self, elements:&[T]) -> crate::Result<bool> {
+      if self.n == 0 {
+         return Ok(false);
+      }
+      let mut queries:Vec<u64> = elements.iter().map(|e| self.hash_to_range(e.as_ref())).collect();
+      queries.sort();
+      let (n, mut r) = self.reader()?;
+      let mut queries = queries.into_iter().peekable();
+      let mut value = 0u64;
+      for _ in 0..n {
+         value += self.read_delta(&mut r)?;
+         loop {
+            match queries.peek() {
+               None => return Ok(false),
+               Some(&q) if q == value => return Ok(true),
+               Some(&q) if q < value => { let _ = queries.next(); },
+               _ => break,
+            }
+         }
+      }
+      Ok(false)
+   }
+}
+
+/// BIP158 block filter and the filter header chain of BIP157
+#[derive(Debug,Clone)]
+pub struct BlockFilter {
+   filter_type: u8,
+   block_hash:  UInt256,
+   filter:      GcsFilter,
+}
+
+fn basic_filter_params(filter_type:u8, block_hash:&UInt256) -> crate::Result<(u64, u64, u8, u32)> {
+   if filter_type != BLOCK_FILTER_TYPE_BASIC {
+      crate::raise_protocol_error!(format!("unknown filter type: {}", filter_type));
+   }
+   let k = |o:usize| block_hash.data[o..(o+8)].iter().rev().fold(0u64, |acc, &b| acc << 8 | b as u64);
+   Ok((k(0), k(8), BASIC_FILTER_P, BASIC_FILTER_M))
+}
+
+impl BlockFilter {
+   /// Builds the basic filter from the output scripts of the block and the scripts spent by
+   /// the inputs of the non-coinbase txs. Empty and OP_RETURN output scripts are excluded.
+   pub fn new_basic(block:&Block, prevout_scripts:&[Script]) -> crate::Result<Self> {
+      let mut elements = HashSet::<&[u8]>::new();
+      for tx in block.txs.iter() {
+         for out in tx.outs.iter() {
+            let s = &out.script_pubkey.bytecode[..];
+            if !s.is_empty() && s[0] != OP_RETURN {
+               elements.insert(s);
+            }
+         }
+      }
+      for s in prevout_scripts.iter() {
+         if !s.bytecode.is_empty() {
+            elements.insert(&s.bytecode[..]);
+         }
+      }
+      let elements:Vec<&[u8]> = elements.into_iter().collect();
+      let block_hash = block.header.get_hash()?;
+      let (k0, k1, p, m) = basic_filter_params(BLOCK_FILTER_TYPE_BASIC, &block_hash)?;
+      Ok(BlockFilter {
+         filter_type: BLOCK_FILTER_TYPE_BASIC,
+         block_hash:  block_hash,
+         filter:      GcsFilter::new(k0, k1, p, m, &elements[..]),
+      })
+   }
+
+   /// Decodes the filter received by cfilter.
+   pub fn from_encoded(filter_type:u8, block_hash:&UInt256, encoded:Vec<u8>) -> crate::Result<Self> {
+      let (k0, k1, p, m) = basic_filter_params(filter_type, block_hash)?;
+      Ok(BlockFilter {
+         filter_type: filter_type,
+         block_hash:  block_hash.clone(),
+         filter:      GcsFilter::from_encoded(k0, k1, p, m, encoded)?,
+      })
+   }
+
+   pub fn filter_type(&self) -> u8 { self.filter_type }
+   pub fn block_hash(&self) -> &UInt256 { &self.block_hash }
+   pub fn filter(&self) -> &GcsFilter { &self.filter }
+   pub fn encoded(&self) -> &[u8] { self.filter.encoded() }
+
+   pub fn get_hash(&self) -> UInt256 {
+      dhash(self.encoded())
+   }
+
+   /// The header of the filter of the genesis block is chained to zero.
+   pub fn compute_header(&self, prev_header:&UInt256) -> UInt256 {
+      compute_filter_header(&self.get_hash(), prev_header)
+   }
+}
+
+/// dhash256(filter_hash || prev_header)
+pub fn compute_filter_header(filter_hash:&UInt256, prev_header:&UInt256) -> UInt256 {
+   let mut buf = [0u8; 64];
+   buf[0..32].copy_from_slice(&filter_hash.data[..]);
+   buf[32..64].copy_from_slice(&prev_header.data[..]);
+   dhash(&buf[..])
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::utils::{h2b, h2b_rev};
+
+   const GENESIS:&str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+   #[test]
+   fn test_testnet_genesis() {
+      // the first of the BIP158 test vectors
+      let mut block = Block::default();
+      let _ = crate::ui::bitcoin::deserialize(&h2b(GENESIS).unwrap()[..], &(), &mut block).unwrap();
+      block.header.time = 1296688602;
+      block.header.nonce = 414098458;
+      assert_eq!(block.header.get_hash().unwrap(), crate::bitcoin::presets::bitcoin_testnet::CHAIN.consensus.hash_genesis_block);
+
+      let filter = BlockFilter::new_basic(&block, &[]).unwrap();
+      assert_eq!(filter.encoded(), &h2b("019dfca8").unwrap()[..]);
+      let header = filter.compute_header(&UInt256::new_null());
+      assert_eq!(&header.data[..], &h2b_rev("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750").unwrap()[..]);
+
+      let decoded = BlockFilter::from_encoded(BLOCK_FILTER_TYPE_BASIC, filter.block_hash(), filter.encoded().to_vec()).unwrap();
+      assert_eq!(decoded.filter().n(), 1);
+      let script = &block.txs[0].outs[0].script_pubkey.bytecode[..];
+      assert_matches!(decoded.filter().match_one(script), Ok(true));
+      assert_matches!(decoded.filter().match_one(&script[1..]), Ok(false));
+   }
+
+   #[test]
+   fn test_basic_filter_elements() {
+      use crate::bitcoin::datatypes::{Tx, TxOut};
+      use crate::bitcoin::datatypes::test_utils::new_txin;
+      let mut block = Block::default();
+      let _ = crate::ui::bitcoin::deserialize(&h2b(GENESIS).unwrap()[..], &(), &mut block).unwrap();
+      let coinbase_script = block.txs[0].outs[0].script_pubkey.clone();
+      let mut tx = Tx::new_null();
+      tx.ins.push(new_txin(1));
+      let p2wpkh = Script::new(h2b("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap().to_vec());
+      for s in &[p2wpkh.clone(), Script::new(vec![]), Script::new(vec![OP_RETURN, 0x01, 0x02]), coinbase_script.clone()] {
+         tx.outs.push(TxOut { value:0, script_pubkey:s.clone() });
+      }
+      block.txs.push(tx);
+      let spent = Script::new(h2b("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap().to_vec());
+
+      // the spent script and the distinct non-empty, non-OP_RETURN output scripts
+      let filter = BlockFilter::new_basic(&block, &[spent.clone(), Script::new(vec![]), p2wpkh.clone()]).unwrap();
+      let (k0, k1, p, m) = basic_filter_params(BLOCK_FILTER_TYPE_BASIC, filter.block_hash()).unwrap();
+      let expected = [&coinbase_script.bytecode[..], &p2wpkh.bytecode[..], &spent.bytecode[..]];
+      assert_eq!(filter.filter().n(), 3);
+      assert_eq!(filter.encoded(), GcsFilter::new(k0, k1, p, m, &expected[..]).encoded());
+      for e in expected.iter() {
+         assert_matches!(filter.filter().match_one(e), Ok(true));
+      }
+      assert_matches!(filter.filter().match_one(&[OP_RETURN, 0x01, 0x02]), Ok(false));
+   }
+
+   #[test]
+   fn test_gcs_match() {
+      let included:Vec<Vec<u8>> = (0u32..100).map(|i| vec![0x51, i as u8, (i >> 8) as u8]).collect();
+      let excluded:Vec<Vec<u8>> = (0u32..100).map(|i| vec![0x52, i as u8, (i >> 8) as u8]).collect();
+      let filter = GcsFilter::new(0x0102, 0x0304, BASIC_FILTER_P, BASIC_FILTER_M, &included[..]);
+      let decoded = GcsFilter::from_encoded(0x0102, 0x0304, BASIC_FILTER_P, BASIC_FILTER_M, filter.encoded().to_vec()).unwrap();
+      assert_eq!(decoded.n(), 100);
+      for e in included.iter() {
+         assert_matches!(decoded.match_one(&e[..]), Ok(true));
+      }
+      // false positive rate is 1/M
+      assert!(excluded.iter().filter(|e| decoded.match_one(&e[..]).unwrap()).count() <= 1);
+      assert_matches!(decoded.match_any(&excluded[..]), Ok(_));
+      let mut mixed = excluded.clone();
+      mixed.push(included[50].clone());
+      assert_matches!(decoded.match_any(&mixed[..]), Ok(true));
+
+      let empty = GcsFilter::new::<Vec<u8>>(0, 0, BASIC_FILTER_P, BASIC_FILTER_M, &[]);
+      assert_eq!(empty.encoded(), &[0u8][..]);
+      assert_matches!(empty.match_one(&[1]), Ok(false));
+   }
+
+   #[test]
+   fn test_decode_invalid() {
+      let filter = GcsFilter::new(1, 2, BASIC_FILTER_P, BASIC_FILTER_M, &[&[1u8][..], &[2u8][..]]);
+      let mut v = filter.encoded().to_vec();
+      v.push(0);
+      assert_matches!(GcsFilter::from_encoded(1, 2, BASIC_FILTER_P, BASIC_FILTER_M, v), Err(crate::Error::BitcoinProtocol(_)));
+      let mut v = filter.encoded().to_vec();
+      v.pop();
+      assert_matches!(GcsFilter::from_encoded(1, 2, BASIC_FILTER_P, BASIC_FILTER_M, v), Err(crate::Error::BitcoinProtocol(_)));
+      assert_matches!(BlockFilter::from_encoded(1, &UInt256::new_null(), vec![0]), Err(crate::Error::BitcoinProtocol(_)));
+   }
+}
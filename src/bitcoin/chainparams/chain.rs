@@ -4,5 +4,7 @@ pub struct Chain<'a> {
    pub magic: u32,
    pub consensus: super::Consensus,
    pub base58check: super::Base58check<'a>,
+   /// human readable part of the bech32 segwit addresses
+   pub bech32_hrp: &'a str,
 }
 
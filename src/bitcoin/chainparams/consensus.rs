@@ -23,8 +23,8 @@ pub struct Consensus {
 }
 
 impl Consensus {
-//   pub fn getDifficultyAdjustmentInterval(&self) -> i64 {
-//      self.pow_target_timespan / self.pow_target_spacing
-//   }
+   pub fn get_difficulty_adjustment_interval(&self) -> i64 {
+      self.pow_target_timespan / self.pow_target_spacing
+   }
 }
 
@@ -10,6 +10,19 @@ pub struct BlockHeader {
    pub nonce: u32,
 }
 
+impl BlockHeader {
+   pub fn get_hash(&self) -> crate::Result<UInt256> {
+      use crypto::digest::Digest;
+      use crate::crypto::digest::{DigestWrite, DHash256};
+      let mut ws = DigestWrite::new(DHash256::new());
+      let enc = BitcoinSerializer::new(&crate::bitcoin::serialize::Medium::default().set_net());
+      let _ = self.serialize(&(), &enc, &mut ws)?;
+      let mut out = [0u8; 32];
+      ws.result(&mut out);
+      Ok(UInt256::new(&out))
+   }
+}
+
 impl std::fmt::Display for BlockHeader {
    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
       write!(f, "BlockHeader(version={}, prev={}, merkle={}, time={}, bits={}, nonce={})",
@@ -0,0 +1,93 @@
+use super::Amount;
+
+/// satoshis per 1000 virtual bytes, same as CFeeRate of bitcoin core
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub struct FeeRatePerKvB(pub Amount);
+
+/// satoshis per virtual byte
+#[derive(Debug,Default,Clone,Copy,PartialEq,Eq,PartialOrd,Ord)]
+pub struct FeeRatePerVB(pub Amount);
+
+impl FeeRatePerKvB {
+   /// rate of the fee paid for the vsize (CFeeRate::CFeeRate(nFeePaid, nBytes))
+   pub fn new(fee:Amount, vsize:usize) -> Self {
+      if vsize == 0 {
+         FeeRatePerKvB(0)
+      } else {
+         FeeRatePerKvB(fee * 1000 / vsize as Amount)
+      }
+   }
+   /// fee of the vsize, rounded up and 1 satoshi at least for the positive rate (CFeeRate::GetFee)
+   pub fn get_fee(&self, vsize:usize) -> Amount {
+      let vsize = vsize as Amount;
+      let fee = self.0 * vsize;
+      let fee = if 0 < fee && fee % 1000 != 0 { fee / 1000 + 1 } else { fee / 1000 };
+      if fee == 0 && vsize != 0 { self.0.signum() } else { fee }
+   }
+   pub fn get_fee_per_k(&self) -> Amount {
+      self.0
+   }
+}
+
+impl FeeRatePerVB {
+   /// rate of the fee paid for the vsize, rounded down
+   pub fn new(fee:Amount, vsize:usize) -> Self {
+      if vsize == 0 {
+         FeeRatePerVB(0)
+      } else {
+         FeeRatePerVB(fee / vsize as Amount)
+      }
+   }
+   pub fn get_fee(&self, vsize:usize) -> Amount {
+      self.0 * vsize as Amount
+   }
+}
+
+impl From<FeeRatePerVB> for FeeRatePerKvB {
+   fn from(r:FeeRatePerVB) -> Self {
+      FeeRatePerKvB(r.0 * 1000)
+   }
+}
+
+/// rounded down
+impl From<FeeRatePerKvB> for FeeRatePerVB {
+   fn from(r:FeeRatePerKvB) -> Self {
+      FeeRatePerVB(r.0 / 1000)
+   }
+}
+
+impl std::fmt::Display for FeeRatePerKvB {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "{} sat/kvB", self.0)
+   }
+}
+
+impl std::fmt::Display for FeeRatePerVB {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "{} sat/vB", self.0)
+   }
+}
+
+#[test]
+fn test_fee_rate() {
+   let r = FeeRatePerKvB(1000);
+   assert_eq!(r.get_fee(0), 0);
+   assert_eq!(r.get_fee(1), 1);
+   assert_eq!(r.get_fee(121), 121);
+   assert_eq!(FeeRatePerKvB(3000).get_fee(182), 546);
+   // rounded up
+   assert_eq!(FeeRatePerKvB(1).get_fee(1), 1);
+   assert_eq!(FeeRatePerKvB(1500).get_fee(3), 5);
+   assert_eq!(FeeRatePerKvB(-1).get_fee(1), -1);
+   assert_eq!(FeeRatePerKvB(0).get_fee(100), 0);
+
+   assert_eq!(FeeRatePerKvB::new(500, 250), FeeRatePerKvB(2000));
+   assert_eq!(FeeRatePerKvB::new(500, 0), FeeRatePerKvB(0));
+   assert_eq!(FeeRatePerVB::new(500, 250), FeeRatePerVB(2));
+   assert_eq!(FeeRatePerVB(2).get_fee(141), 282);
+
+   assert_eq!(FeeRatePerKvB::from(FeeRatePerVB(2)), FeeRatePerKvB(2000));
+   assert_eq!(FeeRatePerVB::from(FeeRatePerKvB(2999)), FeeRatePerVB(2));
+   assert_eq!(format!("{}", FeeRatePerVB(2)), "2 sat/vB");
+   assert_eq!(format!("{}", FeeRatePerKvB(2000)), "2000 sat/kvB");
+}
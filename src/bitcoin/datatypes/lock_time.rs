@@ -1,6 +1,6 @@
 use std::time::SystemTime;
 
-const TRANSACTION_LOCKTIME_BORDER:u32  = 500000000u32;
+pub const TRANSACTION_LOCKTIME_BORDER:u32  = 500000000u32;
 
 #[derive(Debug,PartialEq,Clone)]
 pub enum LockTime {
@@ -32,6 +32,16 @@ impl LockTime {
          _ => None,
       }
    }
+   pub fn as_u64(&self) -> u64 {
+      match self {
+         &LockTime::NoLock   => 0,
+         &LockTime::Block(v) => v as u64,
+         &LockTime::Time(t)  => {
+            use std::time::UNIX_EPOCH;
+            t.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+         },
+      }
+   }
    pub fn new_by_u64(v:u64) -> Self {
       if v == 0 {
          LockTime::NoLock
@@ -0,0 +1,66 @@
+use super::{Block, UInt256};
+
+def_error! { MerkleError }
+#[macro_export]
+macro_rules! raise_merkle_error {
+   ($m:expr) => {
+      Err($crate::bitcoin::datatypes::MerkleError::new($m, 0))?
+   }
+}
+
+pub fn dhash_pair(a:&UInt256, b:&UInt256) -> UInt256 {
+   let mut buf = [0u8;64];
+   buf[0..32].copy_from_slice(&a.data[..]);
+   buf[32..64].copy_from_slice(&b.data[..]);
+   UInt256::new(&crate::ui::create_dhash256().u8_to_u8(&buf[..])[..])
+}
+
+/// Computes the merkle root (consensus/merkle.cpp: ComputeMerkleRoot).
+/// The second value is true if the tree has two identical adjacent nodes,
+/// which make a different tx list have the same root (CVE-2012-2459).
+pub fn compute_merkle_root(mut hashes:Vec<UInt256>) -> (UInt256, bool) {
+   let mut mutated = false;
+   if hashes.is_empty() {
+      return (UInt256::new_null(), mutated);
+   }
+   while 1 < hashes.len() {
+      mutated |= hashes.chunks(2).any(|c| c.len() == 2 && c[0] == c[1]);
+      if hashes.len() % 2 != 0 {
+         let last = hashes[hashes.len()-1].clone();
+         hashes.push(last);
+      }
+      hashes = hashes.chunks(2).map(|c| dhash_pair(&c[0], &c[1])).collect();
+   }
+   (hashes.swap_remove(0), mutated)
+}
+
+pub fn block_merkle_root(block:&Block) -> crate::Result<(UInt256, bool)> {
+   let hashes = block.txs.iter().map(|tx| tx.txid()).collect::<crate::Result<Vec<_>>>()?;
+   Ok(compute_merkle_root(hashes))
+}
+
+/// the wtxid of the coinbase is replaced with zero
+pub fn block_witness_merkle_root(block:&Block) -> crate::Result<(UInt256, bool)> {
+   let mut hashes = Vec::<UInt256>::with_capacity(block.txs.len());
+   for (i, tx) in block.txs.iter().enumerate() {
+      hashes.push(if i == 0 { UInt256::new_null() } else { tx.wtxid()? });
+   }
+   Ok(compute_merkle_root(hashes))
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   #[test]
+   fn test_compute_merkle_root() {
+      let h = |n:u8| UInt256::new(&[n;32]);
+      assert_eq!(compute_merkle_root(vec![]), (UInt256::new_null(), false));
+      assert_eq!(compute_merkle_root(vec![h(1)]), (h(1), false));
+      let (r3, m3) = compute_merkle_root(vec![h(1), h(2), h(3)]);
+      let (r4, m4) = compute_merkle_root(vec![h(1), h(2), h(3), h(3)]);
+      assert_eq!(r3, r4);
+      assert_eq!((m3, m4), (false, true));
+      assert_eq!(r3, dhash_pair(&dhash_pair(&h(1), &h(2)), &dhash_pair(&h(3), &h(3))));
+   }
+}
@@ -1,4 +1,4 @@
-use super::{BlockHeader, PartialMerkleTree};
+use super::{Block, BlockHeader, PartialMerkleTree, UInt256};
 
 #[derive(Debug,Default,Clone)]
 pub struct MerkleBlock {
@@ -6,6 +6,32 @@ pub struct MerkleBlock {
    pub txn:    PartialMerkleTree,
 }
 
+/// (merkleblock.cpp: CMerkleBlock)
+impl MerkleBlock {
+   /// Builds the proof of the transactions of `matches`, which has the flags of each tx of the block.
+   pub fn new(block:&Block, matches:&[bool]) -> crate::Result<Self> {
+      let txids = block.txs.iter().map(|tx| tx.txid()).collect::<crate::Result<Vec<_>>>()?;
+      Ok(MerkleBlock {
+         header: block.header.clone(),
+         txn:    PartialMerkleTree::new(&txids[..], matches)?,
+      })
+   }
+
+   pub fn from_txids(block:&Block, txids:&std::collections::HashSet<UInt256>) -> crate::Result<Self> {
+      let matches = block.txs.iter().map(|tx| tx.txid().map(|h| txids.contains(&h))).collect::<crate::Result<Vec<_>>>()?;
+      Self::new(block, &matches[..])
+   }
+
+   /// Verifies the tree against the merkle root of the header and returns the matched txs.
+   pub fn extract_matches(&self) -> crate::Result<Vec<(u32, UInt256)>> {
+      let (root, matches) = self.txn.extract_matches()?;
+      if root != self.header.hash_merkle_root {
+         crate::raise_merkle_error!(format!("merkle root mismatch: {}", root));
+      }
+      Ok(matches)
+   }
+}
+
 impl std::fmt::Display for MerkleBlock {
    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
       write!(f, "MerkleBlock(header={}, txn={})", self.header, self.txn)
@@ -37,3 +63,23 @@ impl BitcoinDeserializee for MerkleBlock {
       Ok(r)
    }
 }
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::datatypes::test_utils::new_block;
+
+   #[test]
+   fn test_from_txids() {
+      let block = new_block(5);
+      let mut set = std::collections::HashSet::new();
+      set.insert(block.txs[0].txid().unwrap());
+      set.insert(block.txs[4].txid().unwrap());
+      let mb = MerkleBlock::from_txids(&block, &set).unwrap();
+      let m = mb.extract_matches().unwrap();
+      assert_eq!(m.iter().map(|&(i, _)| i).collect::<Vec<_>>(), vec![0, 4]);
+
+      assert_matches!(MerkleBlock::new(&Block::default(), &[]), Err(crate::Error::BitcoinMerkle(_)));
+      assert_matches!(MerkleBlock::new(&block, &[true]), Err(crate::Error::BitcoinMerkle(_)));
+   }
+}
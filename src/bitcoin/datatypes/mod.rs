@@ -4,14 +4,19 @@ pub use self::uint256::UInt256;
 pub mod script;
 pub use self::script::Script;
 
+pub mod witness;
+pub use self::witness::Witness;
+
 pub mod tx_in;
 pub use self::tx_in::{TxIn, TxOutPoint};
 pub mod tx_out;
-pub use self::tx_out::{TxOut};
+pub use self::tx_out::{TxOut, Amount};
+pub mod fee_rate;
+pub use self::fee_rate::{FeeRatePerKvB, FeeRatePerVB};
 pub mod lock_time;
 pub use self::lock_time::{LockTime};
 pub mod tx;
-pub use self::tx::{Tx};
+pub use self::tx::{Tx, WITNESS_SCALE_FACTOR};
 
 pub mod block_header;
 pub use self::block_header::BlockHeader;
@@ -22,9 +27,14 @@ pub use self::block_locator::BlockLocator;
 pub mod block;
 pub use self::block::Block;
 
+pub mod merkle;
+pub use self::merkle::MerkleError;
+
 pub mod partial_merkle_tree;
 pub use self::partial_merkle_tree::PartialMerkleTree;
 
 pub mod merkle_block;
 pub use self::merkle_block::MerkleBlock;
 
+#[cfg(test)]
+pub mod test_utils;
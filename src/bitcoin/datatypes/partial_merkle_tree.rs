@@ -1,4 +1,8 @@
 use super::UInt256;
+use super::merkle::dhash_pair;
+
+// MAX_BLOCK_WEIGHT / MIN_TRANSACTION_WEIGHT
+const MAX_TRANSACTIONS:u32 = 4000000 / (60 * 4);
 
 #[derive(Debug,Default,Clone)]
 pub struct PartialMerkleTree {
@@ -7,6 +11,129 @@ pub struct PartialMerkleTree {
    pub hashes: Vec<UInt256>,
 }
 
+/// (merkleblock.cpp: CPartialMerkleTree)
+impl PartialMerkleTree {
+   /// Builds the tree of the txids which proves the txids of `matches`.
+   pub fn new(txids:&[UInt256], matches:&[bool]) -> crate::Result<Self> {
+      if txids.is_empty() {
+         crate::raise_merkle_error!("partial merkle tree has no transactions");
+      }
+      if txids.len() != matches.len() {
+         crate::raise_merkle_error!(format!("{} flags for {} txids", matches.len(), txids.len()));
+      }
+      let mut ret = PartialMerkleTree {
+         n_transactions: txids.len() as u32,
+         bits:           bit_vec::BitVec::new(),
+         hashes:         Vec::new(),
+      };
+      let mut height = 0;
+      while 1 < ret.calc_tree_width(height) {
+         height += 1;
+      }
+      ret.traverse_and_build(height, 0, txids, matches);
+      Ok(ret)
+   }
+
+   fn calc_tree_width(&self, height:u32) -> u32 {
+      ((self.n_transactions as u64 + (1u64 << height) - 1) >> height) as u32
+   }
+
+   fn calc_hash(&self, height:u32, pos:u32, txids:&[UInt256]) -> UInt256 {
+      if height == 0 {
+         return txids[pos as usize].clone();
+      }
+      let left = self.calc_hash(height-1, pos*2, txids);
+      let right = if pos*2+1 < self.calc_tree_width(height-1) {
+         self.calc_hash(height-1, pos*2+1, txids)
+      } else {
+         left.clone()
+      };
+      dhash_pair(&left, &right)
+   }
+
+   fn traverse_and_build(&mut self, height:u32, pos:u32, txids:&[UInt256], matches:&[bool]) {
+      let from = (pos as usize) << height;
+      let to = std::cmp::min(((pos as usize) + 1) << height, self.n_transactions as usize);
+      let parent_of_match = matches[from..to].iter().any(|&m| m);
+      self.bits.push(parent_of_match);
+      if height == 0 || !parent_of_match {
+         let h = self.calc_hash(height, pos, txids);
+         self.hashes.push(h);
+      } else {
+         self.traverse_and_build(height-1, pos*2, txids, matches);
+         if pos*2+1 < self.calc_tree_width(height-1) {
+            self.traverse_and_build(height-1, pos*2+1, txids, matches);
+         }
+      }
+   }
+
+   fn traverse_and_extract(&self, height:u32, pos:u32, bits_used:&mut usize, hashes_used:&mut usize,
+                           matches:&mut Vec<(u32, UInt256)>) -> crate::Result<UInt256> {
+      if self.bits.len() <= *bits_used {
+         crate::raise_merkle_error!("partial merkle tree overflowed the bits");
+      }
+      let parent_of_match = self.bits[*bits_used];
+      *bits_used += 1;
+      if height == 0 || !parent_of_match {
+         if self.hashes.len() <= *hashes_used {
+            crate::raise_merkle_error!("partial merkle tree overflowed the hashes");
+         }
+         let h = self.hashes[*hashes_used].clone();
+         *hashes_used += 1;
+         if height == 0 && parent_of_match {
+            matches.push((pos, h.clone()));
+         }
+         Ok(h)
+      } else {
+         let left = self.traverse_and_extract(height-1, pos*2, bits_used, hashes_used, matches)?;
+         let right = if pos*2+1 < self.calc_tree_width(height-1) {
+            let right = self.traverse_and_extract(height-1, pos*2+1, bits_used, hashes_used, matches)?;
+            // the identical subtrees make another tx list of the same root (CVE-2012-2459)
+            if right == left {
+               crate::raise_merkle_error!("partial merkle tree has a duplicated right subtree");
+            }
+            right
+         } else {
+            left.clone()
+         };
+         Ok(dhash_pair(&left, &right))
+      }
+   }
+
+   /// Computes the merkle root and returns it with the matched txids and their indexes in the block.
+   /// The caller must compare the root with the one of the block header.
+   pub fn extract_matches(&self) -> crate::Result<(UInt256, Vec<(u32, UInt256)>)> {
+      if self.n_transactions == 0 {
+         crate::raise_merkle_error!("partial merkle tree has no transactions");
+      }
+      if MAX_TRANSACTIONS < self.n_transactions {
+         crate::raise_merkle_error!(format!("partial merkle tree has too many transactions: {}", self.n_transactions));
+      }
+      if (self.n_transactions as usize) < self.hashes.len() {
+         crate::raise_merkle_error!(format!("partial merkle tree has more hashes than transactions: {}", self.hashes.len()));
+      }
+      if self.bits.len() < self.hashes.len() {
+         crate::raise_merkle_error!(format!("partial merkle tree has fewer bits than hashes: {}", self.bits.len()));
+      }
+      let mut height = 0;
+      while 1 < self.calc_tree_width(height) {
+         height += 1;
+      }
+      let mut bits_used = 0usize;
+      let mut hashes_used = 0usize;
+      let mut matches = Vec::new();
+      let root = self.traverse_and_extract(height, 0, &mut bits_used, &mut hashes_used, &mut matches)?;
+      // all the bits except for the padding of the last byte must be consumed
+      if (bits_used + 7) / 8 != (self.bits.len() + 7) / 8 {
+         crate::raise_merkle_error!("partial merkle tree has excess bits");
+      }
+      if hashes_used != self.hashes.len() {
+         crate::raise_merkle_error!("partial merkle tree has excess hashes");
+      }
+      Ok((root, matches))
+   }
+}
+
 impl std::fmt::Display for PartialMerkleTree {
    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
       write!(f, "PartialMerkleTree(n={}, bits={:?}, hash={:?})", self.n_transactions, self.bits, self.hashes)
@@ -65,3 +192,74 @@ impl BitcoinDeserializee for PartialMerkleTree {
       Ok(r)
    }
 }
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::datatypes::merkle::compute_merkle_root;
+
+   fn txids(n:usize) -> Vec<UInt256> {
+      (0..n).map(|i| UInt256::new(&crate::ui::create_dhash256().u8_to_u8(&[i as u8, (i >> 8) as u8][..])[..])).collect()
+   }
+
+   fn reserialize(tree:&PartialMerkleTree) -> PartialMerkleTree {
+      let v = crate::ui::bitcoin::serialize(tree, &()).unwrap();
+      let mut ret = PartialMerkleTree::default();
+      let _ = crate::ui::bitcoin::deserialize(&v[..], &(), &mut ret).unwrap();
+      ret
+   }
+
+   #[test]
+   fn test_build_and_extract() {
+      // fixed patterns instead of the random ones of pmt_tests.cpp
+      for &n in [1usize, 4, 7, 17, 56, 100, 127, 256, 312, 513, 1000, 4095].iter() {
+         let ids = txids(n);
+         let (root, _) = compute_merkle_root(ids.clone());
+         for &step in [1usize, 2, 3, 7, 31, 1000].iter() {
+            let matches:Vec<bool> = (0..n).map(|i| i % step == step / 2).collect();
+            let tree = reserialize(&PartialMerkleTree::new(&ids[..], &matches[..]).unwrap());
+            assert!(tree.hashes.len() <= n);
+            let (r, m) = tree.extract_matches().unwrap();
+            assert_eq!(r, root);
+            let expected:Vec<(u32, UInt256)> = (0..n).filter(|&i| matches[i]).map(|i| (i as u32, ids[i].clone())).collect();
+            assert_eq!(m, expected);
+         }
+      }
+   }
+
+   #[test]
+   fn test_malformed() {
+      let ids = txids(7);
+      let matches = [false, true, false, false, true, false, false];
+      let tree = PartialMerkleTree::new(&ids[..], &matches).unwrap();
+      assert_matches!(tree.extract_matches(), Ok(_));
+
+      let mut t = tree.clone();
+      t.hashes.push(UInt256::new_null());
+      assert_matches!(t.extract_matches(), Err(crate::Error::BitcoinMerkle(_)));
+
+      let mut t = tree.clone();
+      t.hashes.pop();
+      assert_matches!(t.extract_matches(), Err(crate::Error::BitcoinMerkle(_)));
+
+      let mut t = reserialize(&tree);
+      for _ in 0..8 {
+         t.bits.push(false);
+      }
+      assert_matches!(t.extract_matches(), Err(crate::Error::BitcoinMerkle(_)));
+
+      let mut t = tree.clone();
+      t.n_transactions = 0;
+      assert_matches!(t.extract_matches(), Err(crate::Error::BitcoinMerkle(_)));
+
+      assert_matches!(PartialMerkleTree::new(&[], &[]), Err(crate::Error::BitcoinMerkle(_)));
+      assert_matches!(PartialMerkleTree::new(&ids[..], &matches[..6]), Err(crate::Error::BitcoinMerkle(_)));
+
+      // the root of [a,b,c] equals the one of [a,b,c,c]
+      let mut ids = txids(3);
+      let c = ids[2].clone();
+      ids.push(c);
+      let tree = PartialMerkleTree::new(&ids[..], &[false, false, true, true]).unwrap();
+      assert_matches!(tree.extract_matches(), Err(crate::Error::BitcoinMerkle(_)));
+   }
+}
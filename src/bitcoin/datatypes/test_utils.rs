@@ -0,0 +1,32 @@
+use super::{Block, Script, Tx, TxIn, TxOut, TxOutPoint, UInt256};
+
+/// spends the output 0 of the txid filled with n
+pub fn new_txin(n:u8) -> TxIn {
+   TxIn {
+      prevout:    TxOutPoint { txid: UInt256::new(&[n;32]), n: 0 },
+      script_sig: Script::new(vec![0x51]),
+      sequence:   TxIn::SEQUENCE_FINAL,
+      witness:    Default::default(),
+   }
+}
+
+/// spends new_txin(n) to an output which pushes n
+pub fn new_tx(n:u8) -> Tx {
+   let mut tx = Tx::new_null();
+   tx.ins.push(new_txin(n));
+   tx.outs.push(TxOut { value:1000, script_pubkey:Script::new(vec![0x01, n]) });
+   tx
+}
+
+/// the coinbase and new_tx(1) to new_tx(n-1), with the merkle root
+pub fn new_block(n:u8) -> Block {
+   let mut block = Block::default();
+   let mut coinbase = new_tx(0);
+   coinbase.ins[0].prevout = TxOutPoint::new_null();
+   block.txs.push(coinbase);
+   for i in 1..n {
+      block.txs.push(new_tx(i));
+   }
+   block.header.hash_merkle_root = super::merkle::block_merkle_root(&block).unwrap().0;
+   block
+}
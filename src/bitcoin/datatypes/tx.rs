@@ -1,4 +1,4 @@
-use super::{UInt256, TxIn, TxOut, LockTime};
+use super::{UInt256, TxIn, TxOut, LockTime, Amount, FeeRatePerKvB};
 
 #[derive(Debug,Default,Clone)]
 pub struct Tx {
@@ -10,6 +10,9 @@ pub struct Tx {
 
 const TRANSACTION_CURRENT_VERSION:i32 = 1i32;
 
+// consensus/consensus.h
+pub const WITNESS_SCALE_FACTOR:usize = 4;
+
 impl Tx {
    pub fn new_null() -> Self {
       Tx {
@@ -25,10 +28,69 @@ impl Tx {
    pub fn is_null(&self) -> bool {
       self.ins.len() == 0 && self.outs.len() == 0
    }
+   pub fn has_witness(&self) -> bool {
+      self.ins.iter().any(|txin| !txin.witness.is_null())
+   }
    pub fn get_hash(&self) -> crate::Result<UInt256> {
-      let b = crate::ui::bitcoin::serialize(self, &())?;
-      let b = crate::ui::create_dhash256().u8_to_u8(b);
-      Ok(UInt256::new(b.as_ref()))
+      self.txid()
+   }
+   pub fn txid(&self) -> crate::Result<UInt256> {
+      self.hash_without_witness()
+   }
+   pub fn wtxid(&self) -> crate::Result<UInt256> {
+      self.hash_by_medium(&Medium::default().set_net().set_witness())
+   }
+   pub fn hash_without_witness(&self) -> crate::Result<UInt256> {
+      self.hash_by_medium(&Medium::default().set_net().set_no_witness())
+   }
+   /// serialized size without the witness
+   pub fn base_size(&self) -> crate::Result<usize> {
+      self.size_by_medium(&Medium::default().set_net().set_no_witness())
+   }
+   /// serialized size with the witness
+   pub fn total_size(&self) -> crate::Result<usize> {
+      self.size_by_medium(&Medium::default().set_net().set_witness())
+   }
+   /// BIP141 weight (consensus/validation.h: GetTransactionWeight)
+   pub fn weight(&self) -> crate::Result<usize> {
+      Ok(self.base_size()? * (WITNESS_SCALE_FACTOR - 1) + self.total_size()?)
+   }
+   /// weight in virtual bytes, rounded up (policy.cpp: GetVirtualTransactionSize)
+   pub fn vsize(&self) -> crate::Result<usize> {
+      Ok((self.weight()? + WITNESS_SCALE_FACTOR - 1) / WITNESS_SCALE_FACTOR)
+   }
+   /// Sum of the input values minus the output values, or None if the number of prevouts
+   /// differs from the inputs or the sums overflow. prevouts are the outputs spent by self.ins
+   /// in the same order. The coinbase has no fee. Amounts are not checked, see
+   /// validation::check_tx_inputs.
+   pub fn fee(&self, prevouts:&[TxOut]) -> Option<Amount> {
+      if self.is_coin_base() {
+         return Some(0);
+      }
+      if self.ins.len() != prevouts.len() {
+         return None;
+      }
+      let value_in = prevouts.iter().try_fold(0 as Amount, |acc, prevout| acc.checked_add(prevout.value))?;
+      let value_out = self.outs.iter().try_fold(0 as Amount, |acc, txout| acc.checked_add(txout.value))?;
+      value_in.checked_sub(value_out)
+   }
+   /// fee per 1000 virtual bytes
+   pub fn fee_rate(&self, fee:Amount) -> crate::Result<FeeRatePerKvB> {
+      Ok(FeeRatePerKvB::new(fee, self.vsize()?))
+   }
+   fn size_by_medium(&self, med:&Medium) -> crate::Result<usize> {
+      let enc = BitcoinSerializer::new(med);
+      self.serialize(&(), &enc, &mut std::io::sink())
+   }
+   fn hash_by_medium(&self, med:&Medium) -> crate::Result<UInt256> {
+      use crypto::digest::Digest;
+      use crate::crypto::digest::{DigestWrite, DHash256};
+      let mut ws = DigestWrite::new(DHash256::new());
+      let enc = BitcoinSerializer::new(med);
+      let _ = self.serialize(&(), &enc, &mut ws)?;
+      let mut out = [0u8; 32];
+      ws.result(&mut out);
+      Ok(UInt256::new(&out))
    }
 }
 
@@ -39,6 +101,7 @@ impl std::fmt::Display for Tx {
 }
 
 use crate::bitcoin::serialize::{
+   Medium,
    Serializer as BitcoinSerializer,
    Serializee as BitcoinSerializee,
    Deserializer as BitcoinDeserializer,
@@ -48,9 +111,19 @@ impl BitcoinSerializee for Tx {
    type P = ();
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
       let mut r:usize = 0;
+      let with_witness = e.medium().is_witness() && self.has_witness();
       r += e.serialize_i32le(ws, self.version)?;
+      if with_witness {
+         r += e.serialize_u8(ws, 0x00)?; // marker: looks like an empty vin for legacy parsers
+         r += e.serialize_u8(ws, 0x01)?; // flag
+      }
       r += e.serialize_var_array(&(), ws, self.ins.as_slice(), std::usize::MAX)?;
       r += e.serialize_var_array(&(), ws, self.outs.as_slice(), std::usize::MAX)?;
+      if with_witness {
+         for txin in self.ins.iter() {
+            r += txin.witness.serialize(&(), e, ws)?;
+         }
+      }
       r += self.locktime.serialize(&(), e, ws)?;
       Ok(r)
    }
@@ -59,9 +132,32 @@ impl BitcoinDeserializee for Tx {
    type P = ();
    fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
       let mut r:usize = 0;
+      let allow_witness = d.medium().is_witness();
+      let mut flags:u8 = 0;
       r += d.deserialize_i32le(rs, &mut self.version)?;
       r += d.deserialize_var_array(&(), rs, &mut self.ins, std::usize::MAX)?;
-      r += d.deserialize_var_array(&(), rs, &mut self.outs, std::usize::MAX)?;
+      if self.ins.len() == 0 && allow_witness {
+         // empty vin is a marker of extended format
+         r += d.deserialize_u8(rs, &mut flags)?;
+         if flags != 0 {
+            r += d.deserialize_var_array(&(), rs, &mut self.ins, std::usize::MAX)?;
+            r += d.deserialize_var_array(&(), rs, &mut self.outs, std::usize::MAX)?;
+         }
+      } else {
+         r += d.deserialize_var_array(&(), rs, &mut self.outs, std::usize::MAX)?;
+      }
+      if (flags & 1) != 0 && allow_witness {
+         flags ^= 1;
+         for txin in self.ins.iter_mut() {
+            r += txin.witness.deserialize(&(), d, rs)?;
+         }
+         if !self.has_witness() {
+            raise_deserialize_error!("superfluous witness record");
+         }
+      }
+      if flags != 0 {
+         raise_deserialize_error!("unknown transaction optional data");
+      }
       r += self.locktime.deserialize(&(), d, rs)?;
       Ok(r)
    }
@@ -98,7 +194,7 @@ fn test_deserialize_transaction() {
 
 #[test]
 fn test_serialize_transaction() {
-   use super::{UInt256, Script, TxIn, TxOutPoint};
+   use super::{UInt256, Script, TxIn, TxOutPoint, Witness};
 
    let mut tx = Tx::new_null();
    tx.ins.push(TxIn {
@@ -108,6 +204,7 @@ fn test_serialize_transaction() {
       },
       script_sig: crate::ui::bitcoin::hex_to_script("483045022100b31557e47191936cb14e013fb421b1860b5e4fd5d2bc5ec1938f4ffb1651dc8902202661c2920771fd29dd91cd4100cefb971269836da4914d970d333861819265ba014104c54f8ea9507f31a05ae325616e3024bd9878cb0a5dff780444002d731577be4e2e69c663ff2da922902a4454841aa1754c1b6292ad7d317150308d8cce0ad7ab").unwrap(),
       sequence: 0xFFFFFFFFu32,
+      witness: Witness::new_null(),
    } );
    tx.ins.push(TxIn {
       prevout: TxOutPoint {
@@ -116,6 +213,7 @@ fn test_serialize_transaction() {
       },
       script_sig: crate::ui::bitcoin::hex_to_script("4830450220230110bc99ef311f1f8bda9d0d968bfe5dfa4af171adbef9ef71678d658823bf022100f956d4fcfa0995a578d84e7e913f9bb1cf5b5be1440bcede07bce9cd5b38115d014104c6ec27cffce0823c3fecb162dbd576c88dd7cda0b7b32b0961188a392b488c94ca174d833ee6a9b71c0996620ae71e799fc7c77901db147fa7d97732e49c8226").unwrap(),
       sequence: 0xFFFFFFFFu32,
+      witness: Witness::new_null(),
    } );
    tx.outs.push(TxOut {
       value: 39000000,
@@ -130,3 +228,86 @@ fn test_serialize_transaction() {
    
    assert_eq!(crate::ui::bitcoin::tx_to_txid(&tx).unwrap(), "9021b49d445c719106c95d561b9c3fac7bcb3650db67684a9226cd7fa1e1c1a0");
 }
+
+#[test]
+fn test_witness_transaction() {
+   use crate::bitcoin::serialize::Medium;
+   // BIP143 native P2WPKH example
+   let hex = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+   let stripped = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+   let tx = crate::ui::bitcoin::hex_to_tx(hex);
+   assert_matches!(tx, Ok(_));
+   let tx = tx.unwrap();
+   assert_eq!(tx.has_witness(), true);
+   assert_eq!(tx.ins.len(), 2);
+   assert_eq!(tx.outs.len(), 2);
+   assert_eq!(tx.ins[0].witness.is_null(), true);
+   assert_eq!(tx.ins[1].witness.len(), 2);
+   assert_eq!(crate::utils::b2h(tx.ins[1].witness.stack[1].as_slice()), "025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357");
+   assert_eq!(crate::ui::bitcoin::tx_to_hex(&tx).unwrap(), hex);
+   assert_eq!(crate::ui::bitcoin::tx_to_txid(&tx).unwrap(),  "e8151a2af31c368a35053ddd4bdb285a8595c769a3ad83e0fa02314a602d4609");
+   assert_eq!(crate::ui::bitcoin::tx_to_wtxid(&tx).unwrap(), "c36c38370907df2324d9ce9d149d191192f338b37665a82e78e76a12c909b762");
+   assert_eq!(tx.txid().unwrap(), tx.hash_without_witness().unwrap());
+   assert_eq!(tx.txid().unwrap(), tx.get_hash().unwrap());
+   assert_eq!(crate::ui::bitcoin::tx_to_txid_uint256(&tx).unwrap(), tx.txid().unwrap());
+   assert_eq!(crate::ui::bitcoin::tx_to_wtxid_uint256(&tx).unwrap(), tx.wtxid().unwrap());
+
+   let med = Medium::new("net,nowitness").unwrap();
+   let enc = BitcoinSerializer::new(&med);
+   let mut ws = Vec::<u8>::new();
+   assert_matches!(tx.serialize(&(), &enc, &mut ws), Ok(_));
+   assert_eq!(crate::utils::b2h(ws), stripped);
+
+   let tx2 = crate::ui::bitcoin::hex_to_tx(stripped).unwrap();
+   assert_eq!(tx2.has_witness(), false);
+   assert_eq!(crate::ui::bitcoin::tx_to_hex(&tx2).unwrap(), stripped);
+   assert_eq!(tx2.txid().unwrap(),  tx.txid().unwrap());
+   assert_eq!(tx2.wtxid().unwrap(), tx2.txid().unwrap());
+
+   // flag is set but all witnesses are empty
+   let superfluous = format!("{}0001{}0000{}", &stripped[..8], &stripped[8..stripped.len()-8], &stripped[stripped.len()-8..]);
+   assert_matches!(crate::ui::bitcoin::hex_to_tx(superfluous.as_str()), Err(_));
+   // unknown flag
+   let unknown = format!("{}0002{}", &stripped[..8], &stripped[8..]);
+   assert_matches!(crate::ui::bitcoin::hex_to_tx(unknown.as_str()), Err(_));
+}
+
+#[test]
+fn test_weight_and_fee() {
+   use super::Script;
+   use super::{FeeRatePerVB, TxOutPoint};
+   // BIP143 native P2WPKH example
+   let hex = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000";
+   let mut tx = crate::ui::bitcoin::hex_to_tx(hex).unwrap();
+   assert_eq!(tx.base_size().unwrap(), 233);
+   assert_eq!(tx.total_size().unwrap(), 343);
+   assert_eq!(tx.weight().unwrap(), 233 * 3 + 343);
+   assert_eq!(tx.vsize().unwrap(), 261);
+
+   let prevouts = [
+      TxOut { value: 625000000, script_pubkey: Script::new_null() },
+      TxOut { value: 600000000, script_pubkey: Script::new_null() },
+   ];
+   let fee = 625000000 + 600000000 - 112340000 - 223450000;
+   assert_eq!(tx.fee(&prevouts), Some(fee));
+   assert_eq!(tx.fee_rate(fee).unwrap(), FeeRatePerKvB(fee * 1000 / 261));
+   assert_eq!(FeeRatePerVB::from(tx.fee_rate(fee).unwrap()), FeeRatePerVB(fee / 261));
+   assert_eq!(tx.fee(&prevouts[..1]), None);
+   let mut overflow = prevouts.clone();
+   overflow[0].value = Amount::max_value();
+   assert_eq!(tx.fee(&overflow), None);
+
+   let mut coinbase = tx.clone();
+   coinbase.ins.truncate(1);
+   coinbase.ins[0].prevout = TxOutPoint::new_null();
+   assert_eq!(coinbase.fee(&[]), Some(0));
+
+   // without witness, all bytes weigh 4
+   for txin in tx.ins.iter_mut() {
+      txin.witness.set_null();
+   }
+   assert_eq!(tx.total_size().unwrap(), 233);
+   assert_eq!(tx.weight().unwrap(), 233 * 4);
+   assert_eq!(tx.vsize().unwrap(), 233);
+}
@@ -1,6 +1,6 @@
-use super::{UInt256, Script};
+use super::{UInt256, Script, Witness};
 
-#[derive(Debug,Default,Clone,Eq,PartialEq,PartialOrd,Ord)]
+#[derive(Debug,Default,Clone,Eq,PartialEq,PartialOrd,Ord,Hash)]
 pub struct TxOutPoint {
    pub txid: UInt256,
    pub n:    u32,
@@ -26,6 +26,7 @@ pub struct TxIn {
    pub prevout:    TxOutPoint,
    pub script_sig: Script,
    pub sequence:   u32,
+   pub witness:    Witness, // serialized by Tx (BIP144)
 }
 
 
@@ -51,7 +52,7 @@ impl TxIn {
       if l_is_blocktime ^ r_is_blocktime {
          None
       } else {
-         Some((l & Self::SEQUENCE_LOCKTIME_MASK) > (r & Self::SEQUENCE_LOCKTIME_MASK))
+         Some((l & Self::SEQUENCE_LOCKTIME_MASK) >= (r & Self::SEQUENCE_LOCKTIME_MASK))
       }
    }
 
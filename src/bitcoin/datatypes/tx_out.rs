@@ -2,9 +2,13 @@ use super::{Script};
 
 pub type Amount = i64;
 
-#[allow(dead_code)] const COIN:Amount = 100000000;
-#[allow(dead_code)] const CENT:Amount = 1000000;
-#[allow(dead_code)] const MAX_MONEY:Amount = 21000000 * COIN;
+#[allow(dead_code)] pub const COIN:Amount = 100000000;
+#[allow(dead_code)] pub const CENT:Amount = 1000000;
+#[allow(dead_code)] pub const MAX_MONEY:Amount = 21000000 * COIN;
+
+pub fn money_range(v:Amount) -> bool {
+   0 <= v && v <= MAX_MONEY
+}
 
 #[derive(Debug,Default,Clone)]
 pub struct TxOut {
@@ -34,6 +34,66 @@ impl UInt256 {
    pub fn as_slice(&self) -> &[u8] {
       &self.data[..]
    }
+   /// as a 256bit unsigned integer (arith_uint256)
+   pub fn to_biguint(&self) -> num::bigint::BigUint {
+      num::bigint::BigUint::from_bytes_le(&self.data[..])
+   }
+   /// higher bits than 256 are truncated
+   pub fn from_biguint(v:&num::bigint::BigUint) -> Self {
+      let b = v.to_bytes_le();
+      let mut ret = UInt256::new_null();
+      let len = std::cmp::min(b.len(), 32);
+      ret.data[..len].copy_from_slice(&b[..len]);
+      ret
+   }
+   /// numerical comparison. (derived Ord compares the little endian bytes)
+   pub fn cmp_as_uint(&self, other:&Self) -> std::cmp::Ordering {
+      self.data.iter().rev().cmp(other.data.iter().rev())
+   }
+
+   /// Decodes the compact representation "nBits" (arith_uint256::SetCompact).
+   /// Returns (value, negative, overflow).
+   pub fn from_compact(bits:u32) -> (Self, bool, bool) {
+      let size = (bits >> 24) as usize;
+      let mut word = bits & 0x007fffff;
+      let v = if size <= 3 {
+         word >>= 8 * (3 - size);
+         UInt256::from(word as u64)
+      } else {
+         UInt256::from(word as u64) << (8 * (size - 3))
+      };
+      let negative = word != 0 && (bits & 0x00800000) != 0;
+      let overflow = word != 0 && (34 < size || (0xff < word && 33 < size) || (0xffff < word && 32 < size));
+      (v, negative, overflow)
+   }
+
+   /// Encodes into the compact representation (arith_uint256::GetCompact).
+   pub fn to_compact(&self) -> u32 {
+      let mut size = ((self.bits() + 7) / 8) as u32;
+      let mut compact = if size <= 3 {
+         self.low_u32() << (8 * (3 - size))
+      } else {
+         (self.clone() >> (8 * (size as usize - 3))).low_u32()
+      };
+      // the 0x00800000 bit denotes the sign
+      if compact & 0x00800000 != 0 {
+         compact >>= 8;
+         size += 1;
+      }
+      compact | (size << 24)
+   }
+
+   /// number of significant bits
+   pub fn bits(&self) -> usize {
+      match self.data.iter().rposition(|b| *b != 0) {
+         None => 0,
+         Some(i) => i * 8 + (8 - self.data[i].leading_zeros() as usize),
+      }
+   }
+
+   pub fn low_u32(&self) -> u32 {
+      (self.data[0] as u32) | (self.data[1] as u32) << 8 | (self.data[2] as u32) << 16 | (self.data[3] as u32) << 24
+   }
 }
 
 impl std::ops::Index<usize> for UInt256 {
@@ -47,6 +107,46 @@ impl std::ops::IndexMut<usize> for UInt256 {
       &mut self.data[i]
    }
 }
+impl From<u64> for UInt256 {
+   fn from(v:u64) -> Self {
+      let mut ret = UInt256::new_null();
+      for i in 0..8 {
+         ret.data[i] = (v >> (i * 8)) as u8;
+      }
+      ret
+   }
+}
+
+// arithmetics modulo 2^256, same as arith_uint256
+macro_rules! impl_uint256_op {
+   ($tr:ident, $f:ident, |$a:ident, $b:ident| $e:expr) => {
+      impl std::ops::$tr<UInt256> for UInt256 {
+         type Output = UInt256;
+         fn $f(self, rhs:UInt256) -> UInt256 { std::ops::$tr::$f(&self, &rhs) }
+      }
+      impl<'a> std::ops::$tr<&'a UInt256> for &'a UInt256 {
+         type Output = UInt256;
+         fn $f(self, rhs:&'a UInt256) -> UInt256 {
+            let ($a, $b) = (self.to_biguint(), rhs.to_biguint());
+            UInt256::from_biguint(&$e)
+         }
+      }
+   }
+}
+impl_uint256_op!(Add, add, |a, b| a + b);
+impl_uint256_op!(Sub, sub, |a, b| (a + (num::bigint::BigUint::from(1u32) << 256)) - b);
+impl_uint256_op!(Mul, mul, |a, b| a * b);
+impl_uint256_op!(Div, div, |a, b| a / b);
+
+impl std::ops::Shl<usize> for UInt256 {
+   type Output = UInt256;
+   fn shl(self, n:usize) -> UInt256 { UInt256::from_biguint(&(self.to_biguint() << n)) }
+}
+impl std::ops::Shr<usize> for UInt256 {
+   type Output = UInt256;
+   fn shr(self, n:usize) -> UInt256 { UInt256::from_biguint(&(self.to_biguint() >> n)) }
+}
+
 impl std::fmt::Display for UInt256 {
    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
       match crate::ui::bitcoin::serialize(self, &()).map(|b| crate::utils::b2h_rev(b)) {
@@ -111,3 +211,72 @@ fn test_deserialize() {
    assert_eq!(&v.data[..], &v.data[..]);
 }
 
+
+#[test]
+fn test_arith() {
+   use std::cmp::Ordering;
+   let a = UInt256::from(0x100u64);
+   let b = UInt256::from(0x1u64);
+   assert_eq!(a.cmp_as_uint(&b), Ordering::Greater);
+   assert_eq!(&a + &b, UInt256::from(0x101u64));
+   assert_eq!(&a - &b, UInt256::from(0xffu64));
+   assert_eq!(&a * &a, UInt256::from(0x10000u64));
+   assert_eq!(&a / &UInt256::from(0x3u64), UInt256::from(0x55u64));
+   assert_eq!(b.clone() << 8, a);
+   assert_eq!(a.clone() >> 8, b);
+   // wrapping
+   let max = &UInt256::new_null() - &b;
+   assert_eq!(max.data, [0xffu8; 32]);
+   assert_eq!(&max + &b, UInt256::new_null());
+   assert_eq!(b.clone() << 256, UInt256::new_null());
+   assert_eq!(max.bits(), 256);
+   assert_eq!(a.bits(), 9);
+}
+
+#[test]
+fn test_compact() {
+   // arith_uint256_tests.cpp
+   let (v, neg, of) = UInt256::from_compact(0x01123456);
+   assert_eq!((v.clone(), neg, of), (UInt256::from(0x12u64), false, false));
+   assert_eq!(v.to_compact(), 0x01120000);
+
+   // the sign bit is ignored if the shifted mantissa is zero
+   for bits in &[0x00123456u32, 0x01003456, 0x02000056, 0x03000000, 0x04000000, 0x00923456,
+                 0x01803456, 0x02800056, 0x03800000, 0x04800000] {
+      let (v, neg, of) = UInt256::from_compact(*bits);
+      assert_eq!((v.clone(), neg, of), (UInt256::new_null(), false, false));
+      assert_eq!(v.to_compact(), 0);
+   }
+
+   let (v, neg, of) = UInt256::from_compact(0x02008000);
+   assert_eq!((v.clone(), neg, of), (UInt256::from(0x80u64), false, false));
+   assert_eq!(v.to_compact(), 0x02008000);
+
+   let (v, neg, of) = UInt256::from_compact(0x01fedcba);
+   assert_eq!((v.clone(), neg, of), (UInt256::from(0x7eu64), true, false));
+   assert_eq!(v.to_compact(), 0x017e0000); // sign is not kept
+
+   let (v, neg, of) = UInt256::from_compact(0x04923456);
+   assert_eq!((v, neg, of), (UInt256::from(0x12345600u64), true, false));
+
+   let (v, neg, of) = UInt256::from_compact(0x04123456);
+   assert_eq!((v.clone(), neg, of), (UInt256::from(0x12345600u64), false, false));
+   assert_eq!(v.to_compact(), 0x04123456);
+
+   let (v, neg, of) = UInt256::from_compact(0x05009234);
+   assert_eq!((v.clone(), neg, of), (UInt256::from(0x92340000u64), false, false));
+   assert_eq!(v.to_compact(), 0x05009234);
+
+   let (v, neg, of) = UInt256::from_compact(0x20123456);
+   assert_eq!((v.clone(), neg, of), (UInt256::from(0x123456u64) << 232, false, false));
+   assert_eq!(v.to_compact(), 0x20123456);
+   // via BigUint
+   let t = num::bigint::BigUint::from(0x123456u32) << 232;
+   assert_eq!(v.to_biguint(), t);
+   assert_eq!(UInt256::from_biguint(&t).to_compact(), 0x20123456);
+
+   let (_, _, of) = UInt256::from_compact(0xff123456);
+   assert!(of);
+
+   assert_eq!(UInt256::new_null().to_compact(), 0);
+}
@@ -0,0 +1,91 @@
+#[derive(Debug,Default,Clone,PartialEq,Eq)]
+pub struct Witness {
+   pub stack: Vec<Vec<u8>>,
+}
+
+impl Witness {
+   pub fn new(stack: Vec<Vec<u8>>) -> Self {
+      Witness { stack: stack }
+   }
+   pub fn new_null() -> Self {
+      Witness { stack: Vec::new() }
+   }
+   pub fn set_null(&mut self) {
+      self.stack.clear();
+   }
+   pub fn is_null(&self) -> bool {
+      self.stack.is_empty()
+   }
+   pub fn len(&self) -> usize {
+      self.stack.len()
+   }
+   pub fn as_slice(&self) -> &[Vec<u8>] {
+      self.stack.as_slice()
+   }
+}
+
+impl std::fmt::Display for Witness {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "Witness(")?;
+      for (i, item) in self.stack.iter().enumerate() {
+         if 0 < i { write!(f, ",")?; }
+         for b in item.iter() { write!(f, "{:02x}", b)?; }
+      }
+      write!(f, ")")
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+   MAX_SIZE,
+};
+impl BitcoinSerializee for Witness {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += e.serialize_var_int(ws, self.stack.len() as u64)?;
+      for item in self.stack.iter() {
+         r += e.serialize_var_octets(ws, item.as_slice(), std::usize::MAX)?;
+      }
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for Witness {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      let size:usize = {
+         let mut size:u64 = 0;
+         r += d.deserialize_var_int(rs, &mut size)?;
+         size as usize
+      };
+      let mut stack = Vec::<Vec<u8>>::with_capacity(std::cmp::min(size, 1024));
+      for _i in 0..size {
+         let mut item = Vec::<u8>::new();
+         r += d.deserialize_var_octets(rs, &mut item, MAX_SIZE as usize)?;
+         stack.push(item);
+      }
+      self.stack = stack;
+      Ok(r)
+   }
+}
+
+#[test]
+fn test_witness() {
+   let w = Witness::new(vec![vec![0x01, 0x02], vec![], vec![0xff]]);
+   let b = crate::ui::bitcoin::serialize(&w, &()).unwrap();
+   assert_eq!(b.as_ref(), &[0x03u8, 0x02, 0x01, 0x02, 0x00, 0x01, 0xff]);
+
+   let mut w2 = Witness::default();
+   assert_matches!(crate::ui::bitcoin::deserialize(b.as_ref(), &(), &mut w2), Ok(7));
+   assert_eq!(w, w2);
+
+   // the item and the count over MAX_SIZE, the item of MAX_SIZE without the data
+   for h in &["01fe01000002", "fe01000002", "01fe00000002"] {
+      let b = crate::utils::h2b(*h).unwrap();
+      assert_matches!(crate::ui::bitcoin::deserialize(b.as_ref(), &(), &mut w2), Err(_));
+   }
+}
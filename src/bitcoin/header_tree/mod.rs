@@ -0,0 +1,2 @@
+pub mod tree;
+pub use self::tree::{HeaderTree, HeaderNode, TipUpdate, get_block_proof};
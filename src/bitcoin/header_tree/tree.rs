@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use std::cmp::Ordering;
+use crate::bitcoin::datatypes::{UInt256, BlockHeader, BlockLocator};
+use crate::bitcoin::chainparams::Consensus;
+use crate::bitcoin::validation::{HeaderChain, check_block_header, check_next_work_required};
+
+const MEDIAN_TIME_SPAN:usize = 11;
+
+/// Work of the block which has the bits, 2^256 / (target+1) (chain.cpp: GetBlockProof).
+pub fn get_block_proof(bits:u32) -> UInt256 {
+   let (target, negative, overflow) = UInt256::from_compact(bits);
+   if negative || overflow || target.is_null() {
+      return UInt256::new_null();
+   }
+   // 2^256 / (target+1) == ~target / (target+1) + 1
+   let one = UInt256::from(1u64);
+   let inv = &UInt256::new_null() - &(&target + &one);
+   &(&inv / &(&target + &one)) + &one
+}
+
+#[derive(Debug,Clone)]
+pub struct HeaderNode {
+   pub hash:       UInt256,
+   pub header:     BlockHeader,
+   pub height:     i32,
+   pub chain_work: UInt256,
+   prev: Option<usize>,
+}
+
+/// Change of the best chain. Hashes are listed from the old tip down to the fork point for disconnected,
+/// and from the fork point up to the new tip for connected.
+#[derive(Debug,Clone,PartialEq,Eq)]
+pub struct TipUpdate {
+   pub fork_point:   UInt256,
+   pub disconnected: Vec<UInt256>,
+   pub connected:    Vec<UInt256>,
+}
+impl TipUpdate {
+   pub fn is_reorg(&self) -> bool {
+      !self.disconnected.is_empty()
+   }
+}
+
+/// Tree of the block headers rooted at the genesis, which tracks the chain having the most work.
+pub struct HeaderTree {
+   consensus: Consensus,
+   nodes:     Vec<HeaderNode>,
+   index:     HashMap<UInt256, usize>,
+   active:    Vec<usize>, // nodes of the best chain, indexed by the height
+}
+
+/// The branch of the tree from the genesis to the tip.
+struct Branch<'a> {
+   tree: &'a HeaderTree,
+   tip:  usize,
+}
+impl <'a> HeaderChain for Branch<'a> {
+   fn get_header(&self, height:i32) -> Option<&BlockHeader> {
+      let tree = self.tree;
+      let mut i = self.tip;
+      loop {
+         let node = &tree.nodes[i];
+         if node.height < height {
+            return None;
+         }
+         if node.height == height {
+            return Some(&node.header);
+         }
+         if tree.is_active(i) {
+            return tree.active.get(height as usize).map(|&j| &tree.nodes[j].header);
+         }
+         match node.prev {
+            Some(p) => i = p,
+            None => return None,
+         }
+      }
+   }
+}
+
+impl HeaderTree {
+   pub fn new(genesis:BlockHeader, consensus:&Consensus) -> crate::Result<Self> {
+      let hash = genesis.get_hash()?;
+      let node = HeaderNode {
+         hash:       hash.clone(),
+         chain_work: get_block_proof(genesis.bits),
+         header:     genesis,
+         height:     0,
+         prev:       None,
+      };
+      let mut index = HashMap::new();
+      index.insert(hash, 0);
+      Ok(HeaderTree {
+         consensus: consensus.clone(),
+         nodes:     vec![node],
+         index:     index,
+         active:    vec![0],
+      })
+   }
+
+   fn is_active(&self, i:usize) -> bool {
+      self.active.get(self.nodes[i].height as usize) == Some(&i)
+   }
+
+   pub fn tip(&self) -> &HeaderNode {
+      &self.nodes[self.active[self.active.len() - 1]]
+   }
+   pub fn height(&self) -> i32 {
+      self.tip().height
+   }
+   /// number of the headers in the tree, including those of the stale branches
+   pub fn len(&self) -> usize {
+      self.nodes.len()
+   }
+   pub fn get(&self, hash:&UInt256) -> Option<&HeaderNode> {
+      self.index.get(hash).map(|&i| &self.nodes[i])
+   }
+   pub fn get_prev(&self, node:&HeaderNode) -> Option<&HeaderNode> {
+      node.prev.map(|i| &self.nodes[i])
+   }
+   /// the header at the height in the best chain
+   pub fn get_by_height(&self, height:i32) -> Option<&HeaderNode> {
+      if height < 0 { return None; }
+      self.active.get(height as usize).map(|&i| &self.nodes[i])
+   }
+   pub fn is_in_best_chain(&self, hash:&UInt256) -> bool {
+      self.index.get(hash).map_or(false, |&i| self.is_active(i))
+   }
+
+   fn get_median_time_past(&self, mut i:usize) -> u32 {
+      let mut times = Vec::<u32>::with_capacity(MEDIAN_TIME_SPAN);
+      loop {
+         times.push(self.nodes[i].header.time);
+         match self.nodes[i].prev {
+            Some(p) if times.len() < MEDIAN_TIME_SPAN => i = p,
+            _ => break,
+         }
+      }
+      times.sort();
+      times[times.len() / 2]
+   }
+
+   /// Adds the header after checking the linkage, the proof of work, the difficulty and the timestamp.
+   /// Returns the change of the best chain if any. Already known header is ignored.
+   pub fn accept(&mut self, header:BlockHeader) -> crate::Result<Option<TipUpdate>> {
+      let old_tip = self.active[self.active.len() - 1];
+      match self.insert(header)? {
+         Some(i) => Ok(self.update_tip(old_tip, i)),
+         None    => Ok(None),
+      }
+   }
+
+   /// Adds the headers in order, such as those of a headers message.
+   /// The headers before an invalid one are kept even if an error is returned.
+   pub fn accept_all(&mut self, headers:&[BlockHeader]) -> crate::Result<Option<TipUpdate>> {
+      let old_tip = self.active[self.active.len() - 1];
+      let mut best = old_tip;
+      let mut err = None;
+      for header in headers.iter() {
+         match self.insert(header.clone()) {
+            Ok(Some(i)) => {
+               if self.nodes[i].chain_work.cmp_as_uint(&self.nodes[best].chain_work) == Ordering::Greater {
+                  best = i;
+               }
+            },
+            Ok(None) => (),
+            Err(e) => {
+               err = Some(e);
+               break;
+            },
+         }
+      }
+      let update = self.update_tip(old_tip, best);
+      match err {
+         Some(e) => Err(e),
+         None    => Ok(update),
+      }
+   }
+
+   fn insert(&mut self, header:BlockHeader) -> crate::Result<Option<usize>> {
+      let hash = header.get_hash()?;
+      if self.index.contains_key(&hash) {
+         return Ok(None);
+      }
+      let prev = match self.index.get(&header.hash_prev_block) {
+         Some(&p) => p,
+         None => raise_validation_error!(BlockPrevNotFound, format!("{}", header.hash_prev_block)),
+      };
+      let height = self.nodes[prev].height + 1;
+
+      check_block_header(&header, &self.consensus, true)?;
+      check_next_work_required(&header, &Branch { tree:self, tip:prev }, height, &self.consensus)?;
+      if header.time <= self.get_median_time_past(prev) {
+         raise_validation_error!(BlockTimeTooOld);
+      }
+
+      let node = HeaderNode {
+         hash:       hash.clone(),
+         chain_work: &self.nodes[prev].chain_work + &get_block_proof(header.bits),
+         header:     header,
+         height:     height,
+         prev:       Some(prev),
+      };
+      let i = self.nodes.len();
+      self.nodes.push(node);
+      self.index.insert(hash, i);
+      Ok(Some(i))
+   }
+
+   fn update_tip(&mut self, old_tip:usize, candidate:usize) -> Option<TipUpdate> {
+      if self.nodes[candidate].chain_work.cmp_as_uint(&self.nodes[old_tip].chain_work) != Ordering::Greater {
+         return None;
+      }
+      let mut connected = Vec::<usize>::new();
+      let mut i = candidate;
+      while !self.is_active(i) {
+         connected.push(i);
+         i = self.nodes[i].prev.unwrap(); // genesis is always active
+      }
+      let fork = i;
+      let fork_height = self.nodes[fork].height as usize;
+      let nodes = &self.nodes;
+      let disconnected:Vec<UInt256> = self.active.drain((fork_height + 1)..).rev().map(|j| nodes[j].hash.clone()).collect();
+      connected.reverse();
+      self.active.extend(connected.iter().cloned());
+      Some(TipUpdate {
+         fork_point:   self.nodes[fork].hash.clone(),
+         disconnected: disconnected,
+         connected:    connected.into_iter().map(|j| self.nodes[j].hash.clone()).collect(),
+      })
+   }
+
+   /// Locator of the best chain for getheaders: the recent 10 blocks and then exponentially sparse ones to the genesis (chain.cpp: CChain::GetLocator).
+   pub fn get_locator(&self) -> BlockLocator {
+      let mut haves = Vec::<UInt256>::with_capacity(32);
+      let mut step = 1;
+      let mut height = self.height();
+      loop {
+         haves.push(self.nodes[self.active[height as usize]].hash.clone());
+         if height == 0 {
+            break;
+         }
+         height = std::cmp::max(height - step, 0);
+         if 10 < haves.len() {
+            step *= 2;
+         }
+      }
+      BlockLocator { haves: haves }
+   }
+
+   /// The last block in the best chain which is in the locator (validation.cpp: FindForkInGlobalIndex).
+   pub fn find_fork(&self, locator:&BlockLocator) -> &HeaderNode {
+      locator.haves.iter()
+         .filter_map(|h| self.index.get(h))
+         .find(|&&i| self.is_active(i))
+         .map_or(&self.nodes[self.active[0]], |&i| &self.nodes[i])
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::validation::ValidationErrorCode as C;
+
+   fn consensus() -> &'static Consensus {
+      &crate::bitcoin::presets::bitcoin_regtest::CHAIN.consensus
+   }
+
+   fn genesis() -> BlockHeader {
+      BlockHeader {
+         version: 1,
+         hash_prev_block: UInt256::new_null(),
+         hash_merkle_root: crate::ui::bitcoin::hex_to_uint256("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b").unwrap(),
+         time: 1296688602,
+         bits: 0x207fffff,
+         nonce: 2,
+      }
+   }
+
+   fn mine(prev:&BlockHeader, tag:u8) -> BlockHeader {
+      let mut h = BlockHeader {
+         version: 1,
+         hash_prev_block: prev.get_hash().unwrap(),
+         hash_merkle_root: UInt256::new(&[tag;32]),
+         time: prev.time + 600,
+         bits: prev.bits,
+         nonce: 0,
+      };
+      while crate::bitcoin::validation::check_proof_of_work(&h.get_hash().unwrap(), h.bits, consensus()).is_err() {
+         h.nonce += 1;
+      }
+      h
+   }
+
+   fn mine_chain(prev:&BlockHeader, tag:u8, n:usize) -> Vec<BlockHeader> {
+      let mut v = Vec::<BlockHeader>::with_capacity(n);
+      for _ in 0..n {
+         let h = mine(v.last().unwrap_or(prev), tag);
+         v.push(h);
+      }
+      v
+   }
+
+   fn hashes(headers:&[BlockHeader]) -> Vec<UInt256> {
+      headers.iter().map(|h| h.get_hash().unwrap()).collect()
+   }
+
+   #[test]
+   fn test_get_block_proof() {
+      assert_eq!(get_block_proof(0x1d00ffff), UInt256::from(0x100010001u64));
+      assert_eq!(get_block_proof(0x207fffff), UInt256::from(2u64));
+      assert_eq!(get_block_proof(0), UInt256::new_null());
+   }
+
+   #[test]
+   fn test_accept() {
+      let mut tree = HeaderTree::new(genesis(), consensus()).unwrap();
+      assert_eq!(tree.tip().hash, consensus().hash_genesis_block);
+
+      let a = mine_chain(&genesis(), 0xa, 3);
+      let r = tree.accept(a[0].clone()).unwrap().unwrap();
+      assert_eq!(r, TipUpdate { fork_point:consensus().hash_genesis_block.clone(), disconnected:vec![], connected:hashes(&a[0..1]) });
+      let r = tree.accept_all(&a[1..]).unwrap().unwrap();
+      assert!(!r.is_reorg());
+      assert_eq!(r.connected, hashes(&a[1..]));
+      assert_eq!(tree.height(), 3);
+      assert_eq!(tree.tip().chain_work, UInt256::from(8u64));
+      assert_matches!(tree.accept(a[2].clone()), Ok(None));
+
+      // a fork from a[0] having the same work doesn't change the tip
+      let b = mine_chain(&a[0], 0xb, 3);
+      assert_matches!(tree.accept_all(&b[0..2]), Ok(None));
+      assert_eq!(tree.len(), 6);
+      assert_eq!(tree.tip().hash, a[2].get_hash().unwrap());
+      assert!(!tree.is_in_best_chain(&b[1].get_hash().unwrap()));
+
+      let r = tree.accept(b[2].clone()).unwrap().unwrap();
+      assert!(r.is_reorg());
+      assert_eq!(r.fork_point, a[0].get_hash().unwrap());
+      assert_eq!(r.disconnected, vec![a[2].get_hash().unwrap(), a[1].get_hash().unwrap()]);
+      assert_eq!(r.connected, hashes(&b[..]));
+      assert_eq!(tree.height(), 4);
+      assert_eq!(tree.get_by_height(2).unwrap().hash, b[0].get_hash().unwrap());
+      assert!(!tree.is_in_best_chain(&a[1].get_hash().unwrap()));
+      let tip = tree.tip();
+      assert_eq!(tree.get_prev(tip).unwrap().hash, b[1].get_hash().unwrap());
+   }
+
+   #[test]
+   fn test_accept_invalid() {
+      let mut tree = HeaderTree::new(genesis(), consensus()).unwrap();
+      let a = mine_chain(&genesis(), 0xa, 2);
+
+      let is_error = |r:crate::Result<Option<TipUpdate>>, code:C| match r {
+         Err(crate::Error::BitcoinValidation(ref e)) => e.is(code),
+         _ => false,
+      };
+      assert!(is_error(tree.accept(a[1].clone()), C::BlockPrevNotFound));
+
+      let mut h = a[0].clone();
+      h.bits = 0x1d00ffff;
+      assert!(is_error(tree.accept(h), C::BlockHighHash));
+
+      let mut h = a[0].clone();
+      h.time = genesis().time;
+      while crate::bitcoin::validation::check_proof_of_work(&h.get_hash().unwrap(), h.bits, consensus()).is_err() {
+         h.nonce += 1;
+      }
+      assert!(is_error(tree.accept(h), C::BlockTimeTooOld));
+
+      // a[0] is kept
+      let mut b = a.clone();
+      b[1].nonce += 1;
+      assert_matches!(tree.accept_all(&b[..]), Err(_));
+      assert_eq!(tree.height(), 1);
+      assert_eq!(tree.len(), 2);
+   }
+
+   #[test]
+   fn test_get_locator() {
+      let mut tree = HeaderTree::new(genesis(), consensus()).unwrap();
+      let a = mine_chain(&genesis(), 0xa, 30);
+      let _ = tree.accept_all(&a[..]).unwrap();
+
+      let locator = tree.get_locator();
+      let expect:Vec<UInt256> = [30,29,28,27,26,25,24,23,22,21,20,19,17,13,5,0].iter()
+         .map(|&h| tree.get_by_height(h).unwrap().hash.clone()).collect();
+      assert_eq!(locator.haves, expect);
+
+      assert_eq!(tree.find_fork(&locator).height, 30);
+      let locator = BlockLocator { haves: vec![UInt256::new(&[1u8;32]), a[9].get_hash().unwrap()] };
+      assert_eq!(tree.find_fork(&locator).height, 10);
+      assert_eq!(tree.find_fork(&BlockLocator::default()).height, 0);
+   }
+}
@@ -6,7 +6,7 @@ pub mod serialize;
 pub mod datatypes;
 pub use self::datatypes::{
    UInt256, Script,
-   TxOutPoint, TxIn, TxOut, Tx, LockTime,
+   TxOutPoint, TxIn, TxOut, Tx, LockTime, Witness, Amount,
    BlockHeader, PartialMerkleTree, MerkleBlock, Block, BlockLocator,
 };
 
@@ -20,8 +20,34 @@ pub mod protocol;
 #[macro_use]
 pub mod script;
 
+#[macro_use]
+pub mod validation;
+
+pub mod policy;
+
+pub mod block_filter;
+pub use self::block_filter::{GcsFilter, BlockFilter};
+
+pub mod header_tree;
+pub use self::header_tree::HeaderTree;
+
 pub mod p2pkh;
 pub use self::p2pkh::P2PKH;
 
+pub mod p2sh;
+pub use self::p2sh::P2SH;
+
+pub mod p2wpkh;
+pub use self::p2wpkh::P2WPKH;
+
+pub mod p2wsh;
+pub use self::p2wsh::P2WSH;
+
+pub mod p2tr;
+pub use self::p2tr::P2TR;
+
+pub mod multisig;
+pub use self::multisig::Multisig;
+
 
 
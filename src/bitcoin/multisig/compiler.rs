@@ -0,0 +1,98 @@
+use crate::bitcoin::script::opcode::*;
+use crate::bitcoin::script::solver::is_valid_pubkey_size;
+use super::Multisig;
+
+pub struct Compiler();
+
+impl Compiler {
+   pub fn compile(multisig: &Multisig) -> Box<[u8]> {
+      let mut pk_script = Vec::with_capacity(3 + multisig.pubkeys().len() * 66);
+      pk_script.push(OP_1 + (multisig.required() as u8) - 1);
+      for pk in multisig.pubkeys().iter() {
+         pk_script.push(pk.len() as u8);
+         pk_script.extend_from_slice(pk);
+      }
+      pk_script.push(OP_1 + (multisig.pubkeys().len() as u8) - 1);
+      pk_script.push(OP_CHECKMULTISIG);
+      pk_script.into_boxed_slice()
+   }
+}
+
+pub struct Parser();
+
+impl Parser {
+   pub fn check(script: &[u8]) -> crate::Result<()> {
+      let _ = Self::parse(script)?;
+      Ok(())
+   }
+
+   pub fn parse(script: &[u8]) -> crate::Result<Multisig> {
+      let len = script.len();
+      if len < 3 || script[len-1] != OP_CHECKMULTISIG {
+         raise_parse_script_error!("not a multisig pkScript: script mismatch");
+      }
+      let small_int = |op:u8| -> crate::Result<usize> {
+         if op < OP_1 || OP_16 < op {
+            raise_parse_script_error!(format!("not a multisig pkScript: not a small integer: {:x}", op));
+         }
+         Ok((op - OP_1 + 1) as usize)
+      };
+      let required = small_int(script[0])?;
+      let n = small_int(script[len-2])?;
+      let mut pubkeys = Vec::<Box<[u8]>>::with_capacity(n);
+      let mut pc = 1usize;
+      while pc < len - 2 {
+         let size = script[pc] as usize;
+         if (size != 33 && size != 65) || len - 2 < pc + 1 + size || !is_valid_pubkey_size(&script[pc+1 .. pc+1+size]) {
+            raise_parse_script_error!(format!("not a multisig pkScript: invalid pubkey at {}", pc));
+         }
+         pubkeys.push(script[pc+1 .. pc+1+size].to_vec().into_boxed_slice());
+         pc += 1 + size;
+      }
+      if pubkeys.len() != n || n < required {
+         raise_parse_script_error!(format!("not a multisig pkScript: {} of {} but {} keys", required, n, pubkeys.len()));
+      }
+      Multisig::new(required, pubkeys)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::utils::h2b;
+   lazy_static! {
+      static ref PK1:Box<[u8]> = h2b("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+      static ref PK2:Box<[u8]> = h2b("04c6047f9441ed7d6d3045406e95c07cd85c778e4b8cef3ca7abac09b95c709ee51ae168fea63dc339a3c58419466ceaeef7f632653266d0e1236431a950cfe52a").unwrap();
+   }
+
+   #[test]
+   fn test_compile_parse() {
+      let m = crate::bitcoin::multisig::Multisig::new(1, vec![PK1.clone(), PK2.clone()]).unwrap();
+      let pk_script = crate::bitcoin::multisig::Compiler::compile(&m);
+      assert_eq!(pk_script.len(), 1 + 34 + 66 + 2);
+      assert_eq!(pk_script[0], 0x51);
+      assert_eq!(&pk_script[pk_script.len()-2..], &[0x52, 0xae]);
+
+      let parsed = crate::bitcoin::multisig::Parser::parse(pk_script.as_ref());
+      assert_matches!(parsed, Ok(_));
+      let parsed = parsed.unwrap();
+      assert_eq!(parsed.required(), 1);
+      assert_eq!(parsed.pubkeys(), m.pubkeys());
+
+      let mut bad = pk_script.to_vec();
+      bad[0] = 0x53; // 3 of 2
+      assert_matches!(crate::bitcoin::multisig::Parser::parse(&bad[..]), Err(_));
+      assert_matches!(crate::bitcoin::multisig::Parser::parse(&pk_script[1..]), Err(_));
+      assert_matches!(crate::bitcoin::multisig::Multisig::new(0, vec![PK1.clone()]), Err(_));
+
+      // only 0x02 and 0x03 for 33 bytes, 0x04, 0x06 and 0x07 for 65 bytes
+      let mut bad = pk_script.to_vec();
+      bad[2] = 0x05;
+      assert_matches!(crate::bitcoin::multisig::Parser::parse(&bad[..]), Err(_));
+      let mut bad = pk_script.to_vec();
+      bad[36] = 0x03;
+      assert_matches!(crate::bitcoin::multisig::Parser::parse(&bad[..]), Err(_));
+      let mut pk = PK1.to_vec();
+      pk[0] = 0x04;
+      assert_matches!(crate::bitcoin::multisig::Multisig::new(1, vec![pk.into_boxed_slice()]), Err(_));
+   }
+}
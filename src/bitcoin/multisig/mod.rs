@@ -0,0 +1,5 @@
+pub mod multisig;
+pub use self::multisig::Multisig;
+
+pub mod compiler;
+pub use self::compiler::{Compiler, Parser};
@@ -0,0 +1,27 @@
+use crate::bitcoin::script::solver::is_valid_pubkey_size;
+
+pub const MAX_PUBKEYS_PER_MULTISIG:usize = 16;
+
+/// bare m-of-n multisig. The pubkeys are not validated other than their sizes and prefixes.
+#[derive(Debug,Default,Clone)]
+pub struct Multisig {
+   required: usize,
+   pubkeys:  Vec<Box<[u8]>>,
+}
+
+impl Multisig {
+   pub fn new(required: usize, pubkeys: Vec<Box<[u8]>>) -> crate::Result<Self> {
+      if pubkeys.is_empty() || MAX_PUBKEYS_PER_MULTISIG < pubkeys.len() {
+         raise_script_error!(format!("invalid number of pubkeys: {}", pubkeys.len()));
+      }
+      if required < 1 || pubkeys.len() < required {
+         raise_script_error!(format!("invalid number of required sigs: {}", required));
+      }
+      if let Some(pk) = pubkeys.iter().find(|pk| !is_valid_pubkey_size(pk)) {
+         raise_script_error!(format!("invalid pubkey: size={}", pk.len()));
+      }
+      Ok(Self { required: required, pubkeys: pubkeys })
+   }
+   pub fn required(&self) -> usize { self.required }
+   pub fn pubkeys(&self) -> &[Box<[u8]>] { &self.pubkeys[..] }
+}
@@ -0,0 +1,67 @@
+use crate::bitcoin::script::opcode::*;
+use super::P2SH;
+
+pub struct Compiler();
+
+impl Compiler {
+   pub fn compile_to(p2sh: &P2SH, pk_script: &mut [u8; 23]) {
+      pk_script[0] = OP_HASH160;
+      pk_script[1] = OP_PUSHDATAFIX_14;
+      pk_script[2..22].clone_from_slice(p2sh.script_hash());
+      pk_script[22] = OP_EQUAL;
+   }
+   pub fn compile(p2sh: &P2SH) -> Box<[u8]> {
+      let mut pk_script = [0u8; 23];
+      Self::compile_to(p2sh, &mut pk_script);
+      Box::new(pk_script)
+   }
+}
+
+pub struct Parser();
+
+impl Parser {
+   pub fn check(script: &[u8]) -> crate::Result<()> {
+      if script.len() != 23 {
+         raise_parse_script_error!(format!("length mismatch: {}", script.len()));
+      }
+      
+      if script[0] != OP_HASH160 ||
+         script[1] != OP_PUSHDATAFIX_14 ||
+         script[22] != OP_EQUAL
+      {
+         raise_parse_script_error!(format!("script mismatch"));
+      }
+      Ok(())
+   }
+
+   pub fn parse(script: &[u8]) -> crate::Result<P2SH> {
+      if let Err(e) = Self::check(script) {
+         raise_parse_script_error!(format!("not a p2sh pkScript: {}", e));
+      }
+      P2SH::new_with_script_hash(&script[2..22])
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::utils::h2b;
+   lazy_static! {
+      static ref HASH:Box<[u8]> = h2b("1018853670f9f3b0582c5b9ee8ce93764ac32b93").unwrap();
+      static ref PK_SCRIPT:Box<[u8]> = h2b("a9141018853670f9f3b0582c5b9ee8ce93764ac32b9387").unwrap();
+   }
+
+   #[test]
+   fn test_compile() {
+      let p2sh = crate::bitcoin::p2sh::P2SH::new_with_script_hash(HASH.as_ref()).unwrap();
+      let pk_script = crate::bitcoin::p2sh::Compiler::compile(&p2sh);
+      assert_eq!(pk_script.as_ref(), PK_SCRIPT.as_ref());
+   }
+
+   #[test]
+   fn test_parse() {
+      let p2sh = crate::bitcoin::p2sh::Parser::parse(PK_SCRIPT.as_ref());
+      assert_matches!(p2sh, Ok(_));
+      assert_eq!(p2sh.unwrap().script_hash(), HASH.as_ref());
+      assert_matches!(crate::bitcoin::p2sh::Parser::parse(&PK_SCRIPT[1..]), Err(_));
+   }
+}
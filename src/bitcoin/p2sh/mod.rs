@@ -0,0 +1,5 @@
+pub mod p2sh;
+pub use self::p2sh::P2SH;
+
+pub mod compiler;
+pub use self::compiler::{Compiler, Parser};
@@ -0,0 +1,24 @@
+#[derive(Debug,Default,Clone)]
+pub struct P2SH {
+   hash: [u8; 20],
+}
+
+impl P2SH {
+   pub fn new(hash: [u8;20]) -> Self {
+      Self { hash: hash }
+   }
+   pub fn new_with_script_hash(hash: &[u8]) -> crate::Result<Self> {
+      if hash.len() != 20 {
+         raise_script_error!("not a 20");
+      }
+      let mut h = [0u8; 20];
+      h.clone_from_slice(hash);
+      Ok(Self::new(h))
+   }
+   /// pays to the hash of the redeem script
+   pub fn new_with_script(script: &[u8]) -> Self {
+      let hash = crate::ui::create_hash160().u8_to_u8(script);
+      Self::new_with_script_hash(&hash[..]).unwrap()
+   }
+   pub fn script_hash(&self) -> &[u8;20] { &self.hash }
+}
@@ -0,0 +1,65 @@
+use crate::bitcoin::script::opcode::*;
+use super::P2TR;
+
+pub struct Compiler();
+
+impl Compiler {
+   pub fn compile_to(p2tr: &P2TR, pk_script: &mut [u8; 34]) {
+      pk_script[0] = OP_1;
+      pk_script[1] = OP_PUSHDATAFIX_20;
+      pk_script[2..34].clone_from_slice(p2tr.output_key());
+   }
+   pub fn compile(p2tr: &P2TR) -> Box<[u8]> {
+      let mut pk_script = [0u8; 34];
+      Self::compile_to(p2tr, &mut pk_script);
+      Box::new(pk_script)
+   }
+}
+
+pub struct Parser();
+
+impl Parser {
+   pub fn check(script: &[u8]) -> crate::Result<()> {
+      if script.len() != 34 {
+         raise_parse_script_error!(format!("length mismatch: {}", script.len()));
+      }
+      
+      if script[0] != OP_1 ||
+         script[1] != OP_PUSHDATAFIX_20
+      {
+         raise_parse_script_error!(format!("script mismatch"));
+      }
+      Ok(())
+   }
+
+   pub fn parse(script: &[u8]) -> crate::Result<P2TR> {
+      if let Err(e) = Self::check(script) {
+         raise_parse_script_error!(format!("not a p2tr pkScript: {}", e));
+      }
+      P2TR::new_with_output_key(&script[2..34])
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::utils::h2b;
+   lazy_static! {
+      static ref HASH:Box<[u8]> = h2b("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+      static ref PK_SCRIPT:Box<[u8]> = h2b("512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+   }
+
+   #[test]
+   fn test_compile() {
+      let p2tr = crate::bitcoin::p2tr::P2TR::new_with_output_key(HASH.as_ref()).unwrap();
+      let pk_script = crate::bitcoin::p2tr::Compiler::compile(&p2tr);
+      assert_eq!(pk_script.as_ref(), PK_SCRIPT.as_ref());
+   }
+
+   #[test]
+   fn test_parse() {
+      let p2tr = crate::bitcoin::p2tr::Parser::parse(PK_SCRIPT.as_ref());
+      assert_matches!(p2tr, Ok(_));
+      assert_eq!(p2tr.unwrap().output_key(), HASH.as_ref());
+      assert_matches!(crate::bitcoin::p2tr::Parser::parse(&PK_SCRIPT[1..]), Err(_));
+   }
+}
@@ -0,0 +1,5 @@
+pub mod p2tr;
+pub use self::p2tr::P2TR;
+
+pub mod compiler;
+pub use self::compiler::{Compiler, Parser};
@@ -0,0 +1,19 @@
+#[derive(Debug,Default,Clone)]
+pub struct P2TR {
+   output_key: [u8; 32],
+}
+
+impl P2TR {
+   pub fn new(output_key: [u8;32]) -> Self {
+      Self { output_key: output_key }
+   }
+   pub fn new_with_output_key(output_key: &[u8]) -> crate::Result<Self> {
+      if output_key.len() != 32 {
+         raise_script_error!("not a 32");
+      }
+      let mut h = [0u8; 32];
+      h.clone_from_slice(output_key);
+      Ok(Self::new(h))
+   }
+   pub fn output_key(&self) -> &[u8;32] { &self.output_key }
+}
@@ -0,0 +1,65 @@
+use crate::bitcoin::script::opcode::*;
+use super::P2WPKH;
+
+pub struct Compiler();
+
+impl Compiler {
+   pub fn compile_to(p2wpkh: &P2WPKH, pk_script: &mut [u8; 22]) {
+      pk_script[0] = OP_0;
+      pk_script[1] = OP_PUSHDATAFIX_14;
+      pk_script[2..22].clone_from_slice(p2wpkh.pkh());
+   }
+   pub fn compile(p2wpkh: &P2WPKH) -> Box<[u8]> {
+      let mut pk_script = [0u8; 22];
+      Self::compile_to(p2wpkh, &mut pk_script);
+      Box::new(pk_script)
+   }
+}
+
+pub struct Parser();
+
+impl Parser {
+   pub fn check(script: &[u8]) -> crate::Result<()> {
+      if script.len() != 22 {
+         raise_parse_script_error!(format!("length mismatch: {}", script.len()));
+      }
+      
+      if script[0] != OP_0 ||
+         script[1] != OP_PUSHDATAFIX_14
+      {
+         raise_parse_script_error!(format!("script mismatch"));
+      }
+      Ok(())
+   }
+
+   pub fn parse(script: &[u8]) -> crate::Result<P2WPKH> {
+      if let Err(e) = Self::check(script) {
+         raise_parse_script_error!(format!("not a p2wpkh pkScript: {}", e));
+      }
+      P2WPKH::new_with_pkh(&script[2..22])
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::utils::h2b;
+   lazy_static! {
+      static ref HASH:Box<[u8]> = h2b("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+      static ref PK_SCRIPT:Box<[u8]> = h2b("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+   }
+
+   #[test]
+   fn test_compile() {
+      let p2wpkh = crate::bitcoin::p2wpkh::P2WPKH::new_with_pkh(HASH.as_ref()).unwrap();
+      let pk_script = crate::bitcoin::p2wpkh::Compiler::compile(&p2wpkh);
+      assert_eq!(pk_script.as_ref(), PK_SCRIPT.as_ref());
+   }
+
+   #[test]
+   fn test_parse() {
+      let p2wpkh = crate::bitcoin::p2wpkh::Parser::parse(PK_SCRIPT.as_ref());
+      assert_matches!(p2wpkh, Ok(_));
+      assert_eq!(p2wpkh.unwrap().pkh(), HASH.as_ref());
+      assert_matches!(crate::bitcoin::p2wpkh::Parser::parse(&PK_SCRIPT[1..]), Err(_));
+   }
+}
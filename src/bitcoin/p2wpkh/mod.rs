@@ -0,0 +1,5 @@
+pub mod p2wpkh;
+pub use self::p2wpkh::P2WPKH;
+
+pub mod compiler;
+pub use self::compiler::{Compiler, Parser};
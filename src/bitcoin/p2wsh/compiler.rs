@@ -0,0 +1,65 @@
+use crate::bitcoin::script::opcode::*;
+use super::P2WSH;
+
+pub struct Compiler();
+
+impl Compiler {
+   pub fn compile_to(p2wsh: &P2WSH, pk_script: &mut [u8; 34]) {
+      pk_script[0] = OP_0;
+      pk_script[1] = OP_PUSHDATAFIX_20;
+      pk_script[2..34].clone_from_slice(p2wsh.script_hash());
+   }
+   pub fn compile(p2wsh: &P2WSH) -> Box<[u8]> {
+      let mut pk_script = [0u8; 34];
+      Self::compile_to(p2wsh, &mut pk_script);
+      Box::new(pk_script)
+   }
+}
+
+pub struct Parser();
+
+impl Parser {
+   pub fn check(script: &[u8]) -> crate::Result<()> {
+      if script.len() != 34 {
+         raise_parse_script_error!(format!("length mismatch: {}", script.len()));
+      }
+      
+      if script[0] != OP_0 ||
+         script[1] != OP_PUSHDATAFIX_20
+      {
+         raise_parse_script_error!(format!("script mismatch"));
+      }
+      Ok(())
+   }
+
+   pub fn parse(script: &[u8]) -> crate::Result<P2WSH> {
+      if let Err(e) = Self::check(script) {
+         raise_parse_script_error!(format!("not a p2wsh pkScript: {}", e));
+      }
+      P2WSH::new_with_script_hash(&script[2..34])
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use crate::utils::h2b;
+   lazy_static! {
+      static ref HASH:Box<[u8]> = h2b("1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262").unwrap();
+      static ref PK_SCRIPT:Box<[u8]> = h2b("00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262").unwrap();
+   }
+
+   #[test]
+   fn test_compile() {
+      let p2wsh = crate::bitcoin::p2wsh::P2WSH::new_with_script_hash(HASH.as_ref()).unwrap();
+      let pk_script = crate::bitcoin::p2wsh::Compiler::compile(&p2wsh);
+      assert_eq!(pk_script.as_ref(), PK_SCRIPT.as_ref());
+   }
+
+   #[test]
+   fn test_parse() {
+      let p2wsh = crate::bitcoin::p2wsh::Parser::parse(PK_SCRIPT.as_ref());
+      assert_matches!(p2wsh, Ok(_));
+      assert_eq!(p2wsh.unwrap().script_hash(), HASH.as_ref());
+      assert_matches!(crate::bitcoin::p2wsh::Parser::parse(&PK_SCRIPT[1..]), Err(_));
+   }
+}
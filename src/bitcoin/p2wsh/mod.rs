@@ -0,0 +1,5 @@
+pub mod p2wsh;
+pub use self::p2wsh::P2WSH;
+
+pub mod compiler;
+pub use self::compiler::{Compiler, Parser};
@@ -0,0 +1,24 @@
+#[derive(Debug,Default,Clone)]
+pub struct P2WSH {
+   hash: [u8; 32],
+}
+
+impl P2WSH {
+   pub fn new(hash: [u8;32]) -> Self {
+      Self { hash: hash }
+   }
+   pub fn new_with_script_hash(hash: &[u8]) -> crate::Result<Self> {
+      if hash.len() != 32 {
+         raise_script_error!("not a 32");
+      }
+      let mut h = [0u8; 32];
+      h.clone_from_slice(hash);
+      Ok(Self::new(h))
+   }
+   /// pays to the hash of the witness script
+   pub fn new_with_script(script: &[u8]) -> Self {
+      let hash = crate::ui::create_sha256().u8_to_u8(script);
+      Self::new_with_script_hash(&hash[..]).unwrap()
+   }
+   pub fn script_hash(&self) -> &[u8;32] { &self.hash }
+}
@@ -0,0 +1,40 @@
+use crate::bitcoin::datatypes::FeeRatePerKvB;
+use crate::bitcoin::script::flags::ScriptVerify;
+
+// policy/policy.h
+pub const MAX_STANDARD_TX_WEIGHT:usize = 400000;
+pub const MIN_STANDARD_TX_NONWITNESS_SIZE:usize = 65;
+pub const MAX_P2SH_SIGOPS:usize = 15;
+pub const MAX_STANDARD_TX_SIGOPS_COST:usize = super::super::validation::apriori::MAX_BLOCK_SIGOPS_COST / 5;
+pub const MAX_STANDARD_SCRIPTSIG_SIZE:usize = 1650;
+pub const MAX_STANDARD_MULTISIG_PUBKEYS:usize = 3;
+pub const TX_MIN_STANDARD_VERSION:i32 = 1;
+pub const TX_MAX_STANDARD_VERSION:i32 = 2;
+pub const DEFAULT_PERMIT_BAREMULTISIG:bool = true;
+pub const DUST_RELAY_TX_FEE:FeeRatePerKvB = FeeRatePerKvB(3000);
+// policy/policy.h: MAX_OP_RETURN_RELAY, the whole scriptPubKey including OP_RETURN
+pub const MAX_OP_RETURN_RELAY:usize = 83;
+
+/// STANDARD_SCRIPT_VERIFY_FLAGS of bitcoin core
+pub fn standard_script_verify_flags() -> ScriptVerify {
+   ScriptVerify::default()
+      .p2sh(true)
+      .der_sig(true)
+      .strict_enc(true)
+      .minimal_data(true)
+      .null_dummy(true)
+      .discourage_upgradable_nops(true)
+      .clean_stack(true)
+      .minimal_if(true)
+      .null_fail(true)
+      .check_locktime_verify(true)
+      .check_sequence_verify(true)
+      .low_s(true)
+      .witness(true)
+      .discourage_upgradable_witness_program(true)
+      .witness_pubkey_type(true)
+      .taproot(true)
+      .discourage_upgradable_taproot_version(true)
+      .discourage_op_success(true)
+      .discourage_upgradable_pubkey_type(true)
+}
@@ -0,0 +1,8 @@
+pub mod apriori;
+pub use self::apriori::standard_script_verify_flags;
+
+pub mod standard;
+pub use self::standard::{
+   is_standard, is_standard_tx, are_inputs_standard, check_sig_op_cost,
+   get_dust_threshold, is_dust,
+};
@@ -0,0 +1,299 @@
+use crate::bitcoin::datatypes::{Tx, TxOut, Amount, FeeRatePerKvB};
+use crate::bitcoin::script::{Solver, TxOutType};
+use crate::bitcoin::script::parser::Parser;
+use crate::bitcoin::script::opcode::OP_RETURN;
+use crate::bitcoin::script::apriori::MAX_SCRIPT_SIZE;
+use crate::bitcoin::validation::apriori::WITNESS_SCALE_FACTOR;
+use crate::bitcoin::validation::tx::{get_serialize_size, get_transaction_sig_op_cost};
+use super::apriori::{
+   MAX_STANDARD_TX_WEIGHT, MIN_STANDARD_TX_NONWITNESS_SIZE, MAX_STANDARD_SCRIPTSIG_SIZE, MAX_STANDARD_MULTISIG_PUBKEYS, MAX_P2SH_SIGOPS,
+   TX_MIN_STANDARD_VERSION, TX_MAX_STANDARD_VERSION, MAX_STANDARD_TX_SIGOPS_COST,
+   standard_script_verify_flags,
+};
+
+/// (script.h: CScript::IsUnspendable)
+fn is_unspendable(script:&[u8]) -> bool {
+   (0 < script.len() && script[0] == OP_RETURN) || MAX_SCRIPT_SIZE < script.len()
+}
+
+/// Returns the type of the scriptPubKey if it is standard (policy.cpp: IsStandard).
+/// The OP_RETURN outputs are not standard if max_datacarrier_bytes is None or their size exceeds it.
+pub fn is_standard(script:&[u8], max_datacarrier_bytes:Option<usize>) -> Option<TxOutType> {
+   let (t, solutions) = Solver::solve(script);
+   match t {
+      TxOutType::NonStandard => None,
+      TxOutType::Multisig => {
+         let m = solutions[0][0] as usize;
+         let n = solutions[solutions.len()-1][0] as usize;
+         if n < 1 || MAX_STANDARD_MULTISIG_PUBKEYS < n || m < 1 || n < m {
+            None
+         } else {
+            Some(t)
+         }
+      },
+      TxOutType::NullData => match max_datacarrier_bytes {
+         Some(max) if script.len() <= max => Some(t),
+         _ => None,
+      },
+      _ => Some(t),
+   }
+}
+
+/// (policy.cpp: GetDustThreshold)
+pub fn get_dust_threshold(txout:&TxOut, dust_relay_fee:FeeRatePerKvB) -> crate::Result<Amount> {
+   let script = &txout.script_pubkey.bytecode[..];
+   if is_unspendable(script) {
+      return Ok(0);
+   }
+   let mut size = get_serialize_size(txout, false)?;
+   if Parser::is_witness_program(script) {
+      // outpoint, scriptSig length, a quarter of the witness of p2wpkh and sequence
+      size += 32 + 4 + 1 + (107 / WITNESS_SCALE_FACTOR) + 4;
+   } else {
+      // outpoint, scriptSig of p2pkh and sequence
+      size += 32 + 4 + 1 + 107 + 4;
+   }
+   Ok(dust_relay_fee.get_fee(size))
+}
+
+pub fn is_dust(txout:&TxOut, dust_relay_fee:FeeRatePerKvB) -> crate::Result<bool> {
+   Ok(txout.value < get_dust_threshold(txout, dust_relay_fee)?)
+}
+
+/// (policy.cpp: IsStandardTx)
+/// The reason is raised as the validation error which code is rejected as nonstandard.
+pub fn is_standard_tx(tx:&Tx, max_datacarrier_bytes:Option<usize>, permit_bare_multisig:bool, dust_relay_fee:FeeRatePerKvB) -> crate::Result<()> {
+   if tx.version < TX_MIN_STANDARD_VERSION || TX_MAX_STANDARD_VERSION < tx.version {
+      raise_validation_error!(TxVersion, format!("{}", tx.version));
+   }
+   let weight = tx.weight()?;
+   if MAX_STANDARD_TX_WEIGHT < weight {
+      raise_validation_error!(TxSize, format!("weight={}", weight));
+   }
+   for txin in tx.ins.iter() {
+      let script_sig = &txin.script_sig.bytecode[..];
+      if MAX_STANDARD_SCRIPTSIG_SIZE < script_sig.len() {
+         raise_validation_error!(TxScriptSigSize);
+      }
+      if !Parser::is_push_only(script_sig) {
+         raise_validation_error!(TxScriptSigNotPushOnly);
+      }
+   }
+   let mut data_out = 0usize;
+   for txout in tx.outs.iter() {
+      match is_standard(&txout.script_pubkey.bytecode[..], max_datacarrier_bytes) {
+         None => raise_validation_error!(TxScriptPubKey),
+         Some(TxOutType::NullData) => data_out += 1,
+         Some(TxOutType::Multisig) if !permit_bare_multisig => raise_validation_error!(TxBareMultisig),
+         Some(_) => {
+            if is_dust(txout, dust_relay_fee)? {
+               raise_validation_error!(TxDust);
+            }
+         },
+      }
+   }
+   if 1 < data_out {
+      raise_validation_error!(TxMultiOpReturn);
+   }
+   // checked by PreChecks after IsStandardTx in bitcoin core
+   let base_size = tx.base_size()?;
+   if base_size < MIN_STANDARD_TX_NONWITNESS_SIZE {
+      raise_validation_error!(TxSizeSmall, format!("size={}", base_size));
+   }
+   Ok(())
+}
+
+/// the last push of the push only scriptSig
+fn get_last_push(script_sig:&[u8]) -> Option<Vec<u8>> {
+   let mut last = None;
+   for r in Parser::iter(script_sig) {
+      let p = match r {
+         Ok(p) => p,
+         Err(_) => return None,
+      };
+      last = if let Some(d) = p.instruction.data() {
+         Some(d.to_vec())
+      } else if let Some(v) = p.instruction.value() {
+         let mut buf = [0u8; 9];
+         let len = crate::bitcoin::script::ScriptNum::serialize(v, &mut buf);
+         Some(buf[..len].to_vec())
+      } else {
+         return None;
+      };
+   }
+   last
+}
+
+/// Checks the scriptPubKeys of the spent outputs and the sigops of the P2SH redeem scripts
+/// (policy.cpp: AreInputsStandard). prevouts are the outputs spent by tx.ins in the same order.
+pub fn are_inputs_standard(tx:&Tx, prevouts:&[TxOut]) -> crate::Result<()> {
+   if tx.is_coin_base() {
+      return Ok(());
+   }
+   if tx.ins.len() != prevouts.len() {
+      raise_validation_error!(TxNonStandardInputs, format!("{} prevouts for {} inputs", prevouts.len(), tx.ins.len()));
+   }
+   for (i, (txin, prevout)) in tx.ins.iter().zip(prevouts.iter()).enumerate() {
+      match Solver::get_type(&prevout.script_pubkey.bytecode[..]) {
+         TxOutType::NonStandard | TxOutType::WitnessUnknown => {
+            raise_validation_error!(TxNonStandardInputs, format!("input {}", i));
+         },
+         TxOutType::ScriptHash => {
+            let redeem_script = match get_last_push(&txin.script_sig.bytecode[..]) {
+               Some(s) => s,
+               None => raise_validation_error!(TxNonStandardInputs, format!("input {}: no redeem script", i)),
+            };
+            if MAX_P2SH_SIGOPS < Parser::get_sig_op_count(&redeem_script[..], true) {
+               raise_validation_error!(TxNonStandardInputs, format!("input {}: too many sigops", i));
+            }
+         },
+         _ => (),
+      }
+   }
+   Ok(())
+}
+
+/// Checks the sigop cost of tx against MAX_STANDARD_TX_SIGOPS_COST (validation.cpp: PreChecks).
+/// prevouts are the outputs spent by tx.ins in the same order.
+pub fn check_sig_op_cost(tx:&Tx, prevouts:&[TxOut]) -> crate::Result<()> {
+   let cost = get_transaction_sig_op_cost(tx, prevouts, &standard_script_verify_flags())?;
+   if MAX_STANDARD_TX_SIGOPS_COST < cost {
+      raise_validation_error!(TxTooManySigOps, format!("{}", cost));
+   }
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::datatypes::Script;
+   use crate::bitcoin::datatypes::test_utils::new_txin;
+   use crate::bitcoin::validation::ValidationErrorCode as C;
+   use crate::bitcoin::policy::apriori::{DUST_RELAY_TX_FEE, MAX_OP_RETURN_RELAY};
+   use crate::utils::h2b;
+
+   const P2PKH:&str = "76a9141018853670f9f3b0582c5b9ee8ce93764ac32b9388ac";
+   const P2WPKH:&str = "0014751e76e8199196d454941c45d1b3a323f1433bd6";
+
+   fn is_error(r:crate::Result<()>, code:C) -> bool {
+      match r {
+         Err(crate::Error::BitcoinValidation(ref e)) => e.is(code),
+         _ => false,
+      }
+   }
+   fn txout(value:Amount, script:&str) -> TxOut {
+      TxOut { value:value, script_pubkey:Script::new(h2b(script).unwrap()) }
+   }
+   fn create_tx() -> Tx {
+      let mut tx = Tx::new_null();
+      tx.ins.push(new_txin(1));
+      tx.outs.push(txout(1000, P2PKH));
+      tx
+   }
+   fn check(tx:&Tx) -> crate::Result<()> {
+      is_standard_tx(tx, Some(MAX_OP_RETURN_RELAY), true, DUST_RELAY_TX_FEE)
+   }
+
+   #[test]
+   fn test_dust_threshold() {
+      assert_eq!(get_dust_threshold(&txout(0, P2PKH), DUST_RELAY_TX_FEE).unwrap(), 546);
+      assert_eq!(get_dust_threshold(&txout(0, P2WPKH), DUST_RELAY_TX_FEE).unwrap(), 294);
+      assert_eq!(get_dust_threshold(&txout(0, "6a"), DUST_RELAY_TX_FEE).unwrap(), 0);
+      assert!(is_dust(&txout(545, P2PKH), DUST_RELAY_TX_FEE).unwrap());
+      assert!(!is_dust(&txout(546, P2PKH), DUST_RELAY_TX_FEE).unwrap());
+   }
+
+   #[test]
+   fn test_is_standard() {
+      let pk = "210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+      let ms = |m:&str, n:usize, k:&str| h2b(format!("{}{}{}ae", m, pk.repeat(n), k)).unwrap();
+      assert_eq!(is_standard(&ms("51", 3, "53")[..], None), Some(TxOutType::Multisig));
+      assert_eq!(is_standard(&ms("51", 4, "54")[..], None), None);
+      assert_eq!(is_standard(&h2b("6a0568656c6c6f").unwrap()[..], Some(MAX_OP_RETURN_RELAY)), Some(TxOutType::NullData));
+      assert_eq!(is_standard(&h2b("6a0568656c6c6f").unwrap()[..], None), None);
+      assert_eq!(is_standard(&h2b("6a0568656c6c6f").unwrap()[..], Some(6)), None);
+      assert_eq!(is_standard(&h2b("5210751e76e8199196d454941c45d1b3a323").unwrap()[..], None), Some(TxOutType::WitnessUnknown));
+      assert_eq!(is_standard(&h2b("76").unwrap()[..], None), None);
+   }
+
+   #[test]
+   fn test_is_standard_tx() {
+      assert_matches!(check(&create_tx()), Ok(()));
+
+      let mut tx = create_tx();
+      tx.version = 3;
+      assert!(is_error(check(&tx), C::TxVersion));
+
+      // 64 and 65 bytes without witness
+      let mut tx = create_tx();
+      tx.outs[0] = txout(0, "6a0101");
+      assert!(is_error(check(&tx), C::TxSizeSmall));
+      tx.outs[0] = txout(0, "6a020102");
+      assert_matches!(check(&tx), Ok(()));
+
+      let mut tx = create_tx();
+      tx.ins[0].script_sig = Script::new(vec![0x76]);
+      assert!(is_error(check(&tx), C::TxScriptSigNotPushOnly));
+
+      let mut tx = create_tx();
+      tx.ins[0].script_sig = Script::new(vec![0x51; MAX_STANDARD_SCRIPTSIG_SIZE + 1]);
+      assert!(is_error(check(&tx), C::TxScriptSigSize));
+
+      let mut tx = create_tx();
+      tx.outs[0].value = 545;
+      assert!(is_error(check(&tx), C::TxDust));
+
+      let mut tx = create_tx();
+      tx.outs[0] = txout(0, "76");
+      assert!(is_error(check(&tx), C::TxScriptPubKey));
+
+      let mut tx = create_tx();
+      tx.outs.push(txout(0, "6a0568656c6c6f"));
+      assert_matches!(check(&tx), Ok(()));
+      tx.outs.push(txout(0, "6a0568656c6c6f"));
+      assert!(is_error(check(&tx), C::TxMultiOpReturn));
+
+      let mut tx = create_tx();
+      tx.outs[0].script_pubkey = Script::new(h2b("51210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f8179851ae").unwrap());
+      assert_matches!(check(&tx), Ok(()));
+      assert!(is_error(is_standard_tx(&tx, None, false, DUST_RELAY_TX_FEE), C::TxBareMultisig));
+
+      let e = crate::validation_error!(TxDust);
+      assert_eq!(e.reject_code(), Some(crate::bitcoin::protocol::RejectCode::NonStandard));
+   }
+
+   #[test]
+   fn test_are_inputs_standard() {
+      let tx = create_tx();
+      assert_matches!(are_inputs_standard(&tx, &[txout(1000, P2PKH)]), Ok(()));
+      assert!(is_error(are_inputs_standard(&tx, &[txout(1000, "76")]), C::TxNonStandardInputs));
+      assert!(is_error(are_inputs_standard(&tx, &[]), C::TxNonStandardInputs));
+
+      // 16 checksigs in the redeem script
+      let p2sh = "a9141018853670f9f3b0582c5b9ee8ce93764ac32b9387";
+      let mut tx = create_tx();
+      let mut script_sig = vec![16u8];
+      script_sig.extend_from_slice(&[0xac; 16]);
+      tx.ins[0].script_sig = Script::new(script_sig.clone());
+      assert!(is_error(are_inputs_standard(&tx, &[txout(1000, p2sh)]), C::TxNonStandardInputs));
+      script_sig[0] = 15;
+      script_sig.pop();
+      tx.ins[0].script_sig = Script::new(script_sig);
+      assert_matches!(are_inputs_standard(&tx, &[txout(1000, p2sh)]), Ok(()));
+   }
+
+   #[test]
+   fn test_check_sig_op_cost() {
+      // 20 legacy sigops of the bare multisig output cost 80
+      let mut tx = create_tx();
+      tx.outs[0] = txout(1000, "ae");
+      assert_matches!(check_sig_op_cost(&tx, &[txout(1000, P2PKH)]), Ok(()));
+      for _ in 0..(MAX_STANDARD_TX_SIGOPS_COST / 80) {
+         tx.outs.push(txout(1000, "ae"));
+      }
+      assert!(is_error(check_sig_op_cost(&tx, &[txout(1000, P2PKH)]), C::TxTooManySigOps));
+      // without the spent outputs
+      assert!(is_error(check_sig_op_cost(&tx, &[]), C::TxInputsMissingOrSpent));
+      assert!(is_error(check_sig_op_cost(&tx, &[txout(1000, P2PKH), txout(1000, P2PKH)]), C::TxInputsMissingOrSpent));
+   }
+}
@@ -9,6 +9,7 @@ lazy_static! {
       coin:        "Bitcoin",
       network:     "main",
       magic:       0xD9B4BEF9u32,
+      bech32_hrp:  "bc",
       base58check: cp::Base58check {
          table: crate::bitcoin::utils::BASE58_TABLE,
          versions: cp::base58check::Versions {
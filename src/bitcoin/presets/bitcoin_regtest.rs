@@ -9,6 +9,7 @@ lazy_static! {
       coin:        "Bitcoin",
       network:     "regtest",
       magic:       0xDAB5BFFAu32,
+      bech32_hrp:  "bcrt",
       base58check: cp::Base58check {
          table: crate::bitcoin::utils::BASE58_TABLE,
          versions: cp::base58check::Versions {
@@ -9,6 +9,7 @@ lazy_static! {
       coin:        "Bitcoin",
       network:     "test",
       magic:       0x0709110Bu32,
+      bech32_hrp:  "tb",
       base58check: cp::Base58check {
          table: crate::bitcoin::utils::BASE58_TABLE,
          versions: cp::base58check::Versions {
@@ -3,8 +3,11 @@ pub const USER_AGENT:&'static str = "/bitcoinnaut:0.1.0/";
 
 pub const COMMAND_LENGTH:usize = 12;
 
-pub const PROTOCOL_VERSION:i32     = 70012;
+pub const PROTOCOL_VERSION:i32     = 70016;
 pub const SENDHEADERS_VERSION:i32  = 70012;
+pub const FEEFILTER_VERSION:i32    = 70013;
+pub const SHORT_IDS_BLOCKS_VERSION:i32 = 70014;
+pub const WTXID_RELAY_VERSION:i32  = 70016;
 pub const BIP0031_VERSION:i32      = 60000;
 pub const GETHEADERS_VERSION:i32   = 31800;
 pub const ADDRESS_TIME_VERSION:i32 = 31402;
@@ -18,9 +21,25 @@ pub const NODE_GETUTXO:u64 = 1 << 1;
 pub const NODE_BLOOM:u64   = 1 << 2;
 pub const NODE_WITNESS:u64 = 1 << 3;
 pub const NODE_XTHIN:u64   = 1 << 4;
+pub const NODE_COMPACT_FILTERS:u64 = 1 << 6;
 
 pub const MAX_MESSAGE_LENGTH:usize = 4 * 1000 * 1000;
 pub const MAX_SUBVERSION_LENGTH:usize = 256;
 pub const MAX_ADDR_SIZE:usize = 1000;
 pub const MAX_INV_SIZE:usize = 1000;
+// MAX_BLOCK_WEIGHT / MIN_SERIALIZABLE_TRANSACTION_WEIGHT
+pub const MAX_BLOCK_TX_COUNT:usize = 4000000 / 40;
+
+pub const MAX_BLOOM_FILTER_SIZE:usize = 36000; // bytes
+pub const MAX_HASH_FUNCS:u32 = 50;
+pub const BLOOM_UPDATE_NONE:u8 = 0;
+pub const BLOOM_UPDATE_ALL:u8 = 1;
+pub const BLOOM_UPDATE_P2PUBKEY_ONLY:u8 = 2;
+pub const BLOOM_UPDATE_MASK:u8 = 3;
+
+pub const MAX_GETCFILTERS_SIZE:usize = 1000;
+pub const MAX_GETCFHEADERS_SIZE:usize = 2000;
+pub const CFCHECKPT_INTERVAL:usize = 1000;
+// 32-byte headers that fit in a message
+pub const MAX_CFCHECKPT_HEADERS:usize = MAX_MESSAGE_LENGTH / 32;
 
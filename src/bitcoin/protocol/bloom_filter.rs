@@ -0,0 +1,360 @@
+use crate::bitcoin::datatypes::{Tx, TxOutPoint, Block, MerkleBlock, UInt256};
+use crate::bitcoin::script::parser::Parser;
+use crate::bitcoin::script::{Solver, TxOutType};
+use super::apriori::{
+   MAX_BLOOM_FILTER_SIZE, MAX_HASH_FUNCS,
+   BLOOM_UPDATE_ALL, BLOOM_UPDATE_P2PUBKEY_ONLY, BLOOM_UPDATE_MASK,
+};
+use super::message::{FilterLoadMessage, FilterAddMessage};
+
+const LN2SQUARED:f64 = 0.4804530139182014246671025263266649717305529515945455;
+const LN2:f64 = 0.6931471805599453094172321214581765680755001343602552;
+
+/// BIP37 bloom filter (common/bloom.cpp: CBloomFilter)
+#[derive(Debug,Default,Clone)]
+pub struct BloomFilter {
+   data:       Vec<u8>,
+   hash_funcs: u32,
+   tweak:      u32,
+   flags:      u8,
+}
+
+fn serialize_outpoint(outpoint:&TxOutPoint) -> crate::Result<Box<[u8]>> {
+   crate::ui::bitcoin::serialize(outpoint, &())
+}
+
+impl BloomFilter {
+   /// Creates the filter of the false positive rate `fp_rate` for `elements` elements.
+   /// The size is capped by MAX_BLOOM_FILTER_SIZE and MAX_HASH_FUNCS.
+   pub fn new(elements:usize, fp_rate:f64, tweak:u32, flags:u8) -> Self {
+      let elements = std::cmp::max(elements, 1);
+      let bits = (-1.0 / LN2SQUARED * elements as f64 * fp_rate.ln()) as usize;
+      let size = std::cmp::min(bits, MAX_BLOOM_FILTER_SIZE * 8) / 8;
+      let hash_funcs = (((size * 8 / elements) as f64 * LN2) as u32).min(MAX_HASH_FUNCS);
+      BloomFilter {
+         data:       vec![0u8; size],
+         hash_funcs: hash_funcs,
+         tweak:      tweak,
+         flags:      flags,
+      }
+   }
+
+   /// Loads the filter sent by the peer. Fails if it exceeds the size limits.
+   pub fn from_message(msg:&FilterLoadMessage) -> crate::Result<Self> {
+      let filter = BloomFilter {
+         data:       msg.data.clone(),
+         hash_funcs: msg.hash_funcs,
+         tweak:      msg.tweak,
+         flags:      msg.flags,
+      };
+      if !filter.is_within_size_constraints() {
+         raise_protocol_error!(format!("bloom filter is too large: size={}, funcs={}", msg.data.len(), msg.hash_funcs));
+      }
+      Ok(filter)
+   }
+
+   pub fn to_message(&self) -> FilterLoadMessage {
+      FilterLoadMessage {
+         data:       self.data.clone(),
+         hash_funcs: self.hash_funcs,
+         tweak:      self.tweak,
+         flags:      self.flags,
+      }
+   }
+
+   pub fn data(&self) -> &[u8] { &self.data[..] }
+   pub fn hash_funcs(&self) -> u32 { self.hash_funcs }
+   pub fn tweak(&self) -> u32 { self.tweak }
+   pub fn flags(&self) -> u8 { self.flags }
+
+   pub fn is_within_size_constraints(&self) -> bool {
+      self.data.len() <= MAX_BLOOM_FILTER_SIZE && self.hash_funcs <= MAX_HASH_FUNCS
+   }
+
+   fn hash(&self, n:u32, key:&[u8]) -> usize {
+      let seed = n.wrapping_mul(0xFBA4C795).wrapping_add(self.tweak);
+      crate::crypto::murmur3_32(seed, key) as usize % (self.data.len() * 8)
+   }
+
+   pub fn insert(&mut self, key:&[u8]) {
+      // avoid divide-by-zero (CVE-2013-5700)
+      if self.data.is_empty() {
+         return;
+      }
+      for n in 0..self.hash_funcs {
+         let i = self.hash(n, key);
+         self.data[i >> 3] |= 1 << (7 & i);
+      }
+   }
+
+   /// An empty filter matches everything.
+   pub fn contains(&self, key:&[u8]) -> bool {
+      if self.data.is_empty() {
+         return true;
+      }
+      (0..self.hash_funcs).all(|n| {
+         let i = self.hash(n, key);
+         self.data[i >> 3] & (1 << (7 & i)) != 0
+      })
+   }
+
+   pub fn insert_outpoint(&mut self, outpoint:&TxOutPoint) -> crate::Result<()> {
+      let key = serialize_outpoint(outpoint)?;
+      self.insert(&key[..]);
+      Ok(())
+   }
+
+   pub fn contains_outpoint(&self, outpoint:&TxOutPoint) -> crate::Result<bool> {
+      let key = serialize_outpoint(outpoint)?;
+      Ok(self.contains(&key[..]))
+   }
+
+   /// Adds the element sent by filteradd.
+   pub fn add(&mut self, msg:&FilterAddMessage) -> crate::Result<()> {
+      use crate::bitcoin::script::apriori::MAX_SCRIPT_ELEMENT_SIZE;
+      if MAX_SCRIPT_ELEMENT_SIZE < msg.data.len() {
+         raise_protocol_error!(format!("filteradd data is too large: {}", msg.data.len()));
+      }
+      self.insert(&msg.data[..]);
+      Ok(())
+   }
+
+   /// Tests the txid, the data pushed by the output scripts, the spent outpoints and
+   /// the data pushed by the input scripts. The outpoints of the matched outputs are
+   /// inserted to the filter according to the BLOOM_UPDATE_* flags.
+   pub fn is_relevant_and_update(&mut self, tx:&Tx) -> crate::Result<bool> {
+      if self.data.is_empty() {
+         return Ok(true);
+      }
+      let txid = tx.txid()?;
+      let mut found = self.contains(&txid.data[..]);
+      for (i, out) in tx.outs.iter().enumerate() {
+         let script = &out.script_pubkey.bytecode[..];
+         // stops at the first match of each output, and at a parse error
+         let matched = Parser::iter(script)
+            .take_while(|r| r.is_ok())
+            .filter_map(|r| r.ok().and_then(|p| p.instruction.data().map(|d| d.to_vec())))
+            .any(|d| !d.is_empty() && self.contains(&d[..]));
+         if !matched {
+            continue;
+         }
+         found = true;
+         let update = match self.flags & BLOOM_UPDATE_MASK {
+            BLOOM_UPDATE_ALL => true,
+            BLOOM_UPDATE_P2PUBKEY_ONLY => match Solver::get_type(script) {
+               TxOutType::PubKey | TxOutType::Multisig => true,
+               _ => false,
+            },
+            _ => false,
+         };
+         if update {
+            self.insert_outpoint(&TxOutPoint { txid:txid.clone(), n:i as u32 })?;
+         }
+      }
+      if found {
+         return Ok(true);
+      }
+      for txin in tx.ins.iter() {
+         if self.contains_outpoint(&txin.prevout)? {
+            return Ok(true);
+         }
+         let matched = Parser::iter(&txin.script_sig.bytecode[..])
+            .take_while(|r| r.is_ok())
+            .filter_map(|r| r.ok().and_then(|p| p.instruction.data().map(|d| d.to_vec())))
+            .any(|d| !d.is_empty() && self.contains(&d[..]));
+         if matched {
+            return Ok(true);
+         }
+      }
+      Ok(false)
+   }
+}
+
+/// (merkleblock.cpp: CMerkleBlock(block, filter))
+impl MerkleBlock {
+   /// Builds the proof of the transactions matched by the filter, which is updated as the txs are tested.
+   /// Also returns the indexes and the txids of the matched txs.
+   pub fn from_filter(block:&Block, filter:&mut BloomFilter) -> crate::Result<(Self, Vec<(u32, UInt256)>)> {
+      let mut matches = Vec::with_capacity(block.txs.len());
+      let mut matched = Vec::new();
+      for (i, tx) in block.txs.iter().enumerate() {
+         let m = filter.is_relevant_and_update(tx)?;
+         if m {
+            matched.push((i as u32, tx.txid()?));
+         }
+         matches.push(m);
+      }
+      Ok((Self::new(block, &matches[..])?, matched))
+   }
+
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::utils::h2b;
+   use crate::bitcoin::datatypes::{TxOut, Script};
+   use crate::bitcoin::datatypes::test_utils::{new_txin, new_block};
+   use crate::bitcoin::protocol::apriori::BLOOM_UPDATE_NONE;
+
+   #[test]
+   fn test_create_insert_serialize() {
+      // bloom_tests.cpp
+      for &(tweak, expected) in [(0u32, "03614e9b050000000000000001"), (2147483649u32, "03ce4299050000000100008001")].iter() {
+         let mut filter = BloomFilter::new(3, 0.01, tweak, BLOOM_UPDATE_ALL);
+         let a = h2b("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap();
+         filter.insert(&a[..]);
+         assert!(filter.contains(&a[..]));
+         assert!(!filter.contains(&h2b("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap()[..]));
+         filter.insert(&h2b("b5a2c786d9ef4658287ced5914b37a1b4aa32eee").unwrap()[..]);
+         filter.insert(&h2b("b9300670b4c5366e95b2699e8b18bc75e5f729c5").unwrap()[..]);
+
+         let v = crate::ui::bitcoin::serialize(&filter.to_message(), &()).unwrap();
+         assert_eq!(&v[..], &h2b(expected).unwrap()[..]);
+
+         let mut msg = FilterLoadMessage::default();
+         let _ = crate::ui::bitcoin::deserialize(&v[..], &(), &mut msg).unwrap();
+         let loaded = BloomFilter::from_message(&msg).unwrap();
+         assert!(loaded.contains(&a[..]));
+      }
+   }
+
+   #[test]
+   fn test_size_constraints() {
+      let filter = BloomFilter::new(1000000, 0.000001, 0, BLOOM_UPDATE_NONE);
+      assert_eq!(filter.data().len(), MAX_BLOOM_FILTER_SIZE);
+      assert!(filter.hash_funcs() <= MAX_HASH_FUNCS);
+
+      let msg = FilterLoadMessage { data:vec![0; 10], hash_funcs:MAX_HASH_FUNCS + 1, tweak:0, flags:0 };
+      assert_matches!(BloomFilter::from_message(&msg), Err(crate::Error::BitcoinProtocol(_)));
+      let msg = FilterLoadMessage { data:vec![0; MAX_BLOOM_FILTER_SIZE + 1], hash_funcs:1, tweak:0, flags:0 };
+      assert_matches!(BloomFilter::from_message(&msg), Err(crate::Error::BitcoinProtocol(_)));
+      // also rejected on the wire
+      let v = crate::ui::bitcoin::serialize(&FilterLoadMessage { data:vec![0; 10], ..Default::default() }, &()).unwrap();
+      let mut v = v.to_vec();
+      v[0..4].copy_from_slice(&[0xfd, 0xa1, 0x8c, 0x00]); // 36001
+      let mut msg = FilterLoadMessage::default();
+      assert_matches!(crate::ui::bitcoin::deserialize(&v[..], &(), &mut msg), Err(_));
+
+      let mut filter = BloomFilter::new(10, 0.01, 0, BLOOM_UPDATE_NONE);
+      assert_matches!(filter.add(&FilterAddMessage { data:vec![0; 521] }), Err(crate::Error::BitcoinProtocol(_)));
+      assert_matches!(filter.add(&FilterAddMessage { data:vec![0; 520] }), Ok(()));
+
+      // an empty filter matches everything
+      let filter = BloomFilter::default();
+      assert!(filter.contains(&[1, 2, 3]));
+   }
+
+   const PUBKEY:&str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+   const PKH:&str = "751e76e8199196d454941c45d1b3a323f1433bd6";
+
+   fn new_tx(outs:Vec<Script>) -> Tx {
+      let mut tx = Tx::new_null();
+      tx.ins.push(new_txin(1));
+      for s in outs {
+         tx.outs.push(TxOut { value:1000, script_pubkey:s });
+      }
+      tx
+   }
+
+   fn p2pk() -> Script {
+      let mut v = vec![0x21];
+      v.extend_from_slice(&h2b(PUBKEY).unwrap()[..]);
+      v.push(0xac);
+      Script::new(v)
+   }
+   fn p2pkh() -> Script {
+      let mut v = vec![0x76, 0xa9, 0x14];
+      v.extend_from_slice(&h2b(PKH).unwrap()[..]);
+      v.extend_from_slice(&[0x88, 0xac]);
+      Script::new(v)
+   }
+   fn spend(tx:&Tx, n:u32) -> Tx {
+      let mut s = new_tx(vec![Script::new(vec![0x51])]);
+      s.ins[0].prevout = TxOutPoint { txid:tx.txid().unwrap(), n:n };
+      s
+   }
+
+   #[test]
+   fn test_update_flags() {
+      let tx = new_tx(vec![p2pk(), p2pkh()]);
+      let cases = [
+         (BLOOM_UPDATE_NONE, false, false),
+         (BLOOM_UPDATE_ALL, true, true),
+         (BLOOM_UPDATE_P2PUBKEY_ONLY, true, false),
+      ];
+      for &(flags, spend_pk, spend_pkh) in cases.iter() {
+         let mut filter = BloomFilter::new(10, 0.000001, 5, flags);
+         filter.insert(&h2b(PUBKEY).unwrap()[..]);
+         filter.insert(&h2b(PKH).unwrap()[..]);
+         assert_matches!(filter.is_relevant_and_update(&tx), Ok(true));
+         assert_matches!(filter.is_relevant_and_update(&spend(&tx, 0)), Ok(r) if r == spend_pk);
+         assert_matches!(filter.is_relevant_and_update(&spend(&tx, 1)), Ok(r) if r == spend_pkh);
+      }
+   }
+
+   #[test]
+   fn test_match() {
+      let tx = new_tx(vec![p2pkh()]);
+      let mut filter = BloomFilter::new(10, 0.000001, 0, BLOOM_UPDATE_NONE);
+      assert_matches!(filter.is_relevant_and_update(&tx), Ok(false));
+
+      // txid
+      let mut filter = BloomFilter::new(10, 0.000001, 0, BLOOM_UPDATE_NONE);
+      filter.insert(&tx.txid().unwrap().data[..]);
+      assert_matches!(filter.is_relevant_and_update(&tx), Ok(true));
+
+      // spent outpoint
+      let mut filter = BloomFilter::new(10, 0.000001, 0, BLOOM_UPDATE_NONE);
+      filter.insert_outpoint(&tx.ins[0].prevout).unwrap();
+      assert_matches!(filter.contains_outpoint(&tx.ins[0].prevout), Ok(true));
+      assert_matches!(filter.is_relevant_and_update(&tx), Ok(true));
+
+      // data in the input script
+      let mut tx2 = tx.clone();
+      tx2.ins[0].script_sig = Script::new(vec![0x03, 0xaa, 0xbb, 0xcc]);
+      let mut filter = BloomFilter::new(10, 0.000001, 0, BLOOM_UPDATE_NONE);
+      filter.insert(&[0xaa, 0xbb, 0xcc]);
+      assert_matches!(filter.is_relevant_and_update(&tx2), Ok(true));
+      assert_matches!(filter.is_relevant_and_update(&tx), Ok(false));
+   }
+
+   #[test]
+   fn test_update_p2pubkey_only_multisig() {
+      // 1-of-1 bare multisig is updated like a pay-to-pubkey
+      let mut v = vec![0x51, 0x21];
+      v.extend_from_slice(&h2b(PUBKEY).unwrap()[..]);
+      v.extend_from_slice(&[0x51, 0xae]);
+      let multisig = Script::new(v.clone());
+      // 2-of-1 is not a multisig template
+      v[0] = 0x52;
+      let nonstandard = Script::new(v);
+      let tx = new_tx(vec![multisig, nonstandard]);
+
+      let mut filter = BloomFilter::new(10, 0.000001, 5, BLOOM_UPDATE_P2PUBKEY_ONLY);
+      filter.insert(&h2b(PUBKEY).unwrap()[..]);
+      assert_matches!(filter.is_relevant_and_update(&tx), Ok(true));
+      assert_matches!(filter.is_relevant_and_update(&spend(&tx, 0)), Ok(true));
+      assert_matches!(filter.is_relevant_and_update(&spend(&tx, 1)), Ok(false));
+   }
+
+   #[test]
+   fn test_from_filter() {
+      let block = new_block(9);
+      let mut filter = BloomFilter::new(10, 0.000001, 0, BLOOM_UPDATE_ALL);
+      filter.insert(&[3]);
+      filter.insert(&[7]);
+      let (mb, matched) = MerkleBlock::from_filter(&block, &mut filter).unwrap();
+      assert_eq!(matched.len(), 2);
+      assert_eq!(matched[0], (3, block.txs[3].txid().unwrap()));
+      assert_eq!(matched[1], (7, block.txs[7].txid().unwrap()));
+
+      let v = crate::ui::bitcoin::serialize(&mb, &()).unwrap();
+      let mut mb2 = MerkleBlock::default();
+      let _ = crate::ui::bitcoin::deserialize(&v[..], &(), &mut mb2).unwrap();
+      assert_eq!(mb2.extract_matches().unwrap(), matched);
+
+      mb2.header.hash_merkle_root = UInt256::new_null();
+      assert_matches!(mb2.extract_matches(), Err(crate::Error::BitcoinMerkle(_)));
+   }
+}
@@ -0,0 +1,354 @@
+use std::collections::{HashMap, HashSet};
+use crate::bitcoin::datatypes::{Block, BlockHeader, Tx, UInt256};
+use crate::bitcoin::datatypes::merkle::block_merkle_root;
+use super::message::{
+   CmpctBlockMessage, PrefilledTx, GetBlockTxnMessage, BlockTxnMessage,
+};
+use super::message::cmpct_block_message::SHORT_ID_MASK;
+use super::apriori::MAX_BLOCK_TX_COUNT;
+
+/// SipHash keys of the short ids: first two little endian u64 of sha256(header || nonce)
+pub fn short_id_keys(header:&BlockHeader, nonce:u64) -> crate::Result<(u64, u64)> {
+   let mut buf = crate::ui::bitcoin::serialize(header, &())?.to_vec();
+   buf.extend_from_slice(&[nonce as u8, (nonce >> 8) as u8, (nonce >> 16) as u8, (nonce >> 24) as u8,
+                           (nonce >> 32) as u8, (nonce >> 40) as u8, (nonce >> 48) as u8, (nonce >> 56) as u8]);
+   let h = crate::ui::create_sha256().u8_to_u8(&buf[..]);
+   let k = |o:usize| h[o..(o+8)].iter().rev().fold(0u64, |acc, &b| acc << 8 | b as u64);
+   Ok((k(0), k(8)))
+}
+
+/// txid for the version 1, wtxid for the version 2
+pub fn short_id(k0:u64, k1:u64, tx:&Tx, use_wtxid:bool) -> crate::Result<u64> {
+   let hash = if use_wtxid { tx.wtxid()? } else { tx.txid()? };
+   Ok(crate::crypto::siphash24(k0, k1, &hash.data[..]) & SHORT_ID_MASK)
+}
+
+impl CmpctBlockMessage {
+   /// Creates the message with only the coinbase prefilled.
+   pub fn new(block:&Block, nonce:u64, use_wtxid:bool) -> crate::Result<Self> {
+      let (k0, k1) = short_id_keys(&block.header, nonce)?;
+      let mut msg = CmpctBlockMessage {
+         header:        block.header.clone(),
+         nonce:         nonce,
+         short_ids:     Vec::with_capacity(block.txs.len()),
+         prefilled_txs: Vec::with_capacity(1),
+      };
+      for (i, tx) in block.txs.iter().enumerate() {
+         if i == 0 {
+            msg.prefilled_txs.push(PrefilledTx { index:0, tx:tx.clone() });
+         } else {
+            msg.short_ids.push(short_id(k0, k1, tx, use_wtxid)?);
+         }
+      }
+      Ok(msg)
+   }
+}
+
+/// Rebuilds a block from a cmpctblock (blockencodings.cpp: PartiallyDownloadedBlock).
+/// The slots not filled by the prefilled transactions are filled by the caller,
+/// and the rest are requested by getblocktxn.
+#[derive(Debug,Clone)]
+pub struct BlockReconstructor {
+   header:    BlockHeader,
+   k0:        u64,
+   k1:        u64,
+   use_wtxid: bool,
+   txs:       Vec<Option<Tx>>,
+   short_ids: HashMap<u64, usize>,
+   collided:  HashSet<usize>,
+}
+
+impl BlockReconstructor {
+   pub fn new(msg:&CmpctBlockMessage, use_wtxid:bool) -> crate::Result<Self> {
+      let count = msg.block_tx_count();
+      if count == 0 || MAX_BLOCK_TX_COUNT < count {
+         raise_protocol_error!(format!("invalid number of transactions: {}", count));
+      }
+      // the indexes of getblocktxn are 16 bits
+      if (std::u16::MAX as usize) < count {
+         raise_protocol_error!(format!("indexes overflow 16 bits: {}", count));
+      }
+      let mut txs:Vec<Option<Tx>> = vec![None; count];
+      let mut last:Option<usize> = None;
+      for p in msg.prefilled_txs.iter() {
+         let i = p.index as usize;
+         if count <= i {
+            raise_protocol_error!(format!("prefilled index out of range: {}", i));
+         }
+         if last.map_or(false, |l| i <= l) || txs[i].is_some() {
+            raise_protocol_error!(format!("prefilled index is not ascending: {}", i));
+         }
+         last = Some(i);
+         if p.tx.is_null() {
+            raise_protocol_error!(format!("prefilled tx is null: {}", i));
+         }
+         txs[i] = Some(p.tx.clone());
+      }
+      let (k0, k1) = short_id_keys(&msg.header, msg.nonce)?;
+      let mut short_ids = HashMap::with_capacity(msg.short_ids.len());
+      let mut ids = msg.short_ids.iter();
+      for (i, _) in txs.iter().enumerate().filter(|(_, tx)| tx.is_none()) {
+         let id = match ids.next() {
+            Some(id) => *id,
+            None => raise_protocol_error!(format!("short id is missing: {}", i)),
+         };
+         if short_ids.insert(id, i).is_some() {
+            raise_protocol_error!(format!("short id collision: {:x}", id));
+         }
+      }
+      Ok(BlockReconstructor {
+         header:    msg.header.clone(),
+         k0:        k0,
+         k1:        k1,
+         use_wtxid: use_wtxid,
+         txs:       txs,
+         short_ids: short_ids,
+         collided:  HashSet::new(),
+      })
+   }
+
+   pub fn header(&self) -> &BlockHeader {
+      &self.header
+   }
+
+   pub fn short_id(&self, tx:&Tx) -> crate::Result<u64> {
+      short_id(self.k0, self.k1, tx, self.use_wtxid)
+   }
+
+   /// Fills the missing slots by the lookup from a short id.
+   /// A returned tx whose short id does not match is ignored.
+   pub fn fill<F>(&mut self, mut lookup:F) -> crate::Result<()>
+      where F: FnMut(u64) -> Option<Tx>
+   {
+      let mut found = Vec::new();
+      for (&id, &i) in self.short_ids.iter() {
+         if self.txs[i].is_some() || self.collided.contains(&i) {
+            continue;
+         }
+         if let Some(tx) = lookup(id) {
+            found.push((id, i, tx));
+         }
+      }
+      for (id, i, tx) in found {
+         if self.short_id(&tx)? == id {
+            self.txs[i] = Some(tx);
+         }
+      }
+      Ok(())
+   }
+
+   /// Fills the missing slots by the candidates such as mempool.
+   /// A slot matched by two different transactions is left missing.
+   pub fn fill_from<'a, I>(&mut self, candidates:I) -> crate::Result<()>
+      where I: IntoIterator<Item=&'a Tx>
+   {
+      let mut matched = HashMap::<usize, (UInt256, &'a Tx)>::new();
+      for tx in candidates {
+         let id = self.short_id(tx)?;
+         let i = match self.short_ids.get(&id) {
+            Some(&i) if self.txs[i].is_none() => i,
+            _ => continue,
+         };
+         let hash = tx.wtxid()?;
+         match matched.get(&i) {
+            Some(&(ref h, _)) if *h == hash => (),
+            Some(_) => { self.collided.insert(i); },
+            None => { matched.insert(i, (hash, tx)); },
+         }
+      }
+      for (i, (_, tx)) in matched {
+         if !self.collided.contains(&i) {
+            self.txs[i] = Some(tx.clone());
+         }
+      }
+      Ok(())
+   }
+
+   /// the indexes of the transactions not filled yet
+   pub fn missing(&self) -> Vec<u16> {
+      self.txs.iter().enumerate().filter(|(_, tx)| tx.is_none()).map(|(i, _)| i as u16).collect()
+   }
+
+   pub fn is_complete(&self) -> bool {
+      self.txs.iter().all(|tx| tx.is_some())
+   }
+
+   pub fn get_block_txn_request(&self) -> crate::Result<GetBlockTxnMessage> {
+      Ok(GetBlockTxnMessage {
+         block_hash: self.header.get_hash()?,
+         indexes:    self.missing(),
+      })
+   }
+
+   /// Fills the missing slots in order by the response of the getblocktxn.
+   pub fn fill_block_txn(&mut self, msg:&BlockTxnMessage) -> crate::Result<()> {
+      if msg.block_hash != self.header.get_hash()? {
+         raise_protocol_error!(format!("blocktxn for another block: {}", msg.block_hash));
+      }
+      let missing = self.missing();
+      if missing.len() != msg.txs.len() {
+         raise_protocol_error!(format!("blocktxn has {} txs but {} are missing", msg.txs.len(), missing.len()));
+      }
+      for (&i, tx) in missing.iter().zip(msg.txs.iter()) {
+         self.txs[i as usize] = Some(tx.clone());
+      }
+      Ok(())
+   }
+
+   /// Builds the block and checks the merkle root.
+   /// The mismatch means a short id collision and the full block should be requested.
+   pub fn build(&self) -> crate::Result<Block> {
+      let missing = self.missing();
+      if !missing.is_empty() {
+         raise_protocol_error!(format!("{} transactions are missing", missing.len()));
+      }
+      let block = Block {
+         header:  self.header.clone(),
+         txs:     self.txs.iter().map(|tx| tx.clone().unwrap()).collect(),
+         checked: false,
+      };
+      let (root, mutated) = block_merkle_root(&block)?;
+      if mutated || root != block.header.hash_merkle_root {
+         raise_protocol_error!("merkle root mismatch");
+      }
+      Ok(block)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::datatypes::test_utils::{new_tx, new_block};
+
+   fn encode_decode(msg:&CmpctBlockMessage) -> CmpctBlockMessage {
+      let v = crate::ui::bitcoin::serialize(msg, &()).unwrap();
+      let mut ret = CmpctBlockMessage::default();
+      let _ = crate::ui::bitcoin::deserialize(&v[..], &(), &mut ret).unwrap();
+      ret
+   }
+
+   #[test]
+   fn test_short_id() {
+      let block = new_block(3);
+      let msg = CmpctBlockMessage::new(&block, 0x0102030405060708, false).unwrap();
+      assert_eq!(msg.prefilled_txs.len(), 1);
+      assert_eq!(msg.short_ids.len(), 2);
+      assert!(msg.short_ids.iter().all(|&id| id <= SHORT_ID_MASK));
+      assert_ne!(msg.short_ids[0], msg.short_ids[1]);
+
+      let (k0, k1) = short_id_keys(&block.header, 0x0102030405060708).unwrap();
+      let hash = block.txs[1].txid().unwrap();
+      assert_eq!(msg.short_ids[0], crate::crypto::siphash24(k0, k1, &hash.data[..]) & SHORT_ID_MASK);
+
+      let msg2 = CmpctBlockMessage::new(&block, 0x0102030405060709, false).unwrap();
+      assert_ne!(msg.short_ids, msg2.short_ids);
+
+      let dec = encode_decode(&msg);
+      assert_eq!(dec.nonce, msg.nonce);
+      assert_eq!(dec.short_ids, msg.short_ids);
+      assert_eq!(dec.prefilled_txs.len(), 1);
+      assert_eq!(dec.prefilled_txs[0].index, 0);
+   }
+
+   #[test]
+   fn test_reconstruct() {
+      let block = new_block(6);
+      let mut msg = CmpctBlockMessage::new(&block, 42, false).unwrap();
+      // prefill the 4th too
+      msg.short_ids.remove(2);
+      msg.prefilled_txs.push(PrefilledTx { index:3, tx:block.txs[3].clone() });
+      let msg = encode_decode(&msg);
+      assert_eq!(msg.prefilled_txs[1].index, 3);
+
+      let mut r = BlockReconstructor::new(&msg, false).unwrap();
+      assert_eq!(r.missing(), vec![1, 2, 4, 5]);
+
+      let mempool = vec![block.txs[2].clone(), new_tx(100)];
+      r.fill_from(mempool.iter()).unwrap();
+      assert_eq!(r.missing(), vec![1, 4, 5]);
+
+      let id4 = r.short_id(&block.txs[4]).unwrap();
+      let id5 = r.short_id(&block.txs[5]).unwrap();
+      r.fill(|id| {
+         if id == id4 { Some(block.txs[4].clone()) }
+         else if id == id5 { Some(new_tx(101)) } // wrong one is ignored
+         else { None }
+      }).unwrap();
+      assert_eq!(r.missing(), vec![1, 5]);
+      assert!(!r.is_complete());
+      assert_matches!(r.build(), Err(crate::Error::BitcoinProtocol(_)));
+
+      let req = r.get_block_txn_request().unwrap();
+      assert_eq!(req.block_hash, block.header.get_hash().unwrap());
+      assert_eq!(req.indexes, vec![1, 5]);
+
+      let mut res = BlockTxnMessage { block_hash:req.block_hash.clone(), txs:vec![block.txs[1].clone()] };
+      assert_matches!(r.fill_block_txn(&res), Err(crate::Error::BitcoinProtocol(_)));
+      res.txs.push(block.txs[5].clone());
+      r.fill_block_txn(&res).unwrap();
+      assert!(r.is_complete());
+
+      let rebuilt = r.build().unwrap();
+      assert_eq!(rebuilt.txs.len(), 6);
+      for (a, b) in rebuilt.txs.iter().zip(block.txs.iter()) {
+         assert_eq!(a.txid().unwrap(), b.txid().unwrap());
+      }
+   }
+
+   #[test]
+   fn test_reconstruct_wrong_tx() {
+      let block = new_block(3);
+      let msg = CmpctBlockMessage::new(&block, 7, true).unwrap();
+      let mut r = BlockReconstructor::new(&msg, true).unwrap();
+      let res = BlockTxnMessage { block_hash:block.header.get_hash().unwrap(), txs:vec![block.txs[1].clone(), new_tx(9)] };
+      r.fill_block_txn(&res).unwrap();
+      assert_matches!(r.build(), Err(crate::Error::BitcoinProtocol(_)));
+   }
+
+   #[test]
+   fn test_invalid() {
+      let block = new_block(3);
+      let msg = CmpctBlockMessage::new(&block, 7, false).unwrap();
+
+      let mut m = msg.clone();
+      m.short_ids[1] = m.short_ids[0];
+      assert_matches!(BlockReconstructor::new(&m, false), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut m = msg.clone();
+      m.prefilled_txs[0].index = 3;
+      assert_matches!(BlockReconstructor::new(&m, false), Err(crate::Error::BitcoinProtocol(_)));
+
+      // duplicated or descending prefilled indexes
+      let mut m = msg.clone();
+      m.short_ids.pop();
+      m.prefilled_txs.push(m.prefilled_txs[0].clone());
+      assert_matches!(BlockReconstructor::new(&m, false), Err(crate::Error::BitcoinProtocol(_)));
+      let mut m = msg.clone();
+      m.short_ids.pop();
+      m.prefilled_txs.insert(0, PrefilledTx { index:2, tx:block.txs[2].clone() });
+      assert_matches!(BlockReconstructor::new(&m, false), Err(crate::Error::BitcoinProtocol(_)));
+
+      let m = CmpctBlockMessage::default();
+      assert_matches!(BlockReconstructor::new(&m, false), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut m = msg.clone();
+      m.short_ids = (0..(std::u16::MAX as u64)).collect();
+      assert_matches!(BlockReconstructor::new(&m, false), Err(crate::Error::BitcoinProtocol(_)));
+      m.short_ids.pop();
+      assert_matches!(BlockReconstructor::new(&m, false), Ok(_));
+   }
+
+   #[test]
+   fn test_collision_in_candidates() {
+      let block = new_block(3);
+      let msg = CmpctBlockMessage::new(&block, 7, false).unwrap();
+      let mut r = BlockReconstructor::new(&msg, false).unwrap();
+      // pretend another tx has the same short id as txs[1]
+      let i = *r.short_ids.get(&r.short_id(&block.txs[1]).unwrap()).unwrap();
+      let fake = new_tx(77);
+      let fake_id = r.short_id(&fake).unwrap();
+      r.short_ids.insert(fake_id, i);
+      r.fill_from(vec![block.txs[1].clone(), fake, block.txs[2].clone()].iter()).unwrap();
+      assert_eq!(r.missing(), vec![1]);
+   }
+}
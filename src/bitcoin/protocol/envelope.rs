@@ -0,0 +1,299 @@
+use super::apriori::{COMMAND_LENGTH, MAX_MESSAGE_LENGTH};
+use super::MessageHeader;
+use super::message::{
+   Message,
+   VersionMessage, VerAckMessage, AddrMessage, InvMessage, GetDataMessage, MerkleBlockMessage,
+   GetBlocksMessage, GetHeadersMessage, TxMessage, HeadersMessage, BlockMessage, GetAddrMessage,
+   MemPoolMessage, PingMessage, PongMessage, AlertMessage, NotFoundMessage, FilterLoadMessage,
+   FilterAddMessage, FilterClearMessage, RejectMessage, SendHeadersMessage,
+   WtxidRelayMessage, SendAddrV2Message, AddrV2Message,
+   SendCmpctMessage, CmpctBlockMessage, GetBlockTxnMessage, BlockTxnMessage,
+   FeeFilterMessage, GetCFiltersMessage, CFilterMessage, GetCFHeadersMessage, CFHeadersMessage,
+   GetCFCheckptMessage, CFCheckptMessage,
+};
+use crate::bitcoin::serialize::{
+   Medium,
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+
+pub const HEADER_SIZE:usize = 24;
+
+/// first 4 bytes of dhash256 of the payload
+pub fn checksum(payload:&[u8]) -> u32 {
+   let h = crate::ui::create_dhash256().u8_to_u8(payload);
+   (h[0] as u32) | (h[1] as u32) << 8 | (h[2] as u32) << 16 | (h[3] as u32) << 24
+}
+
+/// printable ascii padded with NUL
+pub fn is_valid_command(command:&[u8; COMMAND_LENGTH]) -> bool {
+   let len = command.iter().position(|&c| c == 0).unwrap_or(COMMAND_LENGTH);
+   command[..len].iter().all(|&c| 0x20 <= c && c <= 0x7e) && command[len..].iter().all(|&c| c == 0)
+}
+
+macro_rules! def_network_message {
+   ( $( ($v:ident, $t:ident) ),* ,) => {
+      #[derive(Debug,Clone)]
+      pub enum NetworkMessage {
+         $( $v($t), )*
+         /// a message of unsupported command, which is kept as the raw payload
+         Unknown([u8; COMMAND_LENGTH], Vec<u8>),
+      }
+      impl NetworkMessage {
+         pub fn command(&self) -> [u8; COMMAND_LENGTH] {
+            match *self {
+               $( NetworkMessage::$v(_) => $t::COMMAND, )*
+               NetworkMessage::Unknown(ref c, _) => *c,
+            }
+         }
+         fn serialize_payload<W: std::io::Write>(&self, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+            match *self {
+               $( NetworkMessage::$v(ref m) => m.serialize(&(), e, ws), )*
+               NetworkMessage::Unknown(_, ref p) => e.serialize_octets(ws, &p[..]),
+            }
+         }
+         fn deserialize_payload(command:&[u8; COMMAND_LENGTH], d:&BitcoinDeserializer, payload:&[u8]) -> crate::Result<Self> {
+            let mut rs = payload;
+            $(
+               if *command == $t::COMMAND {
+                  let mut m = $t::default();
+                  let _ = m.deserialize(&(), d, &mut rs)?;
+                  return Ok(NetworkMessage::$v(m));
+               }
+            )*
+            Ok(NetworkMessage::Unknown(*command, payload.to_vec()))
+         }
+      }
+      $(
+         impl From<$t> for NetworkMessage {
+            fn from(m:$t) -> Self { NetworkMessage::$v(m) }
+         }
+      )*
+   }
+}
+
+def_network_message! {
+   (Version,     VersionMessage),
+   (VerAck,      VerAckMessage),
+   (Addr,        AddrMessage),
+   (Inv,         InvMessage),
+   (GetData,     GetDataMessage),
+   (MerkleBlock, MerkleBlockMessage),
+   (GetBlocks,   GetBlocksMessage),
+   (GetHeaders,  GetHeadersMessage),
+   (Tx,          TxMessage),
+   (Headers,     HeadersMessage),
+   (Block,       BlockMessage),
+   (GetAddr,     GetAddrMessage),
+   (MemPool,     MemPoolMessage),
+   (Ping,        PingMessage),
+   (Pong,        PongMessage),
+   (Alert,       AlertMessage),
+   (NotFound,    NotFoundMessage),
+   (FilterLoad,  FilterLoadMessage),
+   (FilterAdd,   FilterAddMessage),
+   (FilterClear, FilterClearMessage),
+   (Reject,      RejectMessage),
+   (SendHeaders, SendHeadersMessage),
+   (WtxidRelay,  WtxidRelayMessage),
+   (SendAddrV2,  SendAddrV2Message),
+   (AddrV2,      AddrV2Message),
+   (SendCmpct,   SendCmpctMessage),
+   (CmpctBlock,  CmpctBlockMessage),
+   (GetBlockTxn, GetBlockTxnMessage),
+   (BlockTxn,    BlockTxnMessage),
+   (FeeFilter,   FeeFilterMessage),
+   (GetCFilters, GetCFiltersMessage),
+   (CFilter,     CFilterMessage),
+   (GetCFHeaders, GetCFHeadersMessage),
+   (CFHeaders,   CFHeadersMessage),
+   (GetCFCheckpt, GetCFCheckptMessage),
+   (CFCheckpt,   CFCheckptMessage),
+}
+
+impl std::fmt::Display for NetworkMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      let c = self.command();
+      let len = c.iter().position(|&b| b == 0).unwrap_or(COMMAND_LENGTH);
+      write!(f, "NetworkMessage({})", String::from_utf8_lossy(&c[..len]))
+   }
+}
+
+/// Frames messages with the message header of the network.
+#[derive(Debug,Clone)]
+pub struct Envelope {
+   pub magic: u32,
+   medium:    Medium,
+}
+
+impl Envelope {
+   pub fn new(magic:u32) -> Self {
+      Envelope { magic:magic, medium:Medium::default().set_net() }
+   }
+   /// protocol version negotiated with the peer
+   pub fn set_version(&mut self, version:i32) {
+      self.medium = self.medium.clone().set_version(version);
+   }
+   pub fn medium(&self) -> &Medium {
+      &self.medium
+   }
+
+   pub fn encode<W: std::io::Write>(&self, msg:&NetworkMessage, ws:&mut W) -> crate::Result<usize> {
+      let e = BitcoinSerializer::new(&self.medium);
+      let mut payload = Vec::<u8>::new();
+      let _ = msg.serialize_payload(&e, &mut payload)?;
+      if MAX_MESSAGE_LENGTH < payload.len() {
+         raise_protocol_error!(format!("message is too large: {}", payload.len()));
+      }
+      let header = MessageHeader {
+         magic:    self.magic,
+         command:  msg.command(),
+         length:   payload.len() as u32,
+         checksum: checksum(&payload[..]),
+      };
+      let mut r = header.serialize(&(), &e, ws)?;
+      ws.write_all(&payload[..])?;
+      r += payload.len();
+      Ok(r)
+   }
+
+   /// Reads a message header and the payload from the blocking reader.
+   pub fn decode<R: std::io::Read>(&self, rs:&mut R) -> crate::Result<NetworkMessage> {
+      let mut buf = [0u8; HEADER_SIZE];
+      rs.read_exact(&mut buf[..])?;
+      let header = self.decode_header(&buf[..])?;
+      let mut payload = vec![0u8; header.length as usize];
+      rs.read_exact(&mut payload[..])?;
+      self.decode_payload(&header, &payload[..])
+   }
+
+   /// Checks the magic, the command and the length of the header.
+   pub fn decode_header(&self, buf:&[u8]) -> crate::Result<MessageHeader> {
+      let d = BitcoinDeserializer::new(&self.medium);
+      let mut header = MessageHeader::default();
+      let mut rs = buf;
+      let _ = header.deserialize(&(), &d, &mut rs)?;
+      if header.magic != self.magic {
+         raise_protocol_error!(format!("magic mismatch: {:08x}", header.magic));
+      }
+      if !is_valid_command(&header.command) {
+         raise_protocol_error!(format!("invalid command: {:?}", header.command));
+      }
+      if MAX_MESSAGE_LENGTH < header.length as usize {
+         raise_protocol_error!(format!("message is too large: {}", header.length));
+      }
+      Ok(header)
+   }
+
+   /// Checks the checksum and deserializes the payload by the command.
+   pub fn decode_payload(&self, header:&MessageHeader, payload:&[u8]) -> crate::Result<NetworkMessage> {
+      if payload.len() != header.length as usize {
+         raise_protocol_error!(format!("payload length mismatch: {} but {}", header.length, payload.len()));
+      }
+      if checksum(payload) != header.checksum {
+         raise_protocol_error!(format!("checksum mismatch: {:08x}", header.checksum));
+      }
+      let d = BitcoinDeserializer::new(&self.medium);
+      NetworkMessage::deserialize_payload(&header.command, &d, payload)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::utils::h2b;
+
+   fn mainnet() -> Envelope {
+      let mut env = Envelope::new(crate::bitcoin::presets::bitcoin_mainnet::CHAIN.magic);
+      env.set_version(crate::bitcoin::protocol::apriori::PROTOCOL_VERSION);
+      env
+   }
+
+   #[test]
+   fn test_encode() {
+      let mut v = Vec::<u8>::new();
+      assert_matches!(mainnet().encode(&VerAckMessage.into(), &mut v), Ok(24));
+      assert_eq!(&v[..], &h2b("f9beb4d976657261636b000000000000000000005df6e0e2").unwrap()[..]);
+
+      let mut v = Vec::<u8>::new();
+      assert_matches!(mainnet().encode(&PingMessage { nonce:0x0102030405060708 }.into(), &mut v), Ok(32));
+      assert_eq!(&v[..], &h2b("f9beb4d970696e670000000000000000080000003b5a75130807060504030201").unwrap()[..]);
+   }
+
+   #[test]
+   fn test_decode() {
+      let env = mainnet();
+      let msgs:Vec<NetworkMessage> = vec![
+         VersionMessage { version:70012, nonce:1, start_height:100, relay:true, ..Default::default() }.into(),
+         VerAckMessage.into(),
+         PingMessage { nonce:0x0102030405060708 }.into(),
+         SendCmpctMessage { announce:true, version:2 }.into(),
+         NetworkMessage::Unknown(*b"sendfoo\0\0\0\0\0", vec![0, 1, 0, 0, 0, 0, 0, 0, 0]),
+      ];
+      let mut v = Vec::<u8>::new();
+      for m in msgs.iter() {
+         let _ = env.encode(m, &mut v).unwrap();
+      }
+      let mut rs = &v[..];
+      assert_matches!(env.decode(&mut rs), Ok(NetworkMessage::Version(ref m)) if m.version == 70012 && m.nonce == 1 && m.start_height == 100 && m.relay);
+      assert_matches!(env.decode(&mut rs), Ok(NetworkMessage::VerAck(_)));
+      assert_matches!(env.decode(&mut rs), Ok(NetworkMessage::Ping(ref m)) if m.nonce == 0x0102030405060708);
+      assert_matches!(env.decode(&mut rs), Ok(NetworkMessage::SendCmpct(ref m)) if m.announce && m.version == 2);
+      assert_matches!(env.decode(&mut rs), Ok(NetworkMessage::Unknown(ref c, ref p)) if &c[..7] == b"sendfoo" && p.len() == 9);
+      assert_matches!(env.decode(&mut rs), Err(crate::Error::Io(_)));
+   }
+
+   #[test]
+   fn test_decode_invalid() {
+      let env = mainnet();
+      let mut v = Vec::<u8>::new();
+      let _ = env.encode(&PingMessage { nonce:1 }.into(), &mut v).unwrap();
+
+      let testnet = Envelope::new(crate::bitcoin::presets::bitcoin_testnet::CHAIN.magic);
+      assert_matches!(testnet.decode(&mut &v[..]), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut w = v.clone();
+      w[HEADER_SIZE] ^= 1;
+      assert_matches!(env.decode(&mut &w[..]), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut w = v.clone();
+      w[4] = 0x01;
+      assert_matches!(env.decode(&mut &w[..]), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut w = v.clone();
+      w[16..20].copy_from_slice(&[0x01, 0x09, 0x3d, 0x00]); // 4000001
+      assert_matches!(env.decode(&mut &w[..]), Err(crate::Error::BitcoinProtocol(_)));
+   }
+
+   #[test]
+   fn test_decode_huge_count() {
+      let env = mainnet();
+      let command = |name:&str| {
+         let mut c = [0u8; COMMAND_LENGTH];
+         c[..name.len()].copy_from_slice(name.as_bytes());
+         c
+      };
+      let tx = "020000000111111111111111111111111111111111111111111111111111111111111111110000000000ffffffff01905f010000000000160014222222222222222222222222222222222222222200000000";
+      let mut v = Vec::<u8>::new();
+      let _ = env.encode(&NetworkMessage::Unknown(command("tx"), h2b(tx).unwrap().to_vec()), &mut v).unwrap();
+      assert_matches!(env.decode(&mut &v[..]), Ok(NetworkMessage::Tx(_)));
+
+      let cases:&[(&str, String)] = &[
+         // tx with the count of the inputs, over MAX_SIZE and MAX_SIZE without the data
+         ("tx", format!("01000000{}", "ffffffffffffffffff")),
+         ("tx", format!("01000000{}", "fe00000002")),
+         // tx with the script length
+         ("tx", format!("0100000001{}00000000{}", "00".repeat(32), "ffffffffffffffffff")),
+         ("block", format!("{}{}", "00".repeat(80), "fe00000002")),
+         ("blocktxn", format!("{}{}", "00".repeat(32), "ffffffffffffffffff")),
+      ];
+      for &(name, ref payload) in cases.iter() {
+         let mut v = Vec::<u8>::new();
+         let msg = NetworkMessage::Unknown(command(name), h2b(payload.as_str()).unwrap().to_vec());
+         let _ = env.encode(&msg, &mut v).unwrap();
+         assert_matches!(env.decode(&mut &v[..]), Err(_));
+      }
+   }
+}
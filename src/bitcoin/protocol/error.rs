@@ -0,0 +1,15 @@
+def_error! { ProtocolError }
+
+#[macro_export]
+macro_rules! protocol_error {
+   ($m:expr) => {
+      crate::bitcoin::protocol::ProtocolError::new($m, 0)
+   }
+}
+
+#[macro_export]
+macro_rules! raise_protocol_error {
+   ($m:expr) => {
+      Err( $crate::protocol_error!($m) )?
+   }
+}
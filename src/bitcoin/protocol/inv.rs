@@ -1,32 +1,96 @@
 use std;
 use crate::bitcoin::datatypes::UInt256;
 
-#[derive(Debug,Clone,PartialEq)]
+pub const MSG_TX:u32             = 1;
+pub const MSG_BLOCK:u32          = 2;
+pub const MSG_FILTERED_BLOCK:u32 = 3;
+pub const MSG_CMPCT_BLOCK:u32    = 4;
+pub const MSG_WTX:u32            = 5;
+pub const MSG_WITNESS_FLAG:u32   = 1 << 30;
+pub const MSG_WITNESS_TX:u32     = MSG_TX | MSG_WITNESS_FLAG;
+pub const MSG_WITNESS_BLOCK:u32  = MSG_BLOCK | MSG_WITNESS_FLAG;
+pub const MSG_FILTERED_WITNESS_BLOCK:u32 = MSG_FILTERED_BLOCK | MSG_WITNESS_FLAG;
+
+/// Unknown types are kept as is so that they can be relayed or answered by notfound.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
 pub enum InvType {
-   Unknown       = 0,
-   Tx            = 1,
-   Block         = 2,
-   FilteredBlock = 3,
+   Tx,
+   Block,
+   FilteredBlock,
+   CmpctBlock,
+   Wtx,
+   WitnessTx,
+   WitnessBlock,
+   FilteredWitnessBlock,
+   Unknown(u32),
 }
 
 impl Default for InvType {
-   fn default() -> Self { InvType::Unknown }
+   fn default() -> Self { InvType::Unknown(0) }
 }
 
 impl std::fmt::Display for InvType {
    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
       match *self {
-         InvType::Tx            => write!(f, "tx"),
-         InvType::Block         => write!(f, "block"),
-         InvType::FilteredBlock => write!(f, "filtered"),
-         _ => write!(f, "{}", *self),
+         InvType::Tx                   => write!(f, "tx"),
+         InvType::Block                => write!(f, "block"),
+         InvType::FilteredBlock        => write!(f, "filtered"),
+         InvType::CmpctBlock           => write!(f, "cmpct"),
+         InvType::Wtx                  => write!(f, "wtx"),
+         InvType::WitnessTx            => write!(f, "witness-tx"),
+         InvType::WitnessBlock         => write!(f, "witness-block"),
+         InvType::FilteredWitnessBlock => write!(f, "witness-filtered"),
+         InvType::Unknown(v)           => write!(f, "unknown({:x})", v),
       }
    }
 }
 impl InvType {
+   pub fn from_u32(v:u32) -> Self {
+      match v {
+         MSG_TX                     => InvType::Tx,
+         MSG_BLOCK                  => InvType::Block,
+         MSG_FILTERED_BLOCK         => InvType::FilteredBlock,
+         MSG_CMPCT_BLOCK            => InvType::CmpctBlock,
+         MSG_WTX                    => InvType::Wtx,
+         MSG_WITNESS_TX             => InvType::WitnessTx,
+         MSG_WITNESS_BLOCK          => InvType::WitnessBlock,
+         MSG_FILTERED_WITNESS_BLOCK => InvType::FilteredWitnessBlock,
+         _ => InvType::Unknown(v),
+      }
+   }
+   pub fn to_u32(&self) -> u32 {
+      match *self {
+         InvType::Tx                   => MSG_TX,
+         InvType::Block                => MSG_BLOCK,
+         InvType::FilteredBlock        => MSG_FILTERED_BLOCK,
+         InvType::CmpctBlock           => MSG_CMPCT_BLOCK,
+         InvType::Wtx                  => MSG_WTX,
+         InvType::WitnessTx            => MSG_WITNESS_TX,
+         InvType::WitnessBlock         => MSG_WITNESS_BLOCK,
+         InvType::FilteredWitnessBlock => MSG_FILTERED_WITNESS_BLOCK,
+         InvType::Unknown(v)           => v,
+      }
+   }
    pub fn is_tx(&self)             -> bool { *self == InvType::Tx }
    pub fn is_block(&self)          -> bool { *self == InvType::Block }
    pub fn is_filtered_block(&self) -> bool { *self == InvType::FilteredBlock }
+   pub fn is_cmpct_block(&self)    -> bool { *self == InvType::CmpctBlock }
+   pub fn is_wtx(&self)            -> bool { *self == InvType::Wtx }
+   pub fn is_unknown(&self)        -> bool { match *self { InvType::Unknown(_) => true, _ => false } }
+   pub fn is_witness(&self)        -> bool { self.to_u32() & MSG_WITNESS_FLAG != 0 }
+   /// The type with the witness flag cleared. e.g. WitnessTx -> Tx
+   pub fn without_witness(&self)   -> Self { Self::from_u32(self.to_u32() & !MSG_WITNESS_FLAG) }
+   /// any of tx, wtx and witness-tx
+   pub fn is_any_tx(&self) -> bool {
+      match self.without_witness() { InvType::Tx | InvType::Wtx => true, _ => false }
+   }
+   /// any of block, filtered, cmpct and their witness variants
+   pub fn is_any_block(&self) -> bool {
+      match self.without_witness() {
+         InvType::Block | InvType::FilteredBlock | InvType::CmpctBlock => true,
+         _ => false,
+      }
+   }
 }
 
 
@@ -51,6 +115,9 @@ impl Inv {
    pub fn new_tx(hash: UInt256)             -> Self { Self::new(InvType::Tx, hash) }
    pub fn new_block(hash: UInt256)          -> Self { Self::new(InvType::Block, hash) }
    pub fn new_filtered_block(hash: UInt256) -> Self { Self::new(InvType::FilteredBlock, hash) }
+   pub fn new_wtx(hash: UInt256)            -> Self { Self::new(InvType::Wtx, hash) }
+   pub fn new_witness_tx(hash: UInt256)     -> Self { Self::new(InvType::WitnessTx, hash) }
+   pub fn new_witness_block(hash: UInt256)  -> Self { Self::new(InvType::WitnessBlock, hash) }
 }
 
 use crate::bitcoin::serialize::{
@@ -62,13 +129,7 @@ use crate::bitcoin::serialize::{
 impl BitcoinSerializee for InvType {
    type P = ();
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
-      let tmp:u32 = match *self {
-         InvType::Tx => 1,
-         InvType::Block => 2,
-         InvType::FilteredBlock => 3,
-         _ => raise_serialize_error!("malformed inv type"),
-      };
-      e.serialize_u32le(ws, tmp)
+      e.serialize_u32le(ws, self.to_u32())
    }
 }
 impl BitcoinDeserializee for InvType {
@@ -77,12 +138,7 @@ impl BitcoinDeserializee for InvType {
       let mut r:usize = 0;
       let mut tmp:u32 = 0;
       r += d.deserialize_u32le(rs, &mut tmp)?;
-      *self = match tmp {
-         1 => InvType::Tx,
-         2 => InvType::Block,
-         3 => InvType::FilteredBlock,
-         _ => raise_serialize_error!("unexpected inv value"),
-      };
+      *self = InvType::from_u32(tmp);
       Ok(r)
    }
 }
@@ -105,3 +161,22 @@ impl BitcoinDeserializee for Inv {
       Ok(r)
    }
 }
+
+#[test]
+fn test_inv_type() {
+   use crate::utils::h2b;
+   let inv = Inv::new_witness_tx(UInt256::default());
+   let ser = crate::ui::bitcoin::serialize(&inv, &()).unwrap();
+   assert_eq!(&ser[0..4], &h2b("01000040").unwrap()[..]);
+   assert!(inv.invtype.is_witness());
+   assert!(inv.invtype.is_any_tx());
+   assert_eq!(inv.invtype.without_witness(), InvType::Tx);
+
+   let mut ser = ser.to_vec();
+   ser[0] = 0x07;
+   let mut inv = Inv::default();
+   let _ = crate::ui::bitcoin::deserialize(&ser[..], &(), &mut inv).unwrap();
+   assert_eq!(inv.invtype, InvType::Unknown(0x40000007));
+   assert!(!inv.invtype.is_any_tx());
+   assert_eq!(&crate::ui::bitcoin::serialize(&inv, &()).unwrap()[..], &ser[..]);
+}
@@ -0,0 +1,45 @@
+use std;
+use super::super::{ NetworkAddressV2 };
+
+/// BIP155: sent instead of addr to the peer which sent sendaddrv2
+#[derive(Debug,Default,Clone)]
+pub struct AddrV2Message {
+   pub addrs : Vec<NetworkAddressV2>,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for AddrV2Message {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x61, 0x64, 0x64, 0x72, 0x76, 0x32, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00];
+}
+
+impl std::fmt::Display for AddrV2Message {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "AddrV2(len={})", self.addrs.len())
+   }
+}
+
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for AddrV2Message {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      use super::super::apriori::MAX_ADDR_SIZE;
+      r += e.serialize_var_array(&(), ws, &self.addrs, MAX_ADDR_SIZE)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for AddrV2Message {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      use super::super::apriori::MAX_ADDR_SIZE;
+      r += d.deserialize_var_array(&(), rs, &mut self.addrs, MAX_ADDR_SIZE)?;
+      Ok(r)
+   }
+}
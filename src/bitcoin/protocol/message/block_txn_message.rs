@@ -0,0 +1,56 @@
+use std;
+use crate::bitcoin::datatypes::{UInt256, Tx};
+
+/// BIP152: the transactions requested by getblocktxn, in the order of the request
+#[derive(Debug,Default,Clone)]
+pub struct BlockTxnMessage {
+   pub block_hash: UInt256,
+   pub txs:        Vec<Tx>,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for BlockTxnMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x62, 0x6c, 0x6f, 0x63, 0x6b, 0x74, 0x78, 0x6e, 0x00, 0x00, 0x00, 0x00];
+}
+
+impl std::fmt::Display for BlockTxnMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "BlockTxn(block={}, txs={})", self.block_hash, self.txs.len())
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for BlockTxnMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += self.block_hash.serialize(&(), e, ws)?;
+      r += e.serialize_var_array(&(), ws, &self.txs[..], std::usize::MAX)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for BlockTxnMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      use super::super::apriori::MAX_BLOCK_TX_COUNT;
+      r += self.block_hash.deserialize(&(), d, rs)?;
+      r += d.deserialize_var_array(&(), rs, &mut self.txs, MAX_BLOCK_TX_COUNT)?;
+      Ok(r)
+   }
+}
+
+#[test]
+fn test_block_txn_message_too_many() {
+   use crate::bitcoin::serialize::Medium;
+   let med = Medium::default().set_net();
+   let mut m = BlockTxnMessage::default();
+   // 100001 txs
+   let v = [&[0u8;32][..], &[0xfe, 0xa1, 0x86, 0x01, 0x00][..]].concat();
+   assert_matches!(m.deserialize(&(), &BitcoinDeserializer::new(&med), &mut &v[..]), Err(crate::Error::BitcoinDeserialize(_)));
+}
@@ -0,0 +1,59 @@
+use std;
+use crate::bitcoin::datatypes::UInt256;
+
+/// BIP157: the filter headers at every 1000 blocks
+#[derive(Debug,Default,Clone)]
+pub struct CFCheckptMessage {
+   pub filter_type:    u8,
+   pub stop_hash:      UInt256,
+   pub filter_headers: Vec<UInt256>,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for CFCheckptMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x63, 0x66, 0x63, 0x68, 0x65, 0x63, 0x6b, 0x70, 0x74, 0x00, 0x00, 0x00];
+}
+
+impl std::fmt::Display for CFCheckptMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "CFCheckpt(type={}, stop={}, headers={})", self.filter_type, self.stop_hash, self.filter_headers.len())
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for CFCheckptMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += e.serialize_u8(ws, self.filter_type)?;
+      r += self.stop_hash.serialize(&(), e, ws)?;
+      r += e.serialize_var_array(&(), ws, &self.filter_headers[..], std::usize::MAX)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for CFCheckptMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      use super::super::apriori::MAX_CFCHECKPT_HEADERS;
+      let mut r:usize = 0;
+      r += d.deserialize_u8(rs, &mut self.filter_type)?;
+      r += self.stop_hash.deserialize(&(), d, rs)?;
+      r += d.deserialize_var_array(&(), rs, &mut self.filter_headers, MAX_CFCHECKPT_HEADERS)?;
+      Ok(r)
+   }
+}
+
+#[test]
+fn test_cfcheckpt_message_too_many() {
+   use crate::bitcoin::serialize::Medium;
+   let med = Medium::default().set_net();
+   let mut m = CFCheckptMessage::default();
+   // 125001 headers
+   let v = [&[0u8;33][..], &[0xfe, 0x49, 0xe8, 0x01, 0x00][..]].concat();
+   assert_matches!(m.deserialize(&(), &BitcoinDeserializer::new(&med), &mut &v[..]), Err(crate::Error::BitcoinDeserialize(_)));
+}
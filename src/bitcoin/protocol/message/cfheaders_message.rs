@@ -0,0 +1,88 @@
+use std;
+use crate::bitcoin::datatypes::UInt256;
+
+/// BIP157: the filter hashes following the filter header of the previous block of start_height
+#[derive(Debug,Default,Clone)]
+pub struct CFHeadersMessage {
+   pub filter_type:        u8,
+   pub stop_hash:          UInt256,
+   pub prev_filter_header: UInt256,
+   pub filter_hashes:      Vec<UInt256>,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for CFHeadersMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x63, 0x66, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72, 0x73, 0x00, 0x00, 0x00];
+}
+
+impl CFHeadersMessage {
+   /// the filter headers chained from prev_filter_header
+   pub fn filter_headers(&self) -> Vec<UInt256> {
+      use crate::bitcoin::block_filter::compute_filter_header;
+      let mut prev = self.prev_filter_header.clone();
+      self.filter_hashes.iter().map(|h| {
+         prev = compute_filter_header(h, &prev);
+         prev.clone()
+      }).collect()
+   }
+}
+
+impl std::fmt::Display for CFHeadersMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "CFHeaders(type={}, stop={}, hashes={})", self.filter_type, self.stop_hash, self.filter_hashes.len())
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for CFHeadersMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      use super::super::apriori::MAX_GETCFHEADERS_SIZE;
+      r += e.serialize_u8(ws, self.filter_type)?;
+      r += self.stop_hash.serialize(&(), e, ws)?;
+      r += self.prev_filter_header.serialize(&(), e, ws)?;
+      r += e.serialize_var_array(&(), ws, &self.filter_hashes[..], MAX_GETCFHEADERS_SIZE)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for CFHeadersMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      use super::super::apriori::MAX_GETCFHEADERS_SIZE;
+      r += d.deserialize_u8(rs, &mut self.filter_type)?;
+      r += self.stop_hash.deserialize(&(), d, rs)?;
+      r += self.prev_filter_header.deserialize(&(), d, rs)?;
+      r += d.deserialize_var_array(&(), rs, &mut self.filter_hashes, MAX_GETCFHEADERS_SIZE)?;
+      Ok(r)
+   }
+}
+
+#[test]
+fn test_cfheaders() {
+   use crate::bitcoin::block_filter::compute_filter_header;
+   let h1 = UInt256::new(&[1u8; 32]);
+   let h2 = UInt256::new(&[2u8; 32]);
+   let m = CFHeadersMessage {
+      filter_type: 0,
+      stop_hash: UInt256::new(&[9u8; 32]),
+      prev_filter_header: UInt256::default(),
+      filter_hashes: vec![h1.clone(), h2.clone()],
+   };
+   let headers = m.filter_headers();
+   assert_eq!(headers.len(), 2);
+   assert_eq!(headers[0], compute_filter_header(&h1, &UInt256::default()));
+   assert_eq!(headers[1], compute_filter_header(&h2, &headers[0]));
+
+   let ser = crate::ui::bitcoin::serialize(&m, &()).unwrap();
+   assert_eq!(ser.len(), 1 + 32 + 32 + 1 + 64);
+   let mut m2 = CFHeadersMessage::default();
+   let _ = crate::ui::bitcoin::deserialize(&ser[..], &(), &mut m2).unwrap();
+   assert_eq!(m2.filter_headers(), headers);
+}
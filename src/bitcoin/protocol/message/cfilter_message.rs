@@ -0,0 +1,59 @@
+use std;
+use crate::bitcoin::datatypes::UInt256;
+
+/// BIP157: the encoded filter of the block
+#[derive(Debug,Default,Clone)]
+pub struct CFilterMessage {
+   pub filter_type: u8,
+   pub block_hash:  UInt256,
+   pub filter:      Vec<u8>,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for CFilterMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x63, 0x66, 0x69, 0x6c, 0x74, 0x65, 0x72, 0x00, 0x00, 0x00, 0x00, 0x00];
+}
+
+impl std::fmt::Display for CFilterMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "CFilter(type={}, block={}, len={})", self.filter_type, self.block_hash, self.filter.len())
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for CFilterMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += e.serialize_u8(ws, self.filter_type)?;
+      r += self.block_hash.serialize(&(), e, ws)?;
+      r += e.serialize_var_octets(ws, &self.filter[..], std::usize::MAX)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for CFilterMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      use super::super::apriori::MAX_MESSAGE_LENGTH;
+      let mut r:usize = 0;
+      r += d.deserialize_u8(rs, &mut self.filter_type)?;
+      r += self.block_hash.deserialize(&(), d, rs)?;
+      r += d.deserialize_var_octets(rs, &mut self.filter, MAX_MESSAGE_LENGTH)?;
+      Ok(r)
+   }
+}
+
+#[test]
+fn test_cfilter_message_too_long() {
+   use crate::bitcoin::serialize::Medium;
+   let med = Medium::default().set_net();
+   let mut m = CFilterMessage::default();
+   // 4000001 bytes
+   let v = [&[0u8;33][..], &[0xfe, 0x01, 0x09, 0x3d, 0x00][..]].concat();
+   assert_matches!(m.deserialize(&(), &BitcoinDeserializer::new(&med), &mut &v[..]), Err(crate::Error::BitcoinDeserialize(_)));
+}
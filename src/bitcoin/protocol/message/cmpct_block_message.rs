@@ -0,0 +1,107 @@
+use std;
+use crate::bitcoin::datatypes::{BlockHeader, Tx};
+
+/// a transaction sent with the cmpctblock. The index is absolute in the block.
+#[derive(Debug,Default,Clone)]
+pub struct PrefilledTx {
+   pub index: u16,
+   pub tx:    Tx,
+}
+
+/// BIP152: the block header, 6 bytes short ids of the transactions and the prefilled transactions.
+/// The indexes of the prefilled transactions are differentially encoded on the wire.
+#[derive(Debug,Default,Clone)]
+pub struct CmpctBlockMessage {
+   pub header:        BlockHeader,
+   pub nonce:         u64,
+   pub short_ids:     Vec<u64>,
+   pub prefilled_txs: Vec<PrefilledTx>,
+}
+
+pub const SHORT_ID_MASK:u64 = 0xffffffffffff;
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for CmpctBlockMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x63, 0x6d, 0x70, 0x63, 0x74, 0x62, 0x6c, 0x6f, 0x63, 0x6b, 0x00, 0x00];
+}
+
+impl CmpctBlockMessage {
+   /// number of the transactions of the block
+   pub fn block_tx_count(&self) -> usize {
+      self.short_ids.len() + self.prefilled_txs.len()
+   }
+}
+
+impl std::fmt::Display for CmpctBlockMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "CmpctBlock(header={}, short_ids={}, prefilled={})", self.header, self.short_ids.len(), self.prefilled_txs.len())
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for CmpctBlockMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += self.header.serialize(&(), e, ws)?;
+      r += e.serialize_u64le(ws, self.nonce)?;
+      r += e.serialize_var_int(ws, self.short_ids.len() as u64)?;
+      for &id in self.short_ids.iter() {
+         if SHORT_ID_MASK < id {
+            raise_serialize_error!(format!("short id exceeds 6 bytes: {:x}", id));
+         }
+         r += e.serialize_u32le(ws, id as u32)?;
+         r += e.serialize_u16le(ws, (id >> 32) as u16)?;
+      }
+      r += e.serialize_var_int(ws, self.prefilled_txs.len() as u64)?;
+      let mut next = 0u64;
+      for p in self.prefilled_txs.iter() {
+         let i = p.index as u64;
+         if i < next {
+            raise_serialize_error!(format!("prefilled indexes are not ascending: {}", i));
+         }
+         r += e.serialize_var_int(ws, i - next)?;
+         r += p.tx.serialize(&(), e, ws)?;
+         next = i + 1;
+      }
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for CmpctBlockMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += self.header.deserialize(&(), d, rs)?;
+      r += d.deserialize_u64le(rs, &mut self.nonce)?;
+      let mut len = 0u64;
+      r += d.deserialize_var_int(rs, &mut len)?;
+      self.short_ids.clear();
+      for _ in 0..len {
+         let (mut lsb, mut msb) = (0u32, 0u16);
+         r += d.deserialize_u32le(rs, &mut lsb)?;
+         r += d.deserialize_u16le(rs, &mut msb)?;
+         self.short_ids.push((msb as u64) << 32 | lsb as u64);
+      }
+      r += d.deserialize_var_int(rs, &mut len)?;
+      self.prefilled_txs.clear();
+      let mut next = 0u64;
+      for _ in 0..len {
+         let mut diff = 0u64;
+         r += d.deserialize_var_int(rs, &mut diff)?;
+         let i = next.saturating_add(diff);
+         if (std::u16::MAX as u64) < i {
+            raise_deserialize_error!(format!("prefilled index overflows: {}", i));
+         }
+         let mut p = PrefilledTx { index:i as u16, tx:Tx::default() };
+         r += p.tx.deserialize(&(), d, rs)?;
+         self.prefilled_txs.push(p);
+         next = i + 1;
+      }
+      Ok(r)
+   }
+}
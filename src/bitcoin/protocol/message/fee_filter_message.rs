@@ -0,0 +1,48 @@
+use std;
+use crate::bitcoin::datatypes::Amount;
+
+/// BIP133: the minimum fee rate in satoshis per 1000 bytes of the transactions to be announced.
+#[derive(Debug,Default,Clone)]
+pub struct FeeFilterMessage {
+   pub feerate: Amount,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for FeeFilterMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x66, 0x65, 0x65, 0x66, 0x69, 0x6c, 0x74, 0x65, 0x72, 0x00, 0x00, 0x00];
+}
+
+impl FeeFilterMessage {
+   pub fn new(feerate:Amount) -> Self {
+      FeeFilterMessage { feerate:feerate }
+   }
+   /// negative or too large values are ignored as bitcoin core does.
+   pub fn is_valid(&self) -> bool {
+      crate::bitcoin::datatypes::tx_out::money_range(self.feerate)
+   }
+}
+
+impl std::fmt::Display for FeeFilterMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "FeeFilter({})", self.feerate)
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for FeeFilterMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      e.serialize_i64le(ws, self.feerate)
+   }
+}
+impl BitcoinDeserializee for FeeFilterMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      d.deserialize_i64le(rs, &mut self.feerate)
+   }
+}
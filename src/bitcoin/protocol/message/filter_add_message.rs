@@ -26,7 +26,8 @@ impl BitcoinSerializee for FilterAddMessage {
    type P = ();
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
       let mut r:usize = 0;
-      r += e.serialize_var_octets(ws, &self.data[..], std::usize::MAX)?;
+      use crate::bitcoin::script::apriori::MAX_SCRIPT_ELEMENT_SIZE;
+      r += e.serialize_var_octets(ws, &self.data[..], MAX_SCRIPT_ELEMENT_SIZE)?;
       Ok(r)
    }
 }
@@ -34,7 +35,8 @@ impl BitcoinDeserializee for FilterAddMessage {
    type P = ();
    fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
       let mut r:usize = 0;
-      r += d.deserialize_var_octets(rs, &mut self.data, std::usize::MAX)?;
+      use crate::bitcoin::script::apriori::MAX_SCRIPT_ELEMENT_SIZE;
+      r += d.deserialize_var_octets(rs, &mut self.data, MAX_SCRIPT_ELEMENT_SIZE)?;
       Ok(r)
    }
 }
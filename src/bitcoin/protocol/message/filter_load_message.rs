@@ -29,7 +29,8 @@ impl BitcoinSerializee for FilterLoadMessage {
    type P = ();
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
       let mut r:usize = 0;
-      r += e.serialize_octets(ws, &self.data[..])?;
+      use super::super::apriori::MAX_BLOOM_FILTER_SIZE;
+      r += e.serialize_var_octets(ws, &self.data[..], MAX_BLOOM_FILTER_SIZE)?;
       r += e.serialize_u32le(ws, self.hash_funcs)?;
       r += e.serialize_u32le(ws, self.tweak)?;
       r += e.serialize_u8(ws, self.flags)?;
@@ -40,7 +41,8 @@ impl BitcoinDeserializee for FilterLoadMessage {
    type P = ();
    fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
       let mut r:usize = 0;
-      r += d.deserialize_octets(rs, &mut self.data)?;
+      use super::super::apriori::MAX_BLOOM_FILTER_SIZE;
+      r += d.deserialize_var_octets(rs, &mut self.data, MAX_BLOOM_FILTER_SIZE)?;
       r += d.deserialize_u32le(rs, &mut self.hash_funcs)?;
       r += d.deserialize_u32le(rs, &mut self.tweak)?;
       r += d.deserialize_u8(rs, &mut self.flags)?;
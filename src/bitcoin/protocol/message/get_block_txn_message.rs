@@ -0,0 +1,85 @@
+use std;
+use crate::bitcoin::datatypes::UInt256;
+
+/// BIP152: requests the transactions of the block by the absolute indexes.
+/// The indexes are differentially encoded on the wire.
+#[derive(Debug,Default,Clone)]
+pub struct GetBlockTxnMessage {
+   pub block_hash: UInt256,
+   pub indexes:    Vec<u16>,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for GetBlockTxnMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x67, 0x65, 0x74, 0x62, 0x6c, 0x6f, 0x63, 0x6b, 0x74, 0x78, 0x6e, 0x00];
+}
+
+impl std::fmt::Display for GetBlockTxnMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "GetBlockTxn(block={}, indexes={})", self.block_hash, self.indexes.len())
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for GetBlockTxnMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += self.block_hash.serialize(&(), e, ws)?;
+      r += e.serialize_var_int(ws, self.indexes.len() as u64)?;
+      let mut next = 0u64;
+      for &i in self.indexes.iter() {
+         let i = i as u64;
+         if i < next {
+            raise_serialize_error!(format!("indexes are not ascending: {}", i));
+         }
+         r += e.serialize_var_int(ws, i - next)?;
+         next = i + 1;
+      }
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for GetBlockTxnMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += self.block_hash.deserialize(&(), d, rs)?;
+      let mut len = 0u64;
+      r += d.deserialize_var_int(rs, &mut len)?;
+      self.indexes.clear();
+      let mut next = 0u64;
+      for _ in 0..len {
+         let mut diff = 0u64;
+         r += d.deserialize_var_int(rs, &mut diff)?;
+         let i = next.saturating_add(diff);
+         if (std::u16::MAX as u64) < i {
+            raise_deserialize_error!(format!("index overflows: {}", i));
+         }
+         self.indexes.push(i as u16);
+         next = i + 1;
+      }
+      Ok(r)
+   }
+}
+
+#[test]
+fn test_get_block_txn_message() {
+   use crate::bitcoin::serialize::Medium;
+   let m = GetBlockTxnMessage { block_hash:UInt256::new_null(), indexes:vec![0, 1, 5, 300] };
+   let med = Medium::default().set_net();
+   let mut v = Vec::<u8>::new();
+   assert_matches!(m.serialize(&(), &BitcoinSerializer::new(&med), &mut v), Ok(39));
+   assert_eq!(&v[32..], &[0x04, 0x00, 0x00, 0x03, 0xfd, 0x26, 0x01][..]);
+
+   let mut m2 = GetBlockTxnMessage::default();
+   assert_matches!(m2.deserialize(&(), &BitcoinDeserializer::new(&med), &mut &v[..]), Ok(39));
+   assert_eq!(m2.indexes, m.indexes);
+
+   let v = [&[0u8;32][..], &[0x02, 0xfd, 0xff, 0xff, 0x00][..]].concat();
+   assert_matches!(m2.deserialize(&(), &BitcoinDeserializer::new(&med), &mut &v[..]), Err(_));
+}
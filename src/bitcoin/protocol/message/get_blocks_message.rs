@@ -1,7 +1,7 @@
 use std;
 use crate::bitcoin::datatypes::{UInt256, BlockLocator};
 
-#[derive(Debug,Default)]
+#[derive(Debug,Default,Clone)]
 pub struct GetBlocksMessage {
    pub locator   : BlockLocator,
    pub hash_stop : UInt256,
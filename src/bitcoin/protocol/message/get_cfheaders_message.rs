@@ -0,0 +1,48 @@
+use std;
+use crate::bitcoin::datatypes::UInt256;
+
+/// BIP157: requests the filter headers of the blocks from start_height to stop_hash
+#[derive(Debug,Default,Clone)]
+pub struct GetCFHeadersMessage {
+   pub filter_type:  u8,
+   pub start_height: u32,
+   pub stop_hash:    UInt256,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for GetCFHeadersMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x67, 0x65, 0x74, 0x63, 0x66, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72, 0x73];
+}
+
+impl std::fmt::Display for GetCFHeadersMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "GetCFHeaders(type={}, start={}, stop={})", self.filter_type, self.start_height, self.stop_hash)
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for GetCFHeadersMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += e.serialize_u8(ws, self.filter_type)?;
+      r += e.serialize_u32le(ws, self.start_height)?;
+      r += self.stop_hash.serialize(&(), e, ws)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for GetCFHeadersMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += d.deserialize_u8(rs, &mut self.filter_type)?;
+      r += d.deserialize_u32le(rs, &mut self.start_height)?;
+      r += self.stop_hash.deserialize(&(), d, rs)?;
+      Ok(r)
+   }
+}
@@ -62,7 +62,49 @@ pub mod filter_clear_message;
 pub use self::filter_clear_message::FilterClearMessage;
 
 pub mod reject_message;
-pub use self::reject_message::RejectMessage;
+pub use self::reject_message::{RejectMessage, RejectCode};
 
 pub mod send_headers_message;
 pub use self::send_headers_message::SendHeadersMessage;
+
+pub mod wtxid_relay_message;
+pub use self::wtxid_relay_message::WtxidRelayMessage;
+
+pub mod send_addr_v2_message;
+pub use self::send_addr_v2_message::SendAddrV2Message;
+
+pub mod addr_v2_message;
+pub use self::addr_v2_message::AddrV2Message;
+
+pub mod send_cmpct_message;
+pub use self::send_cmpct_message::SendCmpctMessage;
+
+pub mod cmpct_block_message;
+pub use self::cmpct_block_message::{CmpctBlockMessage, PrefilledTx};
+
+pub mod get_block_txn_message;
+pub use self::get_block_txn_message::GetBlockTxnMessage;
+
+pub mod block_txn_message;
+pub use self::block_txn_message::BlockTxnMessage;
+
+pub mod fee_filter_message;
+pub use self::fee_filter_message::FeeFilterMessage;
+
+pub mod get_cfilters_message;
+pub use self::get_cfilters_message::GetCFiltersMessage;
+
+pub mod cfilter_message;
+pub use self::cfilter_message::CFilterMessage;
+
+pub mod get_cfheaders_message;
+pub use self::get_cfheaders_message::GetCFHeadersMessage;
+
+pub mod cfheaders_message;
+pub use self::cfheaders_message::CFHeadersMessage;
+
+pub mod get_cfcheckpt_message;
+pub use self::get_cfcheckpt_message::GetCFCheckptMessage;
+
+pub mod cfcheckpt_message;
+pub use self::cfcheckpt_message::CFCheckptMessage;
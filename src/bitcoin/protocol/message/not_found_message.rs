@@ -13,7 +13,11 @@ impl Message for NotFoundMessage {
 
 impl std::fmt::Display for NotFoundMessage {
    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-      write!(f, "NotFound(len={}, 0={}", self.invs.len(), self.invs[0])
+      match self.invs.len() {
+         0 => write!(f, "NotFound(len={})", self.invs.len()),
+         1 => write!(f, "NotFound(len={}, 0={})", self.invs.len(), self.invs[0]),
+         l => write!(f, "NotFound(len={}, 0={}, ...{})", self.invs.len(), self.invs[0], self.invs[l-1])
+      }
    }
 }
 
@@ -28,7 +32,8 @@ impl BitcoinSerializee for NotFoundMessage {
    type P = ();
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
       let mut r:usize = 0;
-      r += e.serialize_var_array(&(), ws, &self.invs[..], std::usize::MAX)?;
+      use super::super::apriori::MAX_INV_SIZE;
+      r += e.serialize_var_array(&(), ws, &self.invs[..], MAX_INV_SIZE)?;
       Ok(r)
    }
 }
@@ -36,7 +41,8 @@ impl BitcoinDeserializee for NotFoundMessage {
    type P = ();
    fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
       let mut r:usize = 0;
-      r += d.deserialize_var_array(&(), rs, &mut self.invs, std::usize::MAX)?;
+      use super::super::apriori::MAX_INV_SIZE;
+      r += d.deserialize_var_array(&(), rs, &mut self.invs, MAX_INV_SIZE)?;
       Ok(r)
    }
 }
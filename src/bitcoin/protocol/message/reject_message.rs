@@ -2,19 +2,82 @@ use std;
 
 //pub const MAX_REJECT_MESSAGE_LENGTH:usize = 111;
 
-const REJECT_MALFORMED:u8        = 0x01;
-const REJECT_INVALID:u8          = 0x10;
-const REJECT_OBSOLETE:u8         = 0x11;
-const REJECT_DUPLICATE:u8        = 0x12;
-const REJECT_NON_STANDARD:u8     = 0x40;
-const REJECT_DUST:u8             = 0x41;
-const REJECT_INSUFFICIENT_FEE:u8 = 0x42;
-const REJECT_CHECKPOINT:u8       = 0x43;
+pub const REJECT_MALFORMED:u8        = 0x01;
+pub const REJECT_INVALID:u8          = 0x10;
+pub const REJECT_OBSOLETE:u8         = 0x11;
+pub const REJECT_DUPLICATE:u8        = 0x12;
+pub const REJECT_NON_STANDARD:u8     = 0x40;
+pub const REJECT_DUST:u8             = 0x41;
+pub const REJECT_INSUFFICIENT_FEE:u8 = 0x42;
+pub const REJECT_CHECKPOINT:u8       = 0x43;
 
-#[derive(Debug,Clone)]
+/// BIP61 reject code. Unknown codes are kept as is.
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum RejectCode {
+   Malformed,
+   Invalid,
+   Obsolete,
+   Duplicate,
+   NonStandard,
+   Dust,
+   InsufficientFee,
+   Checkpoint,
+   Unknown(u8),
+}
+
+impl Default for RejectCode {
+   fn default() -> Self { RejectCode::Unknown(0) }
+}
+
+impl RejectCode {
+   pub fn from_u8(v:u8) -> Self {
+      match v {
+         REJECT_MALFORMED        => RejectCode::Malformed,
+         REJECT_INVALID          => RejectCode::Invalid,
+         REJECT_OBSOLETE         => RejectCode::Obsolete,
+         REJECT_DUPLICATE        => RejectCode::Duplicate,
+         REJECT_NON_STANDARD     => RejectCode::NonStandard,
+         REJECT_DUST             => RejectCode::Dust,
+         REJECT_INSUFFICIENT_FEE => RejectCode::InsufficientFee,
+         REJECT_CHECKPOINT       => RejectCode::Checkpoint,
+         _ => RejectCode::Unknown(v),
+      }
+   }
+   pub fn to_u8(&self) -> u8 {
+      match *self {
+         RejectCode::Malformed       => REJECT_MALFORMED,
+         RejectCode::Invalid         => REJECT_INVALID,
+         RejectCode::Obsolete        => REJECT_OBSOLETE,
+         RejectCode::Duplicate       => REJECT_DUPLICATE,
+         RejectCode::NonStandard     => REJECT_NON_STANDARD,
+         RejectCode::Dust            => REJECT_DUST,
+         RejectCode::InsufficientFee => REJECT_INSUFFICIENT_FEE,
+         RejectCode::Checkpoint      => REJECT_CHECKPOINT,
+         RejectCode::Unknown(v)      => v,
+      }
+   }
+}
+
+impl std::fmt::Display for RejectCode {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      match *self {
+         RejectCode::Malformed       => write!(f, "malformed"),
+         RejectCode::Invalid         => write!(f, "invalid"),
+         RejectCode::Obsolete        => write!(f, "obsolete"),
+         RejectCode::Duplicate       => write!(f, "duplicate"),
+         RejectCode::NonStandard     => write!(f, "nonstandard"),
+         RejectCode::Dust            => write!(f, "dust"),
+         RejectCode::InsufficientFee => write!(f, "insufficientfee"),
+         RejectCode::Checkpoint      => write!(f, "checkpoint"),
+         RejectCode::Unknown(v)      => write!(f, "unknown({:02x})", v),
+      }
+   }
+}
+
+#[derive(Debug,Default,Clone)]
 pub struct RejectMessage {
    pub command : String, //not [u8;12] but var_str. check https://en.bitcoin.it/wiki/Protocol_documentation#reject
-   pub code    : u8,
+   pub code    : RejectCode,
    pub reason  : String,
 }
 
@@ -25,24 +88,34 @@ impl Message for RejectMessage {
 
 impl RejectMessage {
    pub const MAX_REJECT_MESSAGE_LENGTH:usize = 111;
-   pub fn is_malformed(&self)        -> bool { self.code == REJECT_MALFORMED }
-   pub fn is_invalid(&self)          -> bool { self.code == REJECT_INVALID }
-   pub fn is_obsolete(&self)         -> bool { self.code == REJECT_OBSOLETE }
-   pub fn is_duplicate(&self)        -> bool { self.code == REJECT_DUPLICATE }
-   pub fn is_non_standard(&self)     -> bool { self.code == REJECT_NON_STANDARD }
-   pub fn is_dust(&self)             -> bool { self.code == REJECT_DUST }
-   pub fn is_insufficient_fee(&self) -> bool { self.code == REJECT_INSUFFICIENT_FEE }
-   pub fn is_checkpoint(&self)       -> bool { self.code == REJECT_CHECKPOINT }
+   pub fn is_malformed(&self)        -> bool { self.code == RejectCode::Malformed }
+   pub fn is_invalid(&self)          -> bool { self.code == RejectCode::Invalid }
+   pub fn is_obsolete(&self)         -> bool { self.code == RejectCode::Obsolete }
+   pub fn is_duplicate(&self)        -> bool { self.code == RejectCode::Duplicate }
+   pub fn is_non_standard(&self)     -> bool { self.code == RejectCode::NonStandard }
+   pub fn is_dust(&self)             -> bool { self.code == RejectCode::Dust }
+   pub fn is_insufficient_fee(&self) -> bool { self.code == RejectCode::InsufficientFee }
+   pub fn is_checkpoint(&self)       -> bool { self.code == RejectCode::Checkpoint }
 }
 
 impl RejectMessage {
-   pub fn new<T:Message>(_: &T, code_:u8, reason_:&String) -> Self {
+   pub fn new<T:Message>(_: &T, code_:RejectCode, reason_:&String) -> Self {
+      let len = T::COMMAND.iter().position(|&b| b == 0).unwrap_or(COMMAND_LENGTH);
       RejectMessage {
-         command: unsafe{std::str::from_utf8_unchecked(&T::COMMAND[..]).to_string()},
+         command: String::from_utf8_lossy(&T::COMMAND[..len]).into_owned(),
          code:    code_,
          reason:  reason_.clone(),
       }
    }
+   /// rejects the message with the code and the reason of the validation error
+   pub fn from_validation_error<T:Message>(m: &T, err:&crate::bitcoin::validation::ValidationError) -> Self {
+      let code = err.reject_code().unwrap_or(RejectCode::Invalid);
+      let reason = match err.get_code() {
+         Some(c) => c.reason().to_string(),
+         None    => format!("{}", err),
+      };
+      Self::new(m, code, &reason)
+   }
 }
 
 impl std::fmt::Display for RejectMessage {
@@ -63,7 +136,7 @@ impl BitcoinSerializee for RejectMessage {
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
       let mut r:usize = 0;
       r += e.serialize_var_string(ws, self.command.as_str(), std::usize::MAX)?;
-      r += e.serialize_u8(ws, self.code)?;
+      r += e.serialize_u8(ws, self.code.to_u8())?;
       r += e.serialize_var_string(ws, self.reason.as_str(), RejectMessage::MAX_REJECT_MESSAGE_LENGTH)?;
       Ok(r)
    }
@@ -73,9 +146,29 @@ impl BitcoinDeserializee for RejectMessage {
    fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
       let mut r:usize = 0;
       r += d.deserialize_var_string(rs, &mut self.command, std::usize::MAX)?;
-      r += d.deserialize_u8(rs, &mut self.code)?;
+      let mut code:u8 = 0;
+      r += d.deserialize_u8(rs, &mut code)?;
+      self.code = RejectCode::from_u8(code);
       r += d.deserialize_var_string(rs, &mut self.reason, RejectMessage::MAX_REJECT_MESSAGE_LENGTH)?;
       // この後に拡張データがあるが、メッセージヘッダのサイズを見ないと分からない。
       Ok(r)
    }
 }
+
+#[test]
+fn test_reject_message() {
+   use super::TxMessage;
+   let e = crate::validation_error!(TxVinEmpty);
+   let m = RejectMessage::from_validation_error(&TxMessage::default(), &e);
+   assert_eq!(m.command, "tx");
+   assert!(m.is_invalid());
+   assert_eq!(m.reason, "bad-txns-vin-empty");
+
+   let mut ser = crate::ui::bitcoin::serialize(&m, &()).unwrap().to_vec();
+   assert_eq!(ser[3], REJECT_INVALID);
+   ser[3] = 0x7f;
+   let mut m2 = RejectMessage::default();
+   let _ = crate::ui::bitcoin::deserialize(&ser[..], &(), &mut m2).unwrap();
+   assert_eq!(m2.code, RejectCode::Unknown(0x7f));
+   assert_eq!(&crate::ui::bitcoin::serialize(&m2, &()).unwrap()[..], &ser[..]);
+}
@@ -0,0 +1,44 @@
+use std;
+
+/// BIP152: announce=true requests the new blocks to be announced by cmpctblock.
+#[derive(Debug,Default,Clone)]
+pub struct SendCmpctMessage {
+   pub announce: bool,
+   pub version:  u64,
+}
+
+use super::message::{ Message, COMMAND_LENGTH };
+impl Message for SendCmpctMessage {
+   const COMMAND:[u8; COMMAND_LENGTH] = [0x73, 0x65, 0x6e, 0x64, 0x63, 0x6d, 0x70, 0x63, 0x74, 0x00, 0x00, 0x00];
+}
+
+impl std::fmt::Display for SendCmpctMessage {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "SendCmpct(announce={}, version={})", self.announce, self.version)
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+impl BitcoinSerializee for SendCmpctMessage {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += e.serialize_bool(ws, self.announce)?;
+      r += e.serialize_u64le(ws, self.version)?;
+      Ok(r)
+   }
+}
+impl BitcoinDeserializee for SendCmpctMessage {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += d.deserialize_bool(rs, &mut self.announce)?;
+      r += d.deserialize_u64le(rs, &mut self.version)?;
+      Ok(r)
+   }
+}
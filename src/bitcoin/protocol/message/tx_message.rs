@@ -1,7 +1,7 @@
 use std;
 use crate::bitcoin::datatypes::Tx;
 
-#[derive(Debug,Default)]
+#[derive(Debug,Default,Clone)]
 pub struct TxMessage {
    pub tx: Tx,
 }
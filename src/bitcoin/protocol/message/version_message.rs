@@ -93,8 +93,8 @@ impl BitcoinDeserializee for VersionMessage {
          use std::time::{UNIX_EPOCH, Duration};
          self.timestamp = UNIX_EPOCH + Duration::from_secs(t as u64);
       }
-      r += self.addr_from.deserialize(&false, d, rs)?;
       r += self.addr_recv.deserialize(&false, d, rs)?;
+      r += self.addr_from.deserialize(&false, d, rs)?;
       r += d.deserialize_u64le(rs, &mut self.nonce)?;
       {
          use super::super::apriori::MAX_SUBVERSION_LENGTH;
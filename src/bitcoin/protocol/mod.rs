@@ -1,8 +1,15 @@
+#[macro_use]
+pub mod error;
+pub use self::error::ProtocolError;
+
 pub mod apriori;
 
 pub mod network_address;
 pub use self::network_address::{NetworkAddress};
 
+pub mod network_address_v2;
+pub use self::network_address_v2::{NetworkAddressV2, AddrV2};
+
 pub mod inv;
 pub use self::inv::{InvType, Inv};
 
@@ -31,7 +38,31 @@ pub use self::message::NotFoundMessage;
 pub use self::message::FilterLoadMessage;
 pub use self::message::FilterAddMessage;
 pub use self::message::FilterClearMessage;
-pub use self::message::RejectMessage;
+pub use self::message::{RejectMessage, RejectCode};
 pub use self::message::SendHeadersMessage;
+pub use self::message::WtxidRelayMessage;
+pub use self::message::SendAddrV2Message;
+pub use self::message::AddrV2Message;
+pub use self::message::SendCmpctMessage;
+pub use self::message::{CmpctBlockMessage, PrefilledTx};
+pub use self::message::GetBlockTxnMessage;
+pub use self::message::BlockTxnMessage;
+pub use self::message::FeeFilterMessage;
+pub use self::message::{GetCFiltersMessage, CFilterMessage};
+pub use self::message::{GetCFHeadersMessage, CFHeadersMessage};
+pub use self::message::{GetCFCheckptMessage, CFCheckptMessage};
+
+pub mod envelope;
+pub use self::envelope::{Envelope, NetworkMessage};
+
+pub mod stream_decoder;
+pub use self::stream_decoder::StreamDecoder;
+
+pub mod bloom_filter;
+pub use self::bloom_filter::BloomFilter;
 
+pub mod compact_block;
+pub use self::compact_block::BlockReconstructor;
 
+pub mod peer;
+pub use self::peer::{Peer, PeerConfig, PeerState, PeerEvent};
@@ -0,0 +1,385 @@
+use std;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use super::NetworkAddress;
+
+pub const NET_IPV4:u8  = 1;
+pub const NET_IPV6:u8  = 2;
+pub const NET_TORV2:u8 = 3;
+pub const NET_TORV3:u8 = 4;
+pub const NET_I2P:u8   = 5;
+pub const NET_CJDNS:u8 = 6;
+
+pub const ADDR_IPV4_SIZE:usize  = 4;
+pub const ADDR_IPV6_SIZE:usize  = 16;
+pub const ADDR_TORV2_SIZE:usize = 10;
+pub const ADDR_TORV3_SIZE:usize = 32;
+pub const ADDR_I2P_SIZE:usize   = 32;
+pub const ADDR_CJDNS_SIZE:usize = 16;
+
+/// BIP155: maximum size of the address of any network, including unknown ones
+pub const MAX_ADDRV2_SIZE:usize = 512;
+
+const TORV3_VERSION:u8 = 3;
+const TORV3_CHECKSUM_STR:&'static [u8] = b".onion checksum";
+const I2P_SUFFIX:&'static str = ".b32.i2p";
+const ONION_SUFFIX:&'static str = ".onion";
+
+/// The host part of the BIP155 address.
+/// Addresses of a network not known (including the deprecated TorV2) are kept as is.
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub enum AddrV2 {
+   IPv4(Ipv4Addr),
+   IPv6(Ipv6Addr),
+   /// ed25519 public key
+   TorV3([u8; ADDR_TORV3_SIZE]),
+   /// sha256 of the destination
+   I2P([u8; ADDR_I2P_SIZE]),
+   /// in fc00::/8
+   Cjdns(Ipv6Addr),
+   Unknown(u8, Vec<u8>),
+}
+
+impl Default for AddrV2 {
+   fn default() -> Self { AddrV2::IPv4(Ipv4Addr::UNSPECIFIED) }
+}
+
+fn torv3_checksum(pubkey:&[u8]) -> [u8; 2] {
+   use crypto::digest::Digest;
+   let mut hasher = crypto::sha3::Sha3::sha3_256();
+   hasher.input(TORV3_CHECKSUM_STR);
+   hasher.input(pubkey);
+   hasher.input(&[TORV3_VERSION]);
+   let mut out = [0u8; 32];
+   hasher.result(&mut out);
+   [out[0], out[1]]
+}
+
+fn copy_array32(v:&[u8]) -> [u8; 32] {
+   let mut a = [0u8; 32];
+   a.copy_from_slice(v);
+   a
+}
+
+impl AddrV2 {
+   pub fn network_id(&self) -> u8 {
+      match *self {
+         AddrV2::IPv4(_)          => NET_IPV4,
+         AddrV2::IPv6(_)          => NET_IPV6,
+         AddrV2::TorV3(_)         => NET_TORV3,
+         AddrV2::I2P(_)           => NET_I2P,
+         AddrV2::Cjdns(_)         => NET_CJDNS,
+         AddrV2::Unknown(id, _)   => id,
+      }
+   }
+
+   pub fn to_bytes(&self) -> Vec<u8> {
+      match *self {
+         AddrV2::IPv4(ref a)        => a.octets().to_vec(),
+         AddrV2::IPv6(ref a)        => a.octets().to_vec(),
+         AddrV2::TorV3(ref a)       => a.to_vec(),
+         AddrV2::I2P(ref a)         => a.to_vec(),
+         AddrV2::Cjdns(ref a)       => a.octets().to_vec(),
+         AddrV2::Unknown(_, ref a)  => a.clone(),
+      }
+   }
+
+   /// Checks the size limit of the network.
+   pub fn from_bytes(id:u8, bytes:&[u8]) -> crate::Result<Self> {
+      let size = match id {
+         NET_IPV4  => ADDR_IPV4_SIZE,
+         NET_IPV6  => ADDR_IPV6_SIZE,
+         NET_TORV2 => ADDR_TORV2_SIZE,
+         NET_TORV3 => ADDR_TORV3_SIZE,
+         NET_I2P   => ADDR_I2P_SIZE,
+         NET_CJDNS => ADDR_CJDNS_SIZE,
+         _ => MAX_ADDRV2_SIZE,
+      };
+      let known = id != NET_TORV2 && size != MAX_ADDRV2_SIZE;
+      if (known && bytes.len() != size) || size < bytes.len() {
+         raise_protocol_error!(format!("invalid address size of network {}: {}", id, bytes.len()));
+      }
+      let ret = match id {
+         NET_IPV4 => AddrV2::IPv4(Ipv4Addr::new(bytes[0], bytes[1], bytes[2], bytes[3])),
+         NET_IPV6 => {
+            let mut a = [0u8; 16];
+            a.copy_from_slice(bytes);
+            AddrV2::IPv6(Ipv6Addr::from(a))
+         },
+         NET_TORV3 => AddrV2::TorV3(copy_array32(bytes)),
+         NET_I2P   => AddrV2::I2P(copy_array32(bytes)),
+         NET_CJDNS => {
+            if bytes[0] != 0xfc {
+               raise_protocol_error!(format!("cjdns address not in fc00::/8: {:02x}", bytes[0]));
+            }
+            let mut a = [0u8; 16];
+            a.copy_from_slice(bytes);
+            AddrV2::Cjdns(Ipv6Addr::from(a))
+         },
+         _ => AddrV2::Unknown(id, bytes.to_vec()),
+      };
+      Ok(ret)
+   }
+
+   /// whether it can be sent by the legacy addr message
+   pub fn is_addr_v1_compatible(&self) -> bool {
+      match *self {
+         AddrV2::IPv4(_) | AddrV2::IPv6(_) => true,
+         _ => false,
+      }
+   }
+
+   pub fn to_ip_addr(&self) -> Option<IpAddr> {
+      match *self {
+         AddrV2::IPv4(ref a) => Some(IpAddr::V4(a.clone())),
+         AddrV2::IPv6(ref a) => Some(IpAddr::V6(a.clone())),
+         _ => None,
+      }
+   }
+}
+
+impl From<IpAddr> for AddrV2 {
+   fn from(ip:IpAddr) -> Self {
+      match ip {
+         IpAddr::V4(a) => AddrV2::IPv4(a),
+         IpAddr::V6(a) => match a.to_ipv4() {
+            Some(v4) if a.segments()[5] == 0xffff => AddrV2::IPv4(v4),
+            _ => AddrV2::IPv6(a),
+         },
+      }
+   }
+}
+
+impl std::fmt::Display for AddrV2 {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      use crate::utils::base32_encode;
+      match *self {
+         AddrV2::IPv4(ref a)  => write!(f, "{}", a),
+         AddrV2::IPv6(ref a)  => write!(f, "{}", a),
+         AddrV2::TorV3(ref a) => {
+            let mut v = a.to_vec();
+            v.extend_from_slice(&torv3_checksum(&a[..]));
+            v.push(TORV3_VERSION);
+            write!(f, "{}{}", base32_encode(&v[..]), ONION_SUFFIX)
+         },
+         AddrV2::I2P(ref a)   => write!(f, "{}{}", base32_encode(&a[..]), I2P_SUFFIX),
+         AddrV2::Cjdns(ref a) => write!(f, "{}", a),
+         AddrV2::Unknown(id, ref a) => write!(f, "unknown({}):{}", id, crate::utils::b2h(&a[..])),
+      }
+   }
+}
+
+/// Parses an ip address, a TorV3 `.onion` or an I2P `.b32.i2p` host.
+/// A CJDNS address is not distinguishable from IPv6 by the string and is parsed as IPv6.
+impl std::str::FromStr for AddrV2 {
+   type Err = crate::Error;
+   fn from_str(s:&str) -> crate::Result<Self> {
+      use crate::utils::base32_decode;
+      let lower = s.to_ascii_lowercase();
+      if lower.ends_with(ONION_SUFFIX) {
+         let v = base32_decode(&lower[..(lower.len() - ONION_SUFFIX.len())])?;
+         if v.len() != ADDR_TORV3_SIZE + 3 {
+            raise_protocol_error!(format!("not a torv3 address: {}", s));
+         }
+         let (pubkey, checksum, version) = (&v[..32], &v[32..34], v[34]);
+         if version != TORV3_VERSION || checksum != &torv3_checksum(pubkey)[..] {
+            raise_protocol_error!(format!("invalid torv3 address: {}", s));
+         }
+         Ok(AddrV2::TorV3(copy_array32(pubkey)))
+      } else if lower.ends_with(I2P_SUFFIX) {
+         let host = &lower[..(lower.len() - I2P_SUFFIX.len())];
+         // 32 bytes are 52 chars without the padding
+         if host.len() != 52 {
+            raise_protocol_error!(format!("not an i2p address: {}", s));
+         }
+         let v = base32_decode(host)?;
+         Ok(AddrV2::I2P(copy_array32(&v[..])))
+      } else {
+         match s.parse::<IpAddr>() {
+            Ok(ip) => Ok(AddrV2::from(ip)),
+            Err(_) => raise_protocol_error!(format!("not an address: {}", s)),
+         }
+      }
+   }
+}
+
+/// BIP155 address used in the addrv2 message.
+#[derive(Debug,Default,Clone)]
+pub struct NetworkAddressV2 {
+   pub time:     u32,
+   pub services: u64,
+   pub addr:     AddrV2,
+   pub port:     u16,
+}
+
+impl std::fmt::Display for NetworkAddressV2 {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      match self.addr {
+         AddrV2::IPv6(_) | AddrV2::Cjdns(_) => write!(f, "addr=[{}]:{}, time={}", self.addr, self.port, self.time),
+         _ => write!(f, "addr={}:{}, time={}", self.addr, self.port, self.time),
+      }
+   }
+}
+
+impl NetworkAddressV2 {
+   /// None if the address is not IPv4 nor IPv6
+   pub fn to_network_address(&self) -> Option<NetworkAddress> {
+      self.addr.to_ip_addr().map(|ip| NetworkAddress {
+         services: self.services,
+         time:     self.time,
+         sockaddr: SocketAddr::new(ip, self.port),
+      })
+   }
+}
+
+impl <'a> From<&'a NetworkAddress> for NetworkAddressV2 {
+   fn from(a:&'a NetworkAddress) -> Self {
+      NetworkAddressV2 {
+         time:     a.time,
+         services: a.services,
+         addr:     AddrV2::from(a.sockaddr.ip()),
+         port:     a.sockaddr.port(),
+      }
+   }
+}
+
+use crate::bitcoin::serialize::{
+   Serializer as BitcoinSerializer,
+   Serializee as BitcoinSerializee,
+   Deserializer as BitcoinDeserializer,
+   Deserializee as BitcoinDeserializee,
+};
+
+impl BitcoinSerializee for NetworkAddressV2 {
+   type P = ();
+   fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += e.serialize_u32le(ws, self.time)?;
+      r += e.serialize_var_int(ws, self.services)?;
+      r += e.serialize_u8(ws, self.addr.network_id())?;
+      r += e.serialize_var_octets(ws, &self.addr.to_bytes()[..], MAX_ADDRV2_SIZE)?;
+      r += e.serialize_u16be(ws, self.port)?; //network byte order
+      Ok(r)
+   }
+}
+
+impl BitcoinDeserializee for NetworkAddressV2 {
+   type P = ();
+   fn deserialize<R: std::io::Read>(&mut self, _p:&Self::P, d:&BitcoinDeserializer, rs:&mut R) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      r += d.deserialize_u32le(rs, &mut self.time)?;
+      r += d.deserialize_var_int_unchecked(rs, &mut self.services)?;
+      {
+         let mut id = 0u8;
+         let mut bytes = Vec::<u8>::new();
+         r += d.deserialize_u8(rs, &mut id)?;
+         r += d.deserialize_var_octets(rs, &mut bytes, MAX_ADDRV2_SIZE)?;
+         self.addr = AddrV2::from_bytes(id, &bytes[..])?;
+      }
+      r += d.deserialize_u16be(rs, &mut self.port)?;
+      Ok(r)
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::utils::h2b;
+
+   const ONION:&str = "pg6mmjiyjmcrsslvykfwnntlaru7p5svn6y2ymmju6nubxndf4pscryd.onion";
+   const I2P:&str = "ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkdq.b32.i2p";
+
+   #[test]
+   fn test_parse() {
+      let a = ONION.parse::<AddrV2>().unwrap();
+      assert_matches!(a, AddrV2::TorV3(ref k) if k[..] == h2b("79bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1f").unwrap()[..]);
+      assert_eq!(a.to_string(), ONION);
+      assert_matches!(ONION.to_uppercase().parse::<AddrV2>(), Ok(AddrV2::TorV3(_)));
+
+      // checksum
+      let bad = ONION.replace("pscryd", "pscrya");
+      assert_matches!(bad.parse::<AddrV2>(), Err(_));
+      // torv2
+      assert_matches!("5wyqrzbvrdsumnok.onion".parse::<AddrV2>(), Err(_));
+
+      let a = I2P.parse::<AddrV2>().unwrap();
+      assert_matches!(a, AddrV2::I2P(ref k) if k[..] == h2b("a2894dabaec08c0051a481a6dac88b64f98232ae42d4b6fd2fa81952dfe36a87").unwrap()[..]);
+      assert_eq!(a.to_string(), I2P);
+      assert_matches!("ukeu3k5oycgaauneqgtnvselmt4yemvoilkln7jpvamvfx7dnkd.b32.i2p".parse::<AddrV2>(), Err(_));
+
+      assert_matches!("1.2.3.4".parse::<AddrV2>(), Ok(AddrV2::IPv4(_)));
+      assert_matches!("::ffff:1.2.3.4".parse::<AddrV2>(), Ok(AddrV2::IPv4(_)));
+      assert_matches!("fc00::1".parse::<AddrV2>(), Ok(AddrV2::IPv6(_)));
+      assert_matches!("example.com".parse::<AddrV2>(), Err(_));
+   }
+
+   #[test]
+   fn test_serialize() {
+      use crate::bitcoin::serialize::Medium;
+      let med = Medium::default().set_net();
+      let e = BitcoinSerializer::new(&med);
+      let d = BitcoinDeserializer::new(&med);
+
+      let a = NetworkAddressV2 {
+         time:     0x4966bc61,
+         services: 1,
+         addr:     "1.2.3.4".parse().unwrap(),
+         port:     0,
+      };
+      let mut v = Vec::<u8>::new();
+      assert_matches!(a.serialize(&(), &e, &mut v), Ok(13));
+      assert_eq!(&v[..], &h2b("61bc6649010104010203040000").unwrap()[..]);
+
+      let a = NetworkAddressV2 {
+         time:     0x83766279,
+         services: 0x800000000,
+         addr:     ONION.parse().unwrap(),
+         port:     0xf1f2,
+      };
+      let mut v = Vec::<u8>::new();
+      let _ = a.serialize(&(), &e, &mut v).unwrap();
+      assert_eq!(&v[..], &h2b("79627683ff0000000008000000042079bcc625184b05194975c28b66b66b0469f7f6556fb1ac3189a79b40dda32f1ff1f2").unwrap()[..]);
+      let mut b = NetworkAddressV2::default();
+      assert_matches!(b.deserialize(&(), &d, &mut &v[..]), Ok(n) if n == v.len());
+      assert_eq!(b.addr, a.addr);
+      assert_eq!(b.services, a.services);
+      assert_eq!(b.port, a.port);
+
+      // unknown network is kept
+      let v = h2b("0000000000aa030102030000").unwrap();
+      let mut b = NetworkAddressV2::default();
+      assert_matches!(b.deserialize(&(), &d, &mut &v[..]), Ok(12));
+      assert_eq!(b.addr, AddrV2::Unknown(0xaa, vec![1, 2, 3]));
+
+      // size mismatch of a known network
+      let v = h2b("000000000001030102030000").unwrap();
+      assert_matches!(b.deserialize(&(), &d, &mut &v[..]), Err(crate::Error::BitcoinProtocol(_)));
+      // cjdns out of fc00::/8
+      let v = h2b("00000000000610110000000000000000000000000000010000").unwrap();
+      assert_matches!(b.deserialize(&(), &d, &mut &v[..]), Err(crate::Error::BitcoinProtocol(_)));
+      // too long
+      let mut v = h2b("0000000000aafd0102").unwrap().to_vec();
+      v.extend(vec![0u8; 513]);
+      assert_matches!(b.deserialize(&(), &d, &mut &v[..]), Err(_));
+   }
+
+   #[test]
+   fn test_convert() {
+      use std::str::FromStr;
+      let a = NetworkAddress {
+         services: 1,
+         time:     2,
+         sockaddr: SocketAddr::from_str("10.0.0.1:8333").unwrap(),
+      };
+      let b = NetworkAddressV2::from(&a);
+      assert_eq!(b.addr, AddrV2::IPv4(Ipv4Addr::new(10, 0, 0, 1)));
+      assert_eq!(b.port, 8333);
+      let c = b.to_network_address().unwrap();
+      assert_eq!(c.sockaddr, a.sockaddr);
+      assert_eq!(c.services, 1);
+      assert_eq!(c.time, 2);
+
+      let b = NetworkAddressV2 { addr:I2P.parse().unwrap(), ..Default::default() };
+      assert!(!b.addr.is_addr_v1_compatible());
+      assert!(b.to_network_address().is_none());
+   }
+}
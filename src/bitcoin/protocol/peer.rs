@@ -0,0 +1,339 @@
+use std::collections::VecDeque;
+use super::apriori::{
+   PROTOCOL_VERSION, MIN_PEER_PROTO_VERSION, SENDHEADERS_VERSION, WTXID_RELAY_VERSION, NODE_NONE,
+};
+use super::{
+   NetworkAddress, NetworkMessage,
+   VersionMessage, VerAckMessage, PongMessage, SendHeadersMessage, WtxidRelayMessage, SendAddrV2Message,
+};
+
+#[derive(Debug,Clone)]
+pub struct PeerConfig {
+   pub version:      i32,
+   pub services:     u64,
+   pub user_agent:   String,
+   pub start_height: i32,
+   pub relay:        bool,
+   /// to detect a connection to ourself
+   pub nonce:        u64,
+   pub addr_recv:    NetworkAddress,
+   pub addr_from:    NetworkAddress,
+   /// wants block announcements by headers (BIP130)
+   pub send_headers: bool,
+   /// wants tx announcements by wtxid (BIP339)
+   pub wtxid_relay:  bool,
+   /// wants addrv2 messages (BIP155)
+   pub addr_v2:      bool,
+}
+
+impl Default for PeerConfig {
+   fn default() -> Self {
+      PeerConfig {
+         version:      PROTOCOL_VERSION,
+         services:     NODE_NONE,
+         user_agent:   String::from(super::apriori::USER_AGENT),
+         start_height: 0,
+         relay:        false,
+         nonce:        0,
+         addr_recv:    NetworkAddress::default(),
+         addr_from:    NetworkAddress::default(),
+         send_headers: true,
+         wtxid_relay:  true,
+         addr_v2:      true,
+      }
+   }
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum PeerState {
+   Init,
+   /// our version is sent and waiting for theirs
+   VersionSent,
+   /// versions are exchanged and waiting for their verack
+   VerAckWaiting,
+   Established,
+}
+
+#[derive(Debug,Clone)]
+pub enum PeerEvent {
+   /// the handshake is completed
+   Established,
+   /// a message received after the handshake, which is not handled by the state machine
+   Message(NetworkMessage),
+}
+
+/// Handshake of a connection without IO. Received messages are put by receive()
+/// and the messages to be sent are taken by pop_outgoing().
+#[derive(Debug,Clone)]
+pub struct Peer {
+   config:   PeerConfig,
+   inbound:  bool,
+   state:    PeerState,
+   their:    Option<VersionMessage>,
+   version:  i32,
+   outgoing: VecDeque<NetworkMessage>,
+
+   /// the peer wants block announcements by headers
+   pub prefer_headers: bool,
+   /// wtxidrelay is negotiated
+   pub wtxid_relay:    bool,
+   /// the peer wants addrv2 messages
+   pub addr_v2:        bool,
+}
+
+impl Peer {
+   pub fn new(config:PeerConfig, inbound:bool) -> Self {
+      Peer {
+         config:   config,
+         inbound:  inbound,
+         state:    PeerState::Init,
+         their:    None,
+         version:  super::apriori::INIT_PROTO_VERSION,
+         outgoing: VecDeque::new(),
+         prefer_headers: false,
+         wtxid_relay:    false,
+         addr_v2:        false,
+      }
+   }
+
+   pub fn state(&self) -> PeerState { self.state }
+   pub fn is_established(&self) -> bool { self.state == PeerState::Established }
+   pub fn is_inbound(&self) -> bool { self.inbound }
+   /// the version message of the peer
+   pub fn their_version(&self) -> Option<&VersionMessage> { self.their.as_ref() }
+   /// common protocol version, which should be set to the envelope after the versions are exchanged
+   pub fn version(&self) -> i32 { self.version }
+
+   pub fn pop_outgoing(&mut self) -> Option<NetworkMessage> {
+      self.outgoing.pop_front()
+   }
+
+   /// Sends our version message. An outbound connection should call it first.
+   pub fn start(&mut self) {
+      if self.state == PeerState::Init {
+         self.push_version();
+         self.state = PeerState::VersionSent;
+      }
+   }
+
+   fn push_version(&mut self) {
+      let c = &self.config;
+      let m = VersionMessage {
+         version:      c.version,
+         services:     c.services,
+         timestamp:    std::time::SystemTime::now(),
+         addr_recv:    c.addr_recv.clone(),
+         addr_from:    c.addr_from.clone(),
+         nonce:        c.nonce,
+         user_agent:   c.user_agent.clone(),
+         start_height: c.start_height,
+         relay:        c.relay,
+      };
+      self.outgoing.push_back(m.into());
+   }
+
+   /// Handles the received message. Err means a protocol violation and the connection should be closed.
+   pub fn receive(&mut self, msg:NetworkMessage) -> crate::Result<Option<PeerEvent>> {
+      match msg {
+         NetworkMessage::Version(m) => {
+            self.receive_version(m)?;
+            Ok(None)
+         },
+         NetworkMessage::VerAck(_) => {
+            match self.state {
+               PeerState::VerAckWaiting => {
+                  self.state = PeerState::Established;
+                  if self.config.send_headers && SENDHEADERS_VERSION <= self.version {
+                     self.outgoing.push_back(SendHeadersMessage.into());
+                  }
+                  Ok(Some(PeerEvent::Established))
+               },
+               PeerState::Established => Ok(None), // ignore duplicated
+               _ => raise_protocol_error!("verack before version"),
+            }
+         },
+         NetworkMessage::WtxidRelay(_) => {
+            if self.state != PeerState::VerAckWaiting {
+               raise_protocol_error!("wtxidrelay received after verack");
+            }
+            if self.config.wtxid_relay && WTXID_RELAY_VERSION <= self.version {
+               self.wtxid_relay = true;
+            }
+            Ok(None)
+         },
+         NetworkMessage::SendAddrV2(_) => {
+            if self.state != PeerState::VerAckWaiting {
+               raise_protocol_error!("sendaddrv2 received after verack");
+            }
+            self.addr_v2 = true;
+            Ok(None)
+         },
+         _ if self.state != PeerState::Established => Ok(None), // ignore messages before the handshake
+         NetworkMessage::SendHeaders(_) => {
+            self.prefer_headers = true;
+            Ok(None)
+         },
+         NetworkMessage::Ping(m) => {
+            self.outgoing.push_back(PongMessage { nonce:m.nonce }.into());
+            Ok(None)
+         },
+         m => Ok(Some(PeerEvent::Message(m))),
+      }
+   }
+
+   fn receive_version(&mut self, m:VersionMessage) -> crate::Result<()> {
+      if self.their.is_some() {
+         raise_protocol_error!("duplicated version");
+      }
+      if m.version < MIN_PEER_PROTO_VERSION {
+         raise_protocol_error!(format!("obsolete version: {}", m.version));
+      }
+      if self.inbound && m.nonce == self.config.nonce && m.nonce != 0 {
+         raise_protocol_error!("connected to self");
+      }
+      if self.state == PeerState::Init {
+         self.push_version();
+      }
+      self.version = std::cmp::min(self.config.version, m.version);
+      self.their = Some(m);
+
+      // feature negotiations must be sent before verack
+      if self.config.wtxid_relay && WTXID_RELAY_VERSION <= self.version {
+         self.outgoing.push_back(WtxidRelayMessage.into());
+      }
+      if self.config.addr_v2 {
+         self.outgoing.push_back(SendAddrV2Message.into());
+      }
+      self.outgoing.push_back(VerAckMessage.into());
+      self.state = PeerState::VerAckWaiting;
+      Ok(())
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::protocol::{Envelope, StreamDecoder, PingMessage, InvMessage};
+
+   struct Side {
+      peer:    Peer,
+      encoder: Envelope,
+      decoder: StreamDecoder,
+      events:  Vec<PeerEvent>,
+      sent:    Vec<String>,
+   }
+   impl Side {
+      fn new(config:PeerConfig, inbound:bool) -> Self {
+         let env = Envelope::new(crate::bitcoin::presets::bitcoin_mainnet::CHAIN.magic);
+         Side {
+            peer:    Peer::new(config, inbound),
+            encoder: env.clone(),
+            decoder: StreamDecoder::new(env),
+            events:  Vec::new(),
+            sent:    Vec::new(),
+         }
+      }
+      fn flush(&mut self) -> Vec<u8> {
+         let mut buf = Vec::<u8>::new();
+         while let Some(m) = self.peer.pop_outgoing() {
+            self.sent.push(format!("{}", m));
+            let _ = self.encoder.encode(&m, &mut buf).unwrap();
+            // versions are sent by INIT_PROTO_VERSION
+            if self.peer.their_version().is_some() {
+               self.encoder.set_version(self.peer.version());
+            }
+         }
+         buf
+      }
+      fn feed(&mut self, data:&[u8]) -> crate::Result<()> {
+         for m in self.decoder.decode_all(data) {
+            if let Some(ev) = self.peer.receive(m?)? {
+               self.events.push(ev);
+            }
+            if self.peer.their_version().is_some() {
+               self.decoder.envelope_mut().set_version(self.peer.version());
+            }
+         }
+         Ok(())
+      }
+   }
+
+   fn run(a:&mut Side, b:&mut Side) -> crate::Result<()> {
+      for _ in 0..10 {
+         let x = a.flush();
+         b.feed(&x[..])?;
+         let y = b.flush();
+         a.feed(&y[..])?;
+         if x.is_empty() && y.is_empty() {
+            break;
+         }
+      }
+      Ok(())
+   }
+
+   #[test]
+   fn test_handshake() {
+      let mut a = Side::new(PeerConfig { nonce:1, ..Default::default() }, false);
+      let mut b = Side::new(PeerConfig { nonce:2, ..Default::default() }, true);
+      a.peer.start();
+      assert_eq!(a.peer.state(), PeerState::VersionSent);
+      assert_matches!(run(&mut a, &mut b), Ok(()));
+
+      for s in [&a, &b].iter() {
+         assert!(s.peer.is_established());
+         assert_eq!(s.peer.version(), PROTOCOL_VERSION);
+         assert!(s.peer.prefer_headers);
+         assert!(s.peer.wtxid_relay);
+         assert!(s.peer.addr_v2);
+         assert_matches!(s.events[..], [PeerEvent::Established]);
+         assert_eq!(s.sent, vec![
+            "NetworkMessage(version)", "NetworkMessage(wtxidrelay)", "NetworkMessage(sendaddrv2)",
+            "NetworkMessage(verack)", "NetworkMessage(sendheaders)",
+         ]);
+      }
+      assert_eq!(b.peer.their_version().unwrap().nonce, 1);
+
+      // ping is answered and other messages are passed through
+      a.peer.outgoing.push_back(PingMessage { nonce:12345 }.into());
+      a.peer.outgoing.push_back(InvMessage::default().into());
+      assert_matches!(run(&mut a, &mut b), Ok(()));
+      assert_matches!(b.events[1], PeerEvent::Message(NetworkMessage::Inv(_)));
+      assert_matches!(a.events[1], PeerEvent::Message(NetworkMessage::Pong(ref m)) if m.nonce == 12345);
+   }
+
+   #[test]
+   fn test_handshake_old_version() {
+      use crate::bitcoin::protocol::apriori::SENDHEADERS_VERSION;
+      let mut a = Side::new(PeerConfig { nonce:1, ..Default::default() }, false);
+      let mut b = Side::new(PeerConfig { nonce:2, version:SENDHEADERS_VERSION, addr_v2:false, ..Default::default() }, true);
+      a.peer.start();
+      assert_matches!(run(&mut a, &mut b), Ok(()));
+      for s in [&a, &b].iter() {
+         assert!(s.peer.is_established());
+         assert_eq!(s.peer.version(), SENDHEADERS_VERSION);
+         assert!(!s.peer.wtxid_relay);
+      }
+      assert!(b.peer.addr_v2);
+      assert!(!a.peer.addr_v2);
+   }
+
+   #[test]
+   fn test_handshake_violation() {
+      let mut a = Side::new(PeerConfig { nonce:1, ..Default::default() }, false);
+      let mut b = Side::new(PeerConfig { nonce:1, ..Default::default() }, true);
+      a.peer.start();
+      assert_matches!(run(&mut a, &mut b), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut a = Side::new(PeerConfig { nonce:1, version:MIN_PEER_PROTO_VERSION - 1, ..Default::default() }, false);
+      let mut b = Side::new(PeerConfig { nonce:2, ..Default::default() }, true);
+      a.peer.start();
+      assert_matches!(run(&mut a, &mut b), Err(crate::Error::BitcoinProtocol(_)));
+
+      let mut p = Peer::new(PeerConfig::default(), true);
+      assert_matches!(p.receive(VerAckMessage.into()), Err(_));
+      assert_matches!(p.receive(WtxidRelayMessage.into()), Err(_));
+      // ignored before the handshake
+      assert_matches!(p.receive(PingMessage { nonce:1 }.into()), Ok(None));
+      assert!(p.pop_outgoing().is_none());
+   }
+}
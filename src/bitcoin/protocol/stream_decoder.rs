@@ -0,0 +1,222 @@
+use super::MessageHeader;
+use super::envelope::{Envelope, NetworkMessage, HEADER_SIZE};
+
+/// Resumable decoder of the messages fed in arbitrary chunks.
+/// Bytes not beginning with the network magic are skipped to resynchronize.
+#[derive(Debug,Clone)]
+pub struct StreamDecoder {
+   envelope: Envelope,
+   magic:    [u8; 4],
+   buf:      Vec<u8>,
+   header:   Option<MessageHeader>,
+   skipped:  usize,
+}
+
+impl StreamDecoder {
+   pub fn new(envelope:Envelope) -> Self {
+      let m = envelope.magic;
+      StreamDecoder {
+         envelope: envelope,
+         magic:    [m as u8, (m >> 8) as u8, (m >> 16) as u8, (m >> 24) as u8],
+         buf:      Vec::with_capacity(HEADER_SIZE),
+         header:   None,
+         skipped:  0,
+      }
+   }
+   pub fn envelope(&self) -> &Envelope {
+      &self.envelope
+   }
+   pub fn envelope_mut(&mut self) -> &mut Envelope {
+      &mut self.envelope
+   }
+   /// number of bytes of a message not completed yet
+   pub fn buffered(&self) -> usize {
+      self.buf.len() + if self.header.is_some() { HEADER_SIZE } else { 0 }
+   }
+   /// total number of bytes discarded to resynchronize
+   pub fn skipped(&self) -> usize {
+      self.skipped
+   }
+
+   /// Consumes the data until a message is completed and returns the number of consumed bytes and the message.
+   /// The rest of the data should be fed again. An invalid message is returned as Err and the decoding can be continued.
+   pub fn decode(&mut self, data:&[u8]) -> (usize, Option<crate::Result<NetworkMessage>>) {
+      let mut consumed = 0;
+      loop {
+         match self.header.take() {
+            None => {
+               let n = std::cmp::min(HEADER_SIZE - self.buf.len(), data.len() - consumed);
+               self.buf.extend_from_slice(&data[consumed..(consumed + n)]);
+               consumed += n;
+               self.sync();
+               if self.buf.len() < HEADER_SIZE {
+                  if consumed == data.len() {
+                     return (consumed, None);
+                  }
+                  continue;
+               }
+               match self.envelope.decode_header(&self.buf[..]) {
+                  Ok(h) => {
+                     self.buf.clear();
+                     self.header = Some(h);
+                  },
+                  Err(e) => {
+                     // the magic may be matched by chance. search the next one.
+                     self.buf.remove(0);
+                     self.skipped += 1;
+                     return (consumed, Some(Err(e)));
+                  },
+               }
+            },
+            Some(h) => {
+               let len = h.length as usize;
+               let n = std::cmp::min(len - self.buf.len(), data.len() - consumed);
+               self.buf.extend_from_slice(&data[consumed..(consumed + n)]);
+               consumed += n;
+               if self.buf.len() < len {
+                  self.header = Some(h);
+                  return (consumed, None);
+               }
+               let r = self.envelope.decode_payload(&h, &self.buf[..]);
+               self.buf.clear();
+               return (consumed, Some(r));
+            },
+         }
+      }
+   }
+
+   /// Decodes all the data and returns the completed messages.
+   pub fn decode_all(&mut self, mut data:&[u8]) -> Vec<crate::Result<NetworkMessage>> {
+      let mut ret = Vec::new();
+      while !data.is_empty() {
+         let (n, r) = self.decode(data);
+         data = &data[n..];
+         if let Some(r) = r {
+            ret.push(r);
+         }
+      }
+      ret
+   }
+
+   // drops leading bytes until the buffer begins with the magic or its prefix
+   fn sync(&mut self) {
+      let pos = {
+         let (buf, magic) = (&self.buf, &self.magic);
+         (0..buf.len()).find(|&i| {
+            let k = std::cmp::min(magic.len(), buf.len() - i);
+            buf[i..(i + k)] == magic[..k]
+         }).unwrap_or(buf.len())
+      };
+      if 0 < pos {
+         let _ = self.buf.drain(..pos);
+         self.skipped += pos;
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::protocol::{PingMessage, VerAckMessage, InvMessage};
+
+   fn new_envelope() -> Envelope {
+      let mut env = Envelope::new(crate::bitcoin::presets::bitcoin_mainnet::CHAIN.magic);
+      env.set_version(crate::bitcoin::protocol::apriori::PROTOCOL_VERSION);
+      env
+   }
+
+   fn encode(msgs:&[NetworkMessage]) -> Vec<u8> {
+      let env = new_envelope();
+      let mut v = Vec::<u8>::new();
+      for m in msgs.iter() {
+         let _ = env.encode(m, &mut v).unwrap();
+      }
+      v
+   }
+
+   fn sample() -> Vec<NetworkMessage> {
+      vec![
+         PingMessage { nonce:1 }.into(),
+         VerAckMessage.into(),
+         InvMessage::default().into(),
+         PingMessage { nonce:2 }.into(),
+      ]
+   }
+
+   fn assert_sample(r:&[crate::Result<NetworkMessage>]) {
+      assert_eq!(r.len(), 4);
+      assert_matches!(r[0], Ok(NetworkMessage::Ping(ref m)) if m.nonce == 1);
+      assert_matches!(r[1], Ok(NetworkMessage::VerAck(_)));
+      assert_matches!(r[2], Ok(NetworkMessage::Inv(_)));
+      assert_matches!(r[3], Ok(NetworkMessage::Ping(ref m)) if m.nonce == 2);
+   }
+
+   #[test]
+   fn test_decode_chunks() {
+      let data = encode(&sample());
+      for chunk in [1usize, 3, 7, 24, 25, 1000].iter() {
+         let mut dec = StreamDecoder::new(new_envelope());
+         let mut r = Vec::new();
+         for c in data.chunks(*chunk) {
+            r.extend(dec.decode_all(c));
+         }
+         assert_sample(&r[..]);
+         assert_eq!(dec.buffered(), 0);
+         assert_eq!(dec.skipped(), 0);
+      }
+   }
+
+   #[test]
+   fn test_decode_consumed() {
+      let data = encode(&sample());
+      let mut dec = StreamDecoder::new(new_envelope());
+      let (n, r) = dec.decode(&data[..10]);
+      assert_eq!(n, 10);
+      assert!(r.is_none());
+      assert_eq!(dec.buffered(), 10);
+      // a ping is 32 bytes
+      let (n, r) = dec.decode(&data[10..]);
+      assert_eq!(n, 22);
+      assert_matches!(r, Some(Ok(NetworkMessage::Ping(_))));
+      // verack has no payload
+      let (n, r) = dec.decode(&data[32..]);
+      assert_eq!(n, 24);
+      assert_matches!(r, Some(Ok(NetworkMessage::VerAck(_))));
+   }
+
+   #[test]
+   fn test_resync() {
+      let msgs = sample();
+      let mut data = vec![0xf9u8, 0xbe, 0x00, 0x12];
+      data.extend(encode(&msgs[0..2]));
+      data.extend_from_slice(&[0xf9, 0xbe, 0xb4]);
+      data.extend(encode(&msgs[2..4]));
+      let mut dec = StreamDecoder::new(new_envelope());
+      let mut r = Vec::new();
+      for c in data.chunks(5) {
+         r.extend(dec.decode_all(c));
+      }
+      assert_sample(&r[..]);
+      assert_eq!(dec.skipped(), 7);
+   }
+
+   #[test]
+   fn test_corrupt() {
+      let msgs = sample();
+      let mut data = encode(&msgs[0..1]);
+      data[30] ^= 1; // payload of the ping
+      let mut tmp = encode(&msgs[1..2]);
+      tmp[4] = 0x01; // command of the verack
+      data.extend(tmp);
+      data.extend(encode(&msgs[2..4]));
+
+      let mut dec = StreamDecoder::new(new_envelope());
+      let r = dec.decode_all(&data[..]);
+      assert_eq!(r.len(), 4);
+      assert_matches!(r[0], Err(crate::Error::BitcoinProtocol(_)));
+      assert_matches!(r[1], Err(crate::Error::BitcoinProtocol(_)));
+      assert_matches!(r[2], Ok(NetworkMessage::Inv(_)));
+      assert_matches!(r[3], Ok(NetworkMessage::Ping(ref m)) if m.nonce == 2);
+      assert_eq!(dec.skipped(), 24);
+   }
+}
@@ -6,4 +6,15 @@ pub const MAX_STACK_SIZE:usize = 1000;
 pub const MAX_OPS_PER_SCRIPT:usize = 201;
 pub const MAX_PUBKEYS_PER_MULTISIG:usize = 20;
 
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED:i64 = 50;
+pub const VALIDATION_WEIGHT_OFFSET:i64 = 50;
+
+pub const ANNEX_TAG:u8 = 0x50;
+pub const TAPROOT_LEAF_MASK:u8 = 0xfe;
+pub const TAPROOT_LEAF_TAPSCRIPT:u8 = 0xc0;
+pub const TAPROOT_CONTROL_BASE_SIZE:usize = 33;
+pub const TAPROOT_CONTROL_NODE_SIZE:usize = 32;
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT:usize = 128;
+pub const TAPROOT_CONTROL_MAX_SIZE:usize = TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+
 //pub const LOCKTIME_THRESHOLD:u64 = 500000000; // Tue Nov  5 00:53:20 1985 UTC
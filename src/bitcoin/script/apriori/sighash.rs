@@ -1,5 +1,8 @@
+#[allow(dead_code)] pub const DEFAULT:u8      = 0u8; // taproot only
 #[allow(dead_code)] pub const ALL:u8          = 1u8;
 #[allow(dead_code)] pub const NONE:u8         = 2u8;
 #[allow(dead_code)] pub const SINGLE:u8       = 3u8;
 #[allow(dead_code)] pub const ANYONECANPAY:u8 = 0x80u8;
 
+#[allow(dead_code)] pub const OUTPUT_MASK:u8  = 3u8;
+#[allow(dead_code)] pub const INPUT_MASK:u8   = 0x80u8;
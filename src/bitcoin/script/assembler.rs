@@ -17,7 +17,7 @@ pub fn lex(input: &str) -> crate::Result<Vec<Token>> {
       token('\u{0027}'), token('\u{0027}'), many(satisfy(|c| c != '\u{0027}'))
    ).map(|s:String| Tmp::S(s));
    let other_literal = many1(
-      satisfy(|c:char| c=='-' || ('0'<=c && c<='9') || ('a'<=c && c<='z') || ('A'<=c && c<='Z'))
+      satisfy(|c:char| c=='-' || c=='_' || ('0'<=c && c<='9') || ('a'<=c && c<='z') || ('A'<=c && c<='Z'))
    ).map(|s:String| Tmp::O(s));
    let literal = attempt(string_literal).or(attempt(other_literal));
    
@@ -44,6 +44,8 @@ pub fn lex(input: &str) -> crate::Result<Vec<Token>> {
                   ret.push(Token::Digit(v));
                } else if let Some(op) = NAME2CODE.get(format!("OP_{}", s).as_str()) {
                   ret.push(Token::Op(*op));
+               } else if let Some(op) = NAME2CODE.get(s.as_str()) {
+                  ret.push(Token::Op(*op));
                } else {
                   raise_script_error!(format!("unknown opcode `{}'", s));
                }
@@ -188,6 +190,11 @@ fn test_1_assemble_opcode() {
    let bytes = r.unwrap();
    assert_eq!(bytes, [OP_CHECKSIG]);
 
+   let r = assemble("OP_CHECKSIG");
+   assert_matches!(r, Ok(_));
+   let bytes = r.unwrap();
+   assert_eq!(bytes, [OP_CHECKSIG]);
+
    let r = assemble("0");
    assert_matches!(r, Ok(_));
    let bytes = r.unwrap();
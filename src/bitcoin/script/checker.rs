@@ -1,6 +1,8 @@
 use super::apriori::{sighash};
 use super::flags::Flags;
-use crate::bitcoin::datatypes::{Tx, LockTime, TxIn};
+use crate::bitcoin::datatypes::{Tx, LockTime, TxIn, TxOut, Amount};
+use crate::crypto::digest::{DigestWrite, DHash256};
+use crypto::sha2::Sha256;
 use crate::ui::secp256k1::{PublicKeyUi, SignatureUi};
 use std::error::Error;
 
@@ -23,7 +25,10 @@ pub fn get_hash(tx:&Tx, txin_idx:usize, subscript:&[u8], hash_type:i32) -> crate
       use super::parser::Parser;
       let mut beg = 0usize;
       for iter in Parser::iter(subscript) {
-         let parsed = iter?;
+         let parsed = match iter {
+            Ok(parsed) => parsed,
+            Err(_) => break, // the rest is serialized as it is
+         };
          if parsed.opcode == OP_CODESEPARATOR {
             tmp.extend(&subscript[beg .. parsed.offset]);
             beg = parsed.offset + 1;
@@ -41,6 +46,338 @@ pub fn get_hash(tx:&Tx, txin_idx:usize, subscript:&[u8], hash_type:i32) -> crate
    Ok(b)
 }
 
+fn hash_by<D,F>(digest:D, f:F) -> crate::Result<[u8;32]>
+   where D: crypto::digest::Digest,
+         F: FnOnce(&BitcoinSerializer, &mut DigestWrite<D>) -> crate::Result<usize>
+{
+   use crypto::digest::Digest;
+   let med = crate::bitcoin::serialize::Medium::default().set_net();
+   let e = BitcoinSerializer::new(&med);
+   let mut ws = DigestWrite::new(digest);
+   let _ = f(&e, &mut ws)?;
+   let mut out = [0u8; 32];
+   ws.result(&mut out);
+   Ok(out)
+}
+fn dhash_by<F>(f:F) -> crate::Result<[u8;32]>
+   where F: FnOnce(&BitcoinSerializer, &mut DigestWrite<DHash256>) -> crate::Result<usize>
+{
+   hash_by(DHash256::new(), f)
+}
+fn sha256_by<F>(f:F) -> crate::Result<[u8;32]>
+   where F: FnOnce(&BitcoinSerializer, &mut DigestWrite<Sha256>) -> crate::Result<usize>
+{
+   hash_by(Sha256::new(), f)
+}
+fn sha256(input:&[u8]) -> [u8;32] {
+   let mut out = [0u8;32];
+   out.copy_from_slice(&crate::ui::create_sha256().u8_to_u8(input)[..]);
+   out
+}
+
+/// Hashes shared by all inputs of a tx: BIP143 ones and, if spent outputs are given, BIP341 ones.
+#[derive(Debug,Clone)]
+pub struct PrecomputedTxData {
+   pub hash_prevouts: [u8;32],
+   pub hash_sequence: [u8;32],
+   pub hash_outputs:  [u8;32],
+
+   pub spent_outputs:      Vec<TxOut>,
+   pub sha_prevouts:       [u8;32],
+   pub sha_amounts:        [u8;32],
+   pub sha_script_pubkeys: [u8;32],
+   pub sha_sequences:      [u8;32],
+   pub sha_outputs:        [u8;32],
+}
+impl PrecomputedTxData {
+   pub fn new(tx:&Tx) -> crate::Result<Self> {
+      Self::new_with_spent_outputs(tx, Vec::new())
+   }
+   pub fn new_with_spent_outputs(tx:&Tx, spent_outputs:Vec<TxOut>) -> crate::Result<Self> {
+      let sha_prevouts  = get_hash_prevouts(tx, Sha256::new())?;
+      let sha_sequences = get_hash_sequence(tx, Sha256::new())?;
+      let sha_outputs   = get_hash_outputs(tx, Sha256::new())?;
+      let (sha_amounts, sha_script_pubkeys) = if spent_outputs.len() == tx.ins.len() {
+         let amounts = sha256_by(|e, ws| {
+            let mut r = 0usize;
+            for txout in spent_outputs.iter() {
+               r += e.serialize_i64le(ws, txout.value)?;
+            }
+            Ok(r)
+         })?;
+         let script_pubkeys = sha256_by(|e, ws| {
+            let mut r = 0usize;
+            for txout in spent_outputs.iter() {
+               r += txout.script_pubkey.serialize(&true, e, ws)?;
+            }
+            Ok(r)
+         })?;
+         (amounts, script_pubkeys)
+      } else {
+         ([0u8;32], [0u8;32])
+      };
+      Ok(PrecomputedTxData {
+         hash_prevouts: sha256(&sha_prevouts),
+         hash_sequence: sha256(&sha_sequences),
+         hash_outputs:  sha256(&sha_outputs),
+         spent_outputs:      spent_outputs,
+         sha_prevouts:       sha_prevouts,
+         sha_amounts:        sha_amounts,
+         sha_script_pubkeys: sha_script_pubkeys,
+         sha_sequences:      sha_sequences,
+         sha_outputs:        sha_outputs,
+      })
+   }
+   pub fn is_taproot_ready(&self, tx:&Tx) -> bool {
+      0 < tx.ins.len() && self.spent_outputs.len() == tx.ins.len()
+   }
+}
+
+fn get_hash_prevouts<D:crypto::digest::Digest>(tx:&Tx, digest:D) -> crate::Result<[u8;32]> {
+   hash_by(digest, |e, ws| {
+      let mut r = 0usize;
+      for txin in tx.ins.iter() {
+         r += txin.prevout.serialize(&(), e, ws)?;
+      }
+      Ok(r)
+   })
+}
+fn get_hash_sequence<D:crypto::digest::Digest>(tx:&Tx, digest:D) -> crate::Result<[u8;32]> {
+   hash_by(digest, |e, ws| {
+      let mut r = 0usize;
+      for txin in tx.ins.iter() {
+         r += e.serialize_u32le(ws, txin.sequence)?;
+      }
+      Ok(r)
+   })
+}
+fn get_hash_outputs<D:crypto::digest::Digest>(tx:&Tx, digest:D) -> crate::Result<[u8;32]> {
+   hash_by(digest, |e, ws| {
+      let mut r = 0usize;
+      for txout in tx.outs.iter() {
+         r += txout.serialize(&(), e, ws)?;
+      }
+      Ok(r)
+   })
+}
+
+pub fn get_hash_witness_v0(
+   tx:&Tx,
+   txin_idx:usize,
+   script_code:&[u8],
+   amount:Amount,
+   hash_type:i32,
+   txdata:Option<&PrecomputedTxData>,
+) -> crate::Result<Box<[u8]>>
+{
+   if txin_idx >= tx.ins.len() {
+      raise_script_error!(format!("input index out of range: {}", txin_idx));
+   }
+   let anyone_can_pay = (hash_type & (sighash::ANYONECANPAY as i32)) != 0;
+   let base_type      = (hash_type & 0x1f) as u8;
+
+   let zero = [0u8; 32];
+   let hash_prevouts = if anyone_can_pay {
+      zero
+   } else if let Some(d) = txdata {
+      d.hash_prevouts
+   } else {
+      get_hash_prevouts(tx, DHash256::new())?
+   };
+   let hash_sequence = if anyone_can_pay || base_type == sighash::SINGLE || base_type == sighash::NONE {
+      zero
+   } else if let Some(d) = txdata {
+      d.hash_sequence
+   } else {
+      get_hash_sequence(tx, DHash256::new())?
+   };
+   let hash_outputs = if base_type != sighash::SINGLE && base_type != sighash::NONE {
+      if let Some(d) = txdata {
+         d.hash_outputs
+      } else {
+         get_hash_outputs(tx, DHash256::new())?
+      }
+   } else if base_type == sighash::SINGLE && txin_idx < tx.outs.len() {
+      dhash_by(|e, ws| tx.outs[txin_idx].serialize(&(), e, ws))?
+   } else {
+      zero
+   };
+
+   let txin = &tx.ins[txin_idx];
+   let hash = dhash_by(|e, ws| {
+      let mut r = 0usize;
+      r += e.serialize_i32le(ws, tx.version)?;
+      r += e.serialize_octets(ws, &hash_prevouts)?;
+      r += e.serialize_octets(ws, &hash_sequence)?;
+      r += txin.prevout.serialize(&(), e, ws)?;
+      r += e.serialize_var_octets(ws, script_code, std::usize::MAX)?;
+      r += e.serialize_i64le(ws, amount)?;
+      r += e.serialize_u32le(ws, txin.sequence)?;
+      r += e.serialize_octets(ws, &hash_outputs)?;
+      r += tx.locktime.serialize(&(), e, ws)?;
+      r += e.serialize_i32le(ws, hash_type)?;
+      Ok(r)
+   })?;
+   Ok(Box::new(hash))
+}
+
+/// Per-input data which BIP341/342 signature hashes commit to.
+#[derive(Debug,Clone)]
+pub struct ScriptExecutionData {
+   pub tapleaf_hash: [u8;32],
+   pub codeseparator_pos: u32,
+   pub annex_hash: Option<[u8;32]>,
+   pub validation_weight_left: i64,
+}
+impl Default for ScriptExecutionData {
+   fn default() -> Self {
+      ScriptExecutionData {
+         tapleaf_hash: [0u8;32],
+         codeseparator_pos: 0xFFFFFFFFu32,
+         annex_hash: None,
+         validation_weight_left: 0,
+      }
+   }
+}
+
+pub fn get_hash_taproot(
+   tx:&Tx,
+   txin_idx:usize,
+   hash_type:u8,
+   execdata:&ScriptExecutionData,
+   txdata:&PrecomputedTxData,
+   flags:&Flags,
+) -> crate::Result<[u8;32]>
+{
+   if !txdata.is_taproot_ready(tx) {
+      raise_script_error!("spent outputs are not given");
+   }
+   if txin_idx >= tx.ins.len() {
+      raise_script_error!(format!("input index out of range: {}", txin_idx));
+   }
+   match hash_type {
+      0x00 ..= 0x03 | 0x81 ..= 0x83 => (),
+      _ => raise_script_error!(format!("unknown hash type: {}", hash_type)),
+   }
+   let output_type = if hash_type == sighash::DEFAULT { sighash::ALL } else { hash_type & sighash::OUTPUT_MASK };
+   let anyone_can_pay = (hash_type & sighash::INPUT_MASK) == sighash::ANYONECANPAY;
+   let is_tapscript = flags.sig_version.is_tapscript();
+
+   let sha_single_output = if output_type == sighash::SINGLE {
+      if txin_idx >= tx.outs.len() {
+         raise_script_error!(format!("no corresponding output for SIGHASH_SINGLE: {}", txin_idx));
+      }
+      Some(sha256_by(|e, ws| tx.outs[txin_idx].serialize(&(), e, ws))?)
+   } else {
+      None
+   };
+
+   let tagged = crate::crypto::digest::TaggedSha256::new("TapSighash");
+   hash_by(tagged, |e, ws| {
+      let mut r = 0usize;
+      r += e.serialize_u8(ws, 0)?; // epoch
+      r += e.serialize_u8(ws, hash_type)?;
+      r += e.serialize_i32le(ws, tx.version)?;
+      r += tx.locktime.serialize(&(), e, ws)?;
+      if !anyone_can_pay {
+         r += e.serialize_octets(ws, &txdata.sha_prevouts)?;
+         r += e.serialize_octets(ws, &txdata.sha_amounts)?;
+         r += e.serialize_octets(ws, &txdata.sha_script_pubkeys)?;
+         r += e.serialize_octets(ws, &txdata.sha_sequences)?;
+      }
+      if output_type == sighash::ALL {
+         r += e.serialize_octets(ws, &txdata.sha_outputs)?;
+      }
+      let spend_type = (if is_tapscript { 2u8 } else { 0u8 }) | (if execdata.annex_hash.is_some() { 1u8 } else { 0u8 });
+      r += e.serialize_u8(ws, spend_type)?;
+      if anyone_can_pay {
+         let txin  = &tx.ins[txin_idx];
+         let spent = &txdata.spent_outputs[txin_idx];
+         r += txin.prevout.serialize(&(), e, ws)?;
+         r += e.serialize_i64le(ws, spent.value)?;
+         r += spent.script_pubkey.serialize(&true, e, ws)?;
+         r += e.serialize_u32le(ws, txin.sequence)?;
+      } else {
+         r += e.serialize_u32le(ws, txin_idx as u32)?;
+      }
+      if let Some(ref annex_hash) = execdata.annex_hash {
+         r += e.serialize_octets(ws, annex_hash)?;
+      }
+      if let Some(ref h) = sha_single_output {
+         r += e.serialize_octets(ws, h)?;
+      }
+      if is_tapscript {
+         r += e.serialize_octets(ws, &execdata.tapleaf_hash)?;
+         r += e.serialize_u8(ws, 0)?; // key_version
+         r += e.serialize_u32le(ws, execdata.codeseparator_pos)?;
+      }
+      Ok(r)
+   })
+}
+
+pub fn check_schnorr_signature(
+   tx:&Tx,
+   txin_idx:usize,
+   txdata:Option<&PrecomputedTxData>,
+   execdata:&ScriptExecutionData,
+   pk_bytes:&[u8],
+   sig_bytes:&[u8],
+   flags:&Flags,
+) -> crate::Result<()>
+{
+   use crate::ui::secp256k1::XOnlyPublicKeyUi;
+   let (sig, hash_type) = match sig_bytes.len() {
+      64 => (sig_bytes, sighash::DEFAULT),
+      65 => {
+         let hash_type = sig_bytes[64];
+         if hash_type == sighash::DEFAULT {
+            raise_script_interpret_error!(SchnorrSigHashType);
+         }
+         (&sig_bytes[0..64], hash_type)
+      },
+      _ => raise_script_interpret_error!(SchnorrSigSize),
+   };
+   match hash_type {
+      0x00 ..= 0x03 | 0x81 ..= 0x83 => (),
+      _ => raise_script_interpret_error!(SchnorrSigHashType, format!("unknown hash type: {}", hash_type)),
+   }
+   if (hash_type & sighash::OUTPUT_MASK) == sighash::SINGLE && tx.outs.len() <= txin_idx {
+      raise_script_interpret_error!(SchnorrSigHashType, format!("no corresponding output for SIGHASH_SINGLE: {}", txin_idx));
+   }
+   let txdata = match txdata {
+      Some(d) => d,
+      None => raise_script_error!("spent outputs are not given"),
+   };
+   let hash = get_hash_taproot(tx, txin_idx, hash_type, execdata, txdata, flags)?;
+   let pk = XOnlyPublicKeyUi::s_decode(pk_bytes).map_err(|e| {
+      script_interpret_error!(SchnorrSig, e.description())
+   })?;
+   pk.verify_schnorr(&hash, sig).map_err(|e| {
+      script_interpret_error!(SchnorrSig, e.description())
+   })?;
+   Ok(())
+}
+
+pub fn get_sighash(
+   tx:&Tx,
+   txin_idx:usize,
+   subscript:&[u8],
+   amount:Amount,
+   hash_type:i32,
+   txdata:Option<&PrecomputedTxData>,
+   flags:&Flags,
+) -> crate::Result<Box<[u8]>>
+{
+   use super::flags::SigVersion;
+   match flags.sig_version {
+      SigVersion::Base      => get_hash(tx, txin_idx, subscript, hash_type),
+      SigVersion::WitnessV0 => get_hash_witness_v0(tx, txin_idx, subscript, amount, hash_type, txdata),
+      SigVersion::Taproot | SigVersion::Tapscript => {
+         raise_script_error!("taproot signature hash needs execution data. use get_hash_taproot")
+      },
+   }
+}
+
 pub fn check_signature_encoding(vch:&[u8], flags:&Flags) -> crate::Result<()> {
    if vch.len() == 0 {
       return Ok(());
@@ -93,9 +430,12 @@ pub fn is_defined_hashtype_signature(vch:&[u8]) -> crate::Result<()> {
 pub fn chain_check_sign(
    tx:&Tx,
    txin_idx:usize,
+   amount:Amount,
+   txdata:Option<&PrecomputedTxData>,
    subscript:&[u8],
    pk_bytes:&[u8],
    sig_bytes:&[u8],
+   flags:&Flags,
 ) -> crate::Result<bool>
 {
    if pk_bytes.len() < 1 { return Ok(false); }
@@ -106,7 +446,7 @@ pub fn chain_check_sign(
    //println!("txin_idx: {}", txin_idx);
    let hash = {
       let hash_type = sig_bytes[sig_bytes.len()-1];
-      let hash = get_hash(tx, txin_idx, subscript, hash_type as i32)?;
+      let hash = get_sighash(tx, txin_idx, subscript, amount, hash_type as i32, txdata, flags)?;
       hash
    };
 
@@ -134,15 +474,14 @@ pub fn chain_check_locktime(
    locktime: u64,
 ) -> crate::Result<bool>
 {
-   let locktime = LockTime::new_by_u64(locktime);
-   match (&tx.locktime, locktime) {
-      (&LockTime::Block(l), LockTime::Block(r)) => {
-         if l < r { return Ok(false); };
-      },
-      (&LockTime::Time(l), LockTime::Time(r)) => {
-         if l < r { return Ok(false); };
-      },
-      (_, _) => { return Ok(false); }
+   use crate::bitcoin::datatypes::lock_time::TRANSACTION_LOCKTIME_BORDER;
+   let border = TRANSACTION_LOCKTIME_BORDER as u64;
+   let tx_locktime = tx.locktime.as_u64();
+   if (tx_locktime < border) != (locktime < border) {
+      return Ok(false); // block height vs timestamp
+   }
+   if tx_locktime < locktime {
+      return Ok(false);
    }
    if tx.ins[txin_idx].is_sequence_final() {
       return Ok(false);
@@ -179,15 +518,15 @@ struct CustomTx<'a> {
    tx: &'a Tx,
    in_idx: usize,
    subscript: &'a [u8],
-   hash_type: u8,
+   hash_type: i32,
 }
 impl <'a> CustomTx<'a> {
    pub fn new<'x>(tx:&'x Tx, in_idx:usize, subscript:&'x [u8], hash_type:i32) -> CustomTx<'x> {
-      CustomTx { tx:tx, in_idx:in_idx, subscript:subscript, hash_type:hash_type as u8 }
+      CustomTx { tx:tx, in_idx:in_idx, subscript:subscript, hash_type:hash_type }
    }
-   pub fn anyone_can_pay(&self) -> bool { (self.hash_type & sighash::ANYONECANPAY) != 0 }
-   pub fn hash_single(&self) -> bool    { (self.hash_type & 0x1f) == sighash::SINGLE }
-   pub fn hash_none(&self) -> bool      { (self.hash_type & 0x1f) == sighash::NONE }
+   pub fn anyone_can_pay(&self) -> bool { (self.hash_type & (sighash::ANYONECANPAY as i32)) != 0 }
+   pub fn hash_single(&self) -> bool    { (self.hash_type & 0x1f) == (sighash::SINGLE as i32) }
+   pub fn hash_none(&self) -> bool      { (self.hash_type & 0x1f) == (sighash::NONE as i32) }
 
    fn serialize_tx_in<W: std::io::Write>(&self, e:&BitcoinSerializer, ws:&mut W, i:usize) -> crate::Result<usize> {
       let mut r = 0usize;
@@ -230,24 +569,16 @@ impl <'a> CustomTx<'a> {
          if self.hash_none() {
             r += e.serialize_var_int(ws, 0u64)?;
          } else if self.hash_single() && self.in_idx < self.tx.outs.len() {
-            let b = crate::ui::bitcoin::serialize(&self.tx.outs[self.in_idx], &())?;
-            let hash = crate::ui::digest::create_dhash256().u8_to_u8(b.as_ref());
-            r += e.serialize_octets(ws, hash.as_ref())?;
+            // outputs before in_idx are blanked, after in_idx are dropped
+            r += e.serialize_var_int(ws, (self.in_idx + 1) as u64)?;
+            let null = TxOut::new_null();
+            for _i in 0..self.in_idx {
+               r += null.serialize(&(), e, ws)?;
+            }
+            r += self.tx.outs[self.in_idx].serialize(&(), e, ws)?;
          } else {
             r += e.serialize_var_array(&(), ws, self.tx.outs.as_slice(), std::usize::MAX)?;
          }
-
-         /*
-         let num_outs = match (self.hash_none(), self.hash_single()) {
-            (true, _)     => 0usize,
-            (false, true) => self.in_idx + 1,
-            _             => self.tx.outs.len()
-         };
-         r += e.serialize_varint(num_outs as u64)?;
-         for i in 0..num_outs {
-            r += self.serialize_output(e, i)?;
-         }
-          */
       }
       
       r += self.tx.locktime.serialize(&(), e, ws)?;
@@ -260,8 +591,35 @@ impl <'a> BitcoinSerializee for CustomTx<'a> {
    fn serialize<W: std::io::Write>(&self, _p:&Self::P, e:&BitcoinSerializer, ws:&mut W) -> crate::Result<usize> {
       let mut r = 0usize;
       r += self.serialize_tx(e, ws)?;
-      r += e.serialize_i32le(ws, self.hash_type as i32)?;
+      r += e.serialize_i32le(ws, self.hash_type)?;
       Ok(r)
    }
 }
 
+
+#[test]
+fn test_get_hash_witness_v0() {
+   use super::flags::SigVersion;
+   // BIP143 native P2WPKH example
+   let tx = crate::ui::bitcoin::hex_to_tx("0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000").unwrap();
+   let script_code = crate::utils::h2b("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap();
+   let expect = "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670";
+
+   let txdata = PrecomputedTxData::new(&tx).unwrap();
+   assert_eq!(crate::utils::b2h(&txdata.hash_prevouts[..]), "96b827c8483d4e9b96712b6713a7b68d6e8003a781feba36c31143470b4efd37");
+   assert_eq!(crate::utils::b2h(&txdata.hash_sequence[..]), "52b0a642eea2fb7ae638c36f6252b6750293dbe574a806984b8e4d8548339a3b");
+   assert_eq!(crate::utils::b2h(&txdata.hash_outputs[..]),  "863ef3e1a92afbfdb97f31ad0fc7683ee943e9abcf2501590ff8f6551f47e5e5");
+
+   let h = get_hash_witness_v0(&tx, 1, &script_code, 600000000, 1, None).unwrap();
+   assert_eq!(crate::utils::b2h(h), expect);
+   let h = get_hash_witness_v0(&tx, 1, &script_code, 600000000, 1, Some(&txdata)).unwrap();
+   assert_eq!(crate::utils::b2h(h), expect);
+
+   let sig = crate::utils::h2b("304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee01").unwrap();
+   let pk  = crate::utils::h2b("025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357").unwrap();
+   let flags = Flags { sig_version: SigVersion::WitnessV0, ..Flags::default() };
+   assert_matches!(chain_check_sign(&tx, 1, 600000000, Some(&txdata), &script_code, &pk, &sig, &flags), Ok(true));
+   assert_matches!(chain_check_sign(&tx, 1, 600000001, Some(&txdata), &script_code, &pk, &sig, &flags), Err(_));
+   let flags = Flags { sig_version: SigVersion::Base, ..Flags::default() };
+   assert_matches!(chain_check_sign(&tx, 1, 600000000, Some(&txdata), &script_code, &pk, &sig, &flags), Err(_));
+}
@@ -85,6 +85,19 @@ pub enum InterpretErrorCode {
    WitnessUnexpected,
    WitnessPubkeyType,
 
+   /* taproot */
+   SchnorrSigSize,
+   SchnorrSigHashType,
+   SchnorrSig,
+   TaprootWrongControlSize,
+   TapscriptValidationWeight,
+   TapscriptCheckMultisig,
+   TapscriptMinimalIf,
+   TapscriptEmptyPubkey,
+   DiscourageUpgradableTaprootVersion,
+   DiscourageOpSuccess,
+   DiscourageUpgradablePubkeyType,
+
    ErrorCount,
 }
 
@@ -2,6 +2,8 @@
 pub enum SigVersion {
    Base,
    WitnessV0,
+   Taproot,   // BIP341 key path spending
+   Tapscript, // BIP342 script path spending
 }
 impl Default for SigVersion {
    fn default() -> Self {
@@ -21,6 +23,18 @@ impl SigVersion {
          _ => false,
       }
    }
+   pub fn is_taproot(&self) -> bool {
+      match *self {
+         SigVersion::Taproot => true,
+         _ => false,
+      }
+   }
+   pub fn is_tapscript(&self) -> bool {
+      match *self {
+         SigVersion::Tapscript => true,
+         _ => false,
+      }
+   }
 }
 
 #[derive(Debug,Clone,Copy)]
@@ -63,6 +77,10 @@ impl ScriptVerify {
    impl_flags!{is_minimal_if, minimal_if, 13}
    impl_flags!{is_null_fail, null_fail, 14}
    impl_flags!{is_witness_pubkey_type, witness_pubkey_type, 15}
+   impl_flags!{is_taproot, taproot, 16}
+   impl_flags!{is_discourage_upgradable_taproot_version, discourage_upgradable_taproot_version, 17}
+   impl_flags!{is_discourage_op_success, discourage_op_success, 18}
+   impl_flags!{is_discourage_upgradable_pubkey_type, discourage_upgradable_pubkey_type, 19}
    
    pub fn with<O,F>(&self, f:F) -> O where F: Fn(&Self)->O {
       f(self)
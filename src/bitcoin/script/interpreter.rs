@@ -1,5 +1,5 @@
-use crate::bitcoin::datatypes::{Tx, TxIn};
-use super::flags::Flags;
+use crate::bitcoin::datatypes::{Tx, TxIn, TxOut, TxOutPoint, Amount, Witness};
+use super::flags::{Flags, SigVersion};
 use super::stack::Stack;
 use super::checker;
 use super::parser::{Parser, Parsed};
@@ -15,7 +15,11 @@ pub struct Context<'a> {
    pub bytecode:   &'a [u8],
    pub tx:         &'a Tx,
    pub txin_idx:   usize,
+   pub amount:     Amount,
+   pub txdata:     Option<&'a checker::PrecomputedTxData>,
+   pub execdata:   checker::ScriptExecutionData,
    pub codesep:    usize,
+   pub opcode_pos: u32,
    pub conditions: Vec<bool>,
    pub flags:      &'a Flags,
    pub op_count:   usize,
@@ -31,30 +35,55 @@ impl Interpreter {
    }
    pub fn stack(&self) -> &Stack { &self.stack }
    pub fn pop_stack(&mut self) -> crate::Result< super::stack::Entry > { self.stack.pop() }
+   pub fn truncate_stack(&mut self, len:usize) { self.stack.truncate(len) }
 
    pub fn eval<'a>(&mut self, bytecode:&'a [u8], tx:&Tx, txin_idx:usize, flags:&Flags) -> crate::Result<()> {
+      self.eval_with_amount(bytecode, tx, txin_idx, 0, None, flags)
+   }
+   pub fn eval_with_amount<'a>(
+      &mut self,
+      bytecode:&'a [u8],
+      tx:&'a Tx,
+      txin_idx:usize,
+      amount:Amount,
+      txdata:Option<&'a checker::PrecomputedTxData>,
+      flags:&'a Flags
+   ) -> crate::Result<()> {
+      self.eval_with_execdata(bytecode, tx, txin_idx, amount, txdata, &checker::ScriptExecutionData::default(), flags)
+   }
+   pub fn eval_with_execdata<'a>(
+      &mut self,
+      bytecode:&'a [u8],
+      tx:&'a Tx,
+      txin_idx:usize,
+      amount:Amount,
+      txdata:Option<&'a checker::PrecomputedTxData>,
+      execdata:&checker::ScriptExecutionData,
+      flags:&'a Flags
+   ) -> crate::Result<()> {
       //println!("eval: {}", script);
       //let checker = signature::Checker::new(tx, in_idx);
-      if MAX_SCRIPT_SIZE < bytecode.len() {
+      let is_legacy_limits = flags.sig_version.is_base() || flags.sig_version.is_witness_v0();
+      if is_legacy_limits && MAX_SCRIPT_SIZE < bytecode.len() {
          raise_script_interpret_error!(ScriptSize);
       }
       let mut ctx = Context {
          bytecode:   bytecode,
          tx:         tx,
          txin_idx:   txin_idx,
+         amount:     amount,
+         txdata:     txdata,
+         execdata:   execdata.clone(),
          codesep:    0,
+         opcode_pos: 0,
          conditions: Vec::<bool>::new(),
          flags:      flags,
          op_count:   0,
          altstack:   Stack::new(),
       };
-      let mut last_op = OP_0;
       let parseds = Parser::parse(bytecode)
          .map_err(|_| script_interpret_error!(BadOpcode))?;
       for parsed in parseds.into_iter() {
-         if last_op == OP_CODESEPARATOR {
-            ctx.codesep = parsed.offset;
-         }
          //let info = &OPCODE_INFO[code as usize];
          //println!("{:x}={}[{}]", code, info.name, follow.len());
 
@@ -62,7 +91,7 @@ impl Interpreter {
          if 1000 < self.stack.len() {
             script_error!("stack is too long");
          }
-         last_op = parsed.opcode;
+         ctx.opcode_pos += 1;
       }
       if 0 < ctx.conditions.len() {
          raise_script_interpret_error!(UnbalancedConditional);
@@ -123,7 +152,7 @@ impl Interpreter {
             let _info = &OPCODE_INFO[op as usize];
             //println!("op={}", _info.name);
 
-            if OP_16 < op {
+            if OP_16 < op && (ctx.flags.sig_version.is_base() || ctx.flags.sig_version.is_witness_v0()) {
                ctx.op_count += 1;
                if MAX_OPS_PER_SCRIPT < ctx.op_count {
                   raise_script_interpret_error!(OpCount);
@@ -153,7 +182,12 @@ impl Interpreter {
                         }
                         let r = {
                            let e = self.stack.at(-1).unwrap();
-                           if ctx.flags.sig_version.is_witness_v0() &&
+                           if ctx.flags.sig_version.is_tapscript() {
+                              // minimal if is consensus in tapscript
+                              if !e.is_minimal_if() {
+                                 raise_script_interpret_error!(TapscriptMinimalIf);
+                              }
+                           } else if ctx.flags.sig_version.is_witness_v0() &&
                               ctx.flags.script_verify.is_minimal_if()
                            {
                               if !e.is_minimal_if() {
@@ -480,12 +514,19 @@ impl Interpreter {
                   },
                   
                   OP_CODESEPARATOR => {
+                     // only an executed one changes the script code
+                     ctx.codesep = parsed.offset + 1;
+                     ctx.execdata.codeseparator_pos = ctx.opcode_pos;
                   },
                   _ if op == OP_CHECKSIG || op == OP_CHECKSIGVERIFY => {
                      if self.stack.len() < 2 {
                         raise_script_interpret_error!(InvalidStackOperation);
                      }
-                     let r = {
+                     let r = if ctx.flags.sig_version.is_tapscript() {
+                        let sig = self.stack.at(-2)?;
+                        let key = self.stack.at(-1)?;
+                        eval_checksig_tapscript(sig.data(), key.data(), ctx)?
+                     } else {
                         let sig = self.stack.at(-2)?;
                         let key = self.stack.at(-1)?;
                         let subscript = {
@@ -493,13 +534,14 @@ impl Interpreter {
                            if !ctx.flags.sig_version.is_base() {
                               tmp.to_vec()
                            } else {
-                              Parser::find_and_delete(tmp, sig.data()).0
+                              let pushed = super::assembler::assemble_push_data(sig.data())?;
+                              Parser::find_and_delete(tmp, pushed.as_slice()).0
                            }
                         };
 
                         checker::check_signature_encoding(sig.data(), ctx.flags)?;
                         checker::check_pubkey_encoding(key.data(), ctx.flags)?;
-                        let r = checker::chain_check_sign(ctx.tx, ctx.txin_idx, subscript.as_slice(), key.data(), sig.data());
+                        let r = checker::chain_check_sign(ctx.tx, ctx.txin_idx, ctx.amount, ctx.txdata, subscript.as_slice(), key.data(), sig.data(), ctx.flags);
 
                         if ctx.flags.script_verify.is_null_fail() && sig.data().len() != 0 {
                            match r {
@@ -524,7 +566,29 @@ impl Interpreter {
                      }
                   },
 
+                  OP_CHECKSIGADD => {
+                     if !ctx.flags.sig_version.is_tapscript() {
+                        raise_script_interpret_error!(BadOpcode);
+                     }
+                     if self.stack.len() < 3 {
+                        raise_script_interpret_error!(InvalidStackOperation);
+                     }
+                     let n = self.stack.at(-2)?.value(is_require_minimal, 4)?;
+                     let r = {
+                        let sig = self.stack.at(-3)?;
+                        let key = self.stack.at(-1)?;
+                        eval_checksig_tapscript(sig.data(), key.data(), ctx)?
+                     };
+                     self.stack.pop()?;
+                     self.stack.pop()?;
+                     self.stack.pop()?;
+                     self.stack.push_value(n + if r { 1 } else { 0 });
+                  },
+
                   _ if op == OP_CHECKMULTISIG || op == OP_CHECKMULTISIGVERIFY => {
+                     if ctx.flags.sig_version.is_tapscript() {
+                        raise_script_interpret_error!(TapscriptCheckMultisig);
+                     }
                      if self.stack.len() < 1 {
                         raise_script_interpret_error!(InvalidStackOperation);
                      }
@@ -560,9 +624,10 @@ impl Interpreter {
                            if !ctx.flags.sig_version.is_base() {
                               tmp.to_vec()
                            } else {
-                              sigs.iter().fold(tmp.to_vec(), |acc, sig| {
-                                 Parser::find_and_delete(acc.as_slice(), sig.data()).0
-                              })
+                              sigs.iter().fold(Ok(tmp.to_vec()), |acc:crate::Result<Vec<u8>>, sig| {
+                                 let pushed = super::assembler::assemble_push_data(sig.data())?;
+                                 Ok(Parser::find_and_delete(acc?.as_slice(), pushed.as_slice()).0)
+                              })?
                            }
                         };
 
@@ -574,7 +639,7 @@ impl Interpreter {
                            //println!("checkmultisig: isig={}, ikey={}", isig, ikey);
                            checker::check_signature_encoding(sig, ctx.flags)?;
                            checker::check_pubkey_encoding(key, ctx.flags)?;
-                           if checker::chain_check_sign(ctx.tx, ctx.txin_idx, subscript.as_slice(), key, sig).unwrap_or(false) {
+                           if checker::chain_check_sign(ctx.tx, ctx.txin_idx, ctx.amount, ctx.txdata, subscript.as_slice(), key, sig, ctx.flags).unwrap_or(false) {
                               //println!("  checkmultisig successeed: {}, {}", sig.len(), key.len());
                               isig -= 1;
                            }
@@ -664,7 +729,44 @@ impl Interpreter {
    }
 }
 
+fn eval_checksig_tapscript(sig:&[u8], key:&[u8], ctx:&mut Context) -> crate::Result<bool> {
+   let is_success = 0 < sig.len();
+   if is_success {
+      ctx.execdata.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+      if ctx.execdata.validation_weight_left < 0 {
+         raise_script_interpret_error!(TapscriptValidationWeight);
+      }
+   }
+   if key.len() == 0 {
+      raise_script_interpret_error!(TapscriptEmptyPubkey);
+   } else if key.len() == 32 {
+      if is_success {
+         checker::check_schnorr_signature(ctx.tx, ctx.txin_idx, ctx.txdata, &ctx.execdata, key, sig, ctx.flags)?;
+      }
+   } else if ctx.flags.script_verify.is_discourage_upgradable_pubkey_type() {
+      // unknown pubkey types are anyone-can-spend for forward compatibility
+      raise_script_interpret_error!(DiscourageUpgradablePubkeyType);
+   }
+   Ok(is_success)
+}
+
 pub fn verify(sigscr:&[u8], pkscr:&[u8], tx:&Tx, in_idx:usize, flags:&Flags) -> crate::Result<()> {
+   verify_with_amount(sigscr, pkscr, tx, in_idx, 0, None, flags)
+}
+
+pub fn verify_with_amount(
+   sigscr:&[u8],
+   pkscr:&[u8],
+   tx:&Tx,
+   in_idx:usize,
+   amount:Amount,
+   txdata:Option<&checker::PrecomputedTxData>,
+   flags:&Flags
+) -> crate::Result<()> {
+   let empty = Witness::new_null();
+   let witness = tx.ins.get(in_idx).map(|txin| &txin.witness).unwrap_or(&empty);
+   let base_flags = Flags { sig_version: SigVersion::Base, ..*flags };
+
    if flags.script_verify.is_sig_push_only() {
       if !Parser::is_push_only(sigscr) {
          raise_script_interpret_error!(SigPushOnly);
@@ -672,14 +774,14 @@ pub fn verify(sigscr:&[u8], pkscr:&[u8], tx:&Tx, in_idx:usize, flags:&Flags) ->
    }
    
    let mut interpreter = Interpreter::new();
-   let _ = interpreter.eval(sigscr, tx, in_idx, flags)?;
+   let _ = interpreter.eval_with_amount(sigscr, tx, in_idx, amount, txdata, &base_flags)?;
    
    let p2sh = match flags.script_verify.is_p2sh() {
       true => Some(interpreter.clone()),
       false => None,
    };
    
-   let _ = interpreter.eval(pkscr, tx, in_idx, flags)?;
+   let _ = interpreter.eval_with_amount(pkscr, tx, in_idx, amount, txdata, &base_flags)?;
    if interpreter.stack().len() < 1 {
       raise_script_interpret_error!(EvalFalse);
    }
@@ -687,9 +789,19 @@ pub fn verify(sigscr:&[u8], pkscr:&[u8], tx:&Tx, in_idx:usize, flags:&Flags) ->
       raise_script_interpret_error!(EvalFalse);
    }
 
-   // witness
+   // native witness program
+   let mut had_witness = false;
    if flags.script_verify.is_witness() {
-      raise_script_error!("witness is not implemented yet");
+      if let Some((version, program)) = Parser::parse_witness_program(pkscr) {
+         had_witness = true;
+         if 0 < sigscr.len() {
+            // scriptSig must be empty for native witness program
+            raise_script_interpret_error!(WitnessMalleated);
+         }
+         verify_witness_program(witness, version, program, false, tx, in_idx, amount, txdata, flags)?;
+         // leave one item to satisfy CLEANSTACK
+         interpreter.truncate_stack(1);
+      }
    }
 
    if p2sh.is_some() && Parser::is_pay_to_script_hash(pkscr) {
@@ -699,15 +811,26 @@ pub fn verify(sigscr:&[u8], pkscr:&[u8], tx:&Tx, in_idx:usize, flags:&Flags) ->
       interpreter = p2sh.unwrap(); //re-bind
       assert!(0 < interpreter.stack().len());
       let pkscr2 = interpreter.pop_stack().unwrap();
-      let _ = interpreter.eval(pkscr2.data(), tx, in_idx, flags)?;
+      let _ = interpreter.eval_with_amount(pkscr2.data(), tx, in_idx, amount, txdata, &base_flags)?;
       if interpreter.stack().len() < 1 {
          raise_script_interpret_error!(EvalFalse);
       }
       if ! interpreter.stack().at(-1)?.as_bool() {
          raise_script_interpret_error!(EvalFalse);
       }
+
+      // P2SH nested witness program
       if flags.script_verify.is_witness() {
-         raise_script_error!("witness is not implemented yet");
+         if let Some((version, program)) = Parser::parse_witness_program(pkscr2.data()) {
+            had_witness = true;
+            let expect = super::assembler::assemble_push_data(pkscr2.data())?;
+            if sigscr != expect.as_slice() {
+               // scriptSig must be exactly a single push of the redeemScript
+               raise_script_interpret_error!(WitnessMalleatedP2sh);
+            }
+            verify_witness_program(witness, version, program, true, tx, in_idx, amount, txdata, flags)?;
+            interpreter.truncate_stack(1);
+         }
       }
    }
 
@@ -721,9 +844,367 @@ pub fn verify(sigscr:&[u8], pkscr:&[u8], tx:&Tx, in_idx:usize, flags:&Flags) ->
 
    if flags.script_verify.is_witness() {
       //assert!(flags.script_verify.is_p2sh());
-      raise_script_error!("witness is not implemented yet");
+      if !had_witness && !witness.is_null() {
+         raise_script_interpret_error!(WitnessUnexpected);
+      }
    }
    
    Ok(())
 }
-   
+
+/// Verifies all inputs of the tx against their spent outputs.
+/// Returns an error if some prevout is missing, otherwise the result of each input.
+pub fn verify_tx(
+   tx:&Tx,
+   prevouts:&std::collections::HashMap<TxOutPoint, TxOut>,
+   flags:&Flags
+) -> crate::Result<Vec<crate::Result<()>>> {
+   let spent_outputs = tx.ins.iter().map(|txin| {
+      match prevouts.get(&txin.prevout) {
+         Some(txout) => Ok(txout.clone()),
+         None => raise_script_error!(format!("prevout is not found: {}:{}", txin.prevout.txid, txin.prevout.n)),
+      }
+   }).collect::<crate::Result<Vec<TxOut>>>()?;
+   let txdata = checker::PrecomputedTxData::new_with_spent_outputs(tx, spent_outputs)?;
+   let results = tx.ins.iter().enumerate().map(|(i, txin)| {
+      let spent = &txdata.spent_outputs[i];
+      verify_with_amount(&txin.script_sig.bytecode[..], &spent.script_pubkey.bytecode[..], tx, i, spent.value, Some(&txdata), flags)
+   }).collect();
+   Ok(results)
+}
+
+fn verify_witness_program(
+   witness:&Witness,
+   version:u8,
+   program:&[u8],
+   is_p2sh:bool,
+   tx:&Tx,
+   in_idx:usize,
+   amount:Amount,
+   txdata:Option<&checker::PrecomputedTxData>,
+   flags:&Flags
+) -> crate::Result<()> {
+   let execdata = checker::ScriptExecutionData::default();
+   if version == 0 {
+      let witness_flags = Flags { sig_version: SigVersion::WitnessV0, ..*flags };
+      if program.len() == 32 { // P2WSH
+         if witness.len() == 0 {
+            raise_script_interpret_error!(WitnessProgramWitnessEmpty);
+         }
+         let (script, items) = witness.as_slice().split_last().unwrap();
+         let hash = crate::ui::create_sha256().u8_to_u8(script.as_slice());
+         if &hash[..] != program {
+            raise_script_interpret_error!(WitnessProgramMismatch);
+         }
+         execute_witness_script(script.as_slice(), items, tx, in_idx, amount, txdata, &execdata, &witness_flags)
+      } else if program.len() == 20 { // P2WPKH
+         if witness.len() != 2 {
+            raise_script_interpret_error!(WitnessProgramMismatch);
+         }
+         let mut script = Vec::<u8>::with_capacity(25);
+         script.push(OP_DUP);
+         script.push(OP_HASH160);
+         script.push(OP_PUSHDATAFIX_14);
+         script.extend_from_slice(program);
+         script.push(OP_EQUALVERIFY);
+         script.push(OP_CHECKSIG);
+         execute_witness_script(script.as_slice(), witness.as_slice(), tx, in_idx, amount, txdata, &execdata, &witness_flags)
+      } else {
+         raise_script_interpret_error!(WitnessProgramWrongLength)
+      }
+   } else if version == 1 && program.len() == 32 && !is_p2sh {
+      if !flags.script_verify.is_taproot() {
+         // taproot is not active yet
+         return Ok(());
+      }
+      verify_taproot(witness, program, tx, in_idx, amount, txdata, flags)
+   } else if flags.script_verify.is_discourage_upgradable_witness_program() {
+      raise_script_interpret_error!(DiscourageUpgradableWitnessProgram)
+   } else {
+      // higher versions are anyone-can-spend for forward compatibility
+      Ok(())
+   }
+}
+
+fn verify_taproot(
+   witness:&Witness,
+   program:&[u8],
+   tx:&Tx,
+   in_idx:usize,
+   amount:Amount,
+   txdata:Option<&checker::PrecomputedTxData>,
+   flags:&Flags
+) -> crate::Result<()> {
+   use super::taproot;
+   let mut execdata = checker::ScriptExecutionData::default();
+   let mut stack = witness.as_slice();
+   if stack.len() == 0 {
+      raise_script_interpret_error!(WitnessProgramWitnessEmpty);
+   }
+   if 2 <= stack.len() {
+      let last = &stack[stack.len()-1];
+      if 0 < last.len() && last[0] == ANNEX_TAG {
+         execdata.annex_hash = Some(taproot::annex_hash(last.as_slice())?);
+         stack = &stack[..(stack.len()-1)];
+      }
+   }
+
+   if stack.len() == 1 { // key path spending
+      let key_flags = Flags { sig_version: SigVersion::Taproot, ..*flags };
+      return checker::check_schnorr_signature(tx, in_idx, txdata, &execdata, program, stack[0].as_slice(), &key_flags);
+   }
+
+   // script path spending
+   let (control, stack) = stack.split_last().unwrap();
+   let (script, items) = stack.split_last().unwrap();
+   if !taproot::is_valid_control_size(control.as_slice()) {
+      raise_script_interpret_error!(TaprootWrongControlSize);
+   }
+   let leaf_version = taproot::get_leaf_version(control.as_slice());
+   execdata.tapleaf_hash = taproot::tapleaf_hash(leaf_version, script.as_slice())?;
+   if !taproot::verify_commitment(control.as_slice(), program, &execdata.tapleaf_hash).unwrap_or(false) {
+      raise_script_interpret_error!(WitnessProgramMismatch);
+   }
+   if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+      if flags.script_verify.is_discourage_upgradable_taproot_version() {
+         raise_script_interpret_error!(DiscourageUpgradableTaprootVersion);
+      }
+      // unknown leaf versions are anyone-can-spend for forward compatibility
+      return Ok(());
+   }
+
+   execdata.validation_weight_left = crate::ui::bitcoin::serialize(witness, &())?.len() as i64 + VALIDATION_WEIGHT_OFFSET;
+   for parsed in Parser::iter(script.as_slice()) {
+      let parsed = parsed.map_err(|_| script_interpret_error!(BadOpcode))?;
+      if is_op_success(parsed.opcode) {
+         if flags.script_verify.is_discourage_op_success() {
+            raise_script_interpret_error!(DiscourageOpSuccess);
+         }
+         return Ok(());
+      }
+   }
+   if MAX_STACK_SIZE < items.len() {
+      raise_script_interpret_error!(StackSize);
+   }
+   let tapscript_flags = Flags { sig_version: SigVersion::Tapscript, ..*flags };
+   execute_witness_script(script.as_slice(), items, tx, in_idx, amount, txdata, &execdata, &tapscript_flags)
+}
+
+fn execute_witness_script(
+   script:&[u8],
+   items:&[Vec<u8>],
+   tx:&Tx,
+   in_idx:usize,
+   amount:Amount,
+   txdata:Option<&checker::PrecomputedTxData>,
+   execdata:&checker::ScriptExecutionData,
+   flags:&Flags
+) -> crate::Result<()> {
+   let mut stack = Stack::new();
+   for item in items.iter() {
+      if MAX_SCRIPT_ELEMENT_SIZE < item.len() {
+         raise_script_interpret_error!(PushSize);
+      }
+      stack.push_data(item.as_slice());
+   }
+
+   let mut interpreter = Interpreter::new_with_stack(stack);
+   let _ = interpreter.eval_with_execdata(script, tx, in_idx, amount, txdata, execdata, flags)?;
+
+   // witness scripts implicitly require cleanstack
+   if interpreter.stack().len() != 1 {
+      raise_script_interpret_error!(EvalFalse);
+   }
+   if ! interpreter.stack().at(-1)?.as_bool() {
+      raise_script_interpret_error!(EvalFalse);
+   }
+   Ok(())
+}
+
+#[test]
+fn test_verify_taproot() {
+   use crate::bitcoin::datatypes::{TxOut, Script};
+   use super::flags::ScriptVerify;
+   use crate::utils::h2b;
+   let mut tx = crate::ui::bitcoin::hex_to_tx("020000000111111111111111111111111111111111111111111111111111111111111111110000000000ffffffff01905f010000000000160014222222222222222222222222222222222222222200000000").unwrap();
+   let pkscr = h2b("5120b2c477733f693c8e8c8480960d0f5c5d696ba1f24759987510864e82d65d79a1").unwrap();
+   let spent = TxOut { value:100000, script_pubkey:Script::new(pkscr.clone()) };
+   let txdata = checker::PrecomputedTxData::new_with_spent_outputs(&tx, vec![spent]).unwrap();
+   let flags = Flags {
+      script_verify: ScriptVerify::default().p2sh(true).witness(true).taproot(true),
+      sig_version: SigVersion::Base,
+   };
+   let verify_witness = |tx:&mut Tx, stack:Vec<&str>| {
+      tx.ins[0].witness = Witness::new(stack.iter().map(|h| h2b(*h).unwrap().to_vec()).collect());
+      verify_with_amount(&[], &pkscr, tx, 0, 100000, Some(&txdata), &flags)
+   };
+
+   // key path, SIGHASH_DEFAULT
+   let sig = "e713c317fe904d69f8a7f0ef4341e211b35c6f166ece32927d54dba8a9e22668ad0dbf94751ed067556567a7503d3e86eb85a943bbf1801216e963efe207a5ef";
+   assert_matches!(verify_witness(&mut tx, vec![sig]), Ok(()));
+   let sig01 = format!("{}01", sig);
+   assert_matches!(verify_witness(&mut tx, vec![sig01.as_str()]), Err(_));
+
+   // key path, SIGHASH_SINGLE|ANYONECANPAY with annex
+   let sig = "d8854e0241aa31d1f8253b84ba918aca674b040b41a497119a82f916eacd222013fd559617f0ee2d2ad3c318cf9000eb402a8f7bebc92889c2de2fc14a981f0c83";
+   assert_matches!(verify_witness(&mut tx, vec![sig, "500102"]), Ok(()));
+   assert_matches!(verify_witness(&mut tx, vec![sig, "500103"]), Err(_));
+   assert_matches!(verify_witness(&mut tx, vec![sig]), Err(_));
+
+   // script path: <pubkey> OP_CHECKSIG, the first leaf of two
+   let sig     = "a08d37bdb91692cfe1235e3e90a394d6eca6b54a7940351db2155f585eca8cc17a0c6fcaf03956a8af6efdc3fe36eda4313497f64c290a1447717501669caa47";
+   let script  = "202f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4ac";
+   let control = "c1f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9196ed1c843c4a0124f49a14f0693a476863f1c53d37b5d027b82ae7de57f6997";
+   assert_matches!(verify_witness(&mut tx, vec![sig, script, control]), Ok(()));
+   let control_bad_parity = format!("c0{}", &control[2..]);
+   assert_matches!(verify_witness(&mut tx, vec![sig, script, control_bad_parity.as_str()]), Err(_));
+   assert_matches!(verify_witness(&mut tx, vec![sig, script, &control[0..64]]), Err(_));
+   assert_matches!(verify_witness(&mut tx, vec!["", script, control]), Err(_));
+   // a script not committed in the output is rejected even if it is OP_SUCCESS
+   assert_matches!(verify_witness(&mut tx, vec!["50", control]), Err(_));
+
+   // without taproot flag, v1 program is anyone-can-spend
+   tx.ins[0].witness = Witness::new_null();
+   let flags = Flags { script_verify: flags.script_verify.taproot(false), ..flags };
+   assert_matches!(verify_with_amount(&[], &pkscr, &tx, 0, 100000, Some(&txdata), &flags), Ok(()));
+}
+
+#[test]
+fn test_verify_tapscript() {
+   use crate::bitcoin::datatypes::{TxOut, Script};
+   use crate::crypto::secp256k1::schnorr;
+   use super::flags::ScriptVerify;
+   use super::taproot;
+   use super::error::InterpretErrorCode as C;
+   use secp256k1::Secp256k1;
+   use secp256k1::key::{PublicKey, SecretKey};
+   let ctx = Secp256k1::new();
+   let sk = [2u8;32];
+   let internal = schnorr::to_xonly(&PublicKey::from_secret_key(&ctx, &SecretKey::from_slice(&[1u8;32]).unwrap())).0;
+   let key = schnorr::to_xonly(&PublicKey::from_secret_key(&ctx, &SecretKey::from_slice(&sk).unwrap())).0;
+   let push_key = |v:&mut Vec<u8>| { v.push(0x20); v.extend_from_slice(&key); };
+
+   let tx = crate::ui::bitcoin::hex_to_tx("020000000111111111111111111111111111111111111111111111111111111111111111110000000000ffffffff01905f010000000000160014222222222222222222222222222222222222222200000000").unwrap();
+   let flags = Flags {
+      script_verify: ScriptVerify::default().p2sh(true).witness(true).taproot(true),
+      sig_version: SigVersion::Base,
+   };
+   let is_error = |r:crate::Result<()>, code:C| match r {
+      Err(crate::Error::BitcoinInterpretScript(ref e)) => e.is(code),
+      _ => false,
+   };
+
+   // commits the script as the single leaf and returns (scriptPubKey, control block, tapleaf hash, txdata)
+   let commit = |script:&[u8]| {
+      let leaf = taproot::tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script).unwrap();
+      let mut q = schnorr::lift_x(&internal).unwrap();
+      q.add_exp_assign(&ctx, &taproot::taptweak_hash(&internal, Some(&leaf))[..]).unwrap();
+      let (qx, odd) = schnorr::to_xonly(&q);
+      let mut pkscr = vec![0x51, 0x20];
+      pkscr.extend_from_slice(&qx);
+      let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | (odd as u8)];
+      control.extend_from_slice(&internal);
+      let spent = TxOut { value:100000, script_pubkey:Script::new(pkscr.clone()) };
+      let txdata = checker::PrecomputedTxData::new_with_spent_outputs(&tx, vec![spent]).unwrap();
+      (pkscr, control, leaf, txdata)
+   };
+   let sign = |leaf:&[u8;32], codeseparator_pos:u32, txdata:&checker::PrecomputedTxData| {
+      let execdata = checker::ScriptExecutionData { tapleaf_hash:*leaf, codeseparator_pos:codeseparator_pos, ..Default::default() };
+      let sigflags = Flags { sig_version: SigVersion::Tapscript, ..flags };
+      let hash = checker::get_hash_taproot(&tx, 0, sighash::DEFAULT, &execdata, txdata, &sigflags).unwrap();
+      schnorr::sign(&ctx, &sk, &hash, &[0u8;32]).unwrap().to_vec()
+   };
+   let spend = |script:&[u8], items:Vec<Vec<u8>>, flags:&Flags| {
+      let (pkscr, control, _, txdata) = commit(script);
+      let mut tx = tx.clone();
+      let mut stack = items;
+      stack.push(script.to_vec());
+      stack.push(control);
+      tx.ins[0].witness = Witness::new(stack);
+      verify_with_amount(&[], &pkscr, &tx, 0, 100000, Some(&txdata), flags)
+   };
+
+   // <key> OP_CHECKSIG
+   let mut script = Vec::new();
+   push_key(&mut script);
+   script.push(OP_CHECKSIG);
+   let (_, _, leaf, txdata) = commit(&script);
+   let sig = sign(&leaf, 0xFFFFFFFF, &txdata);
+   assert_matches!(spend(&script, vec![sig.clone()], &flags), Ok(()));
+   let mut sig04 = sig.clone();
+   sig04.push(0x04);
+   assert!(is_error(spend(&script, vec![sig04], &flags), C::SchnorrSigHashType));
+   {
+      // missing spent outputs is not a hash type error
+      let (pkscr, control, _, _) = commit(&script);
+      let mut tx = tx.clone();
+      tx.ins[0].witness = Witness::new(vec![sig.clone(), script.clone(), control]);
+      let r = verify_with_amount(&[], &pkscr, &tx, 0, 100000, None, &flags);
+      assert!(r.is_err());
+      assert!(!is_error(r, C::SchnorrSigHashType));
+   }
+
+   // OP_SUCCESSx makes the script succeed even if it is not executed or the rest is unparsable
+   for s in &["50", "0063506800", "504c"] {
+      let s = crate::utils::h2b(*s).unwrap();
+      assert_matches!(spend(&s, vec![], &flags), Ok(()));
+      let discourage = Flags { script_verify: flags.script_verify.discourage_op_success(true), ..flags };
+      assert!(is_error(spend(&s, vec![], &discourage), C::DiscourageOpSuccess));
+   }
+
+   // <key> OP_CHECKSIG <key> OP_CHECKSIGADD OP_2 OP_NUMEQUAL
+   let mut script = Vec::new();
+   push_key(&mut script);
+   script.push(OP_CHECKSIG);
+   push_key(&mut script);
+   script.extend_from_slice(&[OP_CHECKSIGADD, OP_2, OP_NUMEQUAL]);
+   let (_, _, leaf, txdata) = commit(&script);
+   let sig = sign(&leaf, 0xFFFFFFFF, &txdata);
+   assert_matches!(spend(&script, vec![sig.clone(), sig.clone()], &flags), Ok(()));
+   assert!(is_error(spend(&script, vec![vec![], sig.clone()], &flags), C::EvalFalse));
+   assert!(is_error(spend(&script, vec![sig.clone(), vec![]], &flags), C::EvalFalse));
+
+   // <key> (OP_2DUP OP_CHECKSIGVERIFY)*n OP_CHECKSIG, the budget is the witness size + 50 and each sigop costs 50
+   let checksigs = |n:usize| {
+      let mut script = Vec::new();
+      push_key(&mut script);
+      for _ in 0..n {
+         script.extend_from_slice(&[OP_2DUP, OP_CHECKSIGVERIFY]);
+      }
+      script.push(OP_CHECKSIG);
+      let (_, _, leaf, txdata) = commit(&script);
+      let sig = sign(&leaf, 0xFFFFFFFF, &txdata);
+      spend(&script, vec![sig], &flags)
+   };
+   assert_matches!(checksigs(2), Ok(())); // 150 <= 139 + 50
+   assert!(is_error(checksigs(3), C::TapscriptValidationWeight)); // 200 > 141 + 50
+
+   // OP_NOP OP_CODESEPARATOR <key> OP_CHECKSIG: the sighash commits the opcode position of the last OP_CODESEPARATOR
+   let mut script = vec![OP_NOP, OP_CODESEPARATOR];
+   push_key(&mut script);
+   script.push(OP_CHECKSIG);
+   let (_, _, leaf, txdata) = commit(&script);
+   assert_matches!(spend(&script, vec![sign(&leaf, 1, &txdata)], &flags), Ok(()));
+   assert_matches!(spend(&script, vec![sign(&leaf, 0xFFFFFFFF, &txdata)], &flags), Err(_));
+
+   // v1 program before taproot activation is anyone-can-spend, even if upgradable programs are discouraged
+   let (pkscr, _, _, txdata) = commit(&[OP_1]);
+   let pre_taproot = Flags {
+      script_verify: flags.script_verify.taproot(false).discourage_upgradable_witness_program(true),
+      ..flags
+   };
+   assert_matches!(verify_with_amount(&[], &pkscr, &tx, 0, 100000, Some(&txdata), &pre_taproot), Ok(()));
+   // but P2SH wrapped v1 program is not taproot
+   {
+      use crate::crypto::digest::{Hash160, u8_to_u8};
+      let mut p2sh = vec![OP_HASH160, 0x14];
+      p2sh.extend_from_slice(&u8_to_u8(&mut Hash160::new(), &pkscr[..]));
+      p2sh.push(OP_EQUAL);
+      let mut script_sig = vec![pkscr.len() as u8];
+      script_sig.extend_from_slice(&pkscr);
+      let mut tx = tx.clone();
+      tx.ins[0].witness = Witness::new(vec![vec![]]);
+      assert!(is_error(verify_with_amount(&script_sig, &p2sh, &tx, 0, 100000, Some(&txdata), &pre_taproot),
+                       C::DiscourageUpgradableWitnessProgram));
+   }
+}
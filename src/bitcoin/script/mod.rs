@@ -20,9 +20,13 @@ pub use self::instruction::Instruction;
 
 pub mod parser;
 
+pub mod solver;
+pub use self::solver::{Solver, TxOutType};
+
 pub mod stack;
 pub mod checker;
+pub mod taproot;
 pub mod interpreter;
-pub use self::interpreter::{Interpreter, verify};
+pub use self::interpreter::{Interpreter, verify, verify_with_amount, verify_tx};
 
 
@@ -137,6 +137,9 @@ defop!(OP_NOP8, 0xb7);
 defop!(OP_NOP9, 0xb8);
 defop!(OP_NOP10, 0xb9);
 
+// tapscript (BIP342)
+defop!(OP_CHECKSIGADD, 0xba);
+
 
 // template matching params
 defop!(OP_SMALLINTEGER, 0xfa);
@@ -186,6 +189,14 @@ pub fn get_opcode_for_pushdata(data:&[u8]) -> crate::Result<u8> {
    }      
 }
 
+/// OP_SUCCESSx of BIP342: makes a tapscript unconditionally valid.
+pub fn is_op_success(op:u8) -> bool {
+   match op {
+      80 | 98 | 126 ..= 129 | 131 ..= 134 | 137 ..= 138 | 141 ..= 142 | 149 ..= 153 | 187 ..= 254 => true,
+      _ => false,
+   }
+}
+
 const CONTEXT_SOURCE:u32  = 0x01;
 const CONTEXT_EXECUTE:u32 = 0x02;
 const CONTEXT_NONE:u32    = 0x00;
@@ -403,7 +414,7 @@ pub const OPCODE_INFO: [&OpCodeInfo; 256] = [
    &OpCodeInfo{ code:0xb8, name:"OP_NOP9", num_operands:0, validity:CONTEXT_ALL, },
    &OpCodeInfo{ code:0xb9, name:"OP_NOP10", num_operands:0, validity:CONTEXT_ALL, },
 
-   &OpCodeInfo{ code:0xba, name:"OP_UNUSED_BA", num_operands:0, validity:CONTEXT_NONE, },
+   &OpCodeInfo{ code:0xba, name:"OP_CHECKSIGADD", num_operands:0, validity:CONTEXT_ALL, },
    &OpCodeInfo{ code:0xbb, name:"OP_UNUSED_BB", num_operands:0, validity:CONTEXT_NONE, },
    &OpCodeInfo{ code:0xbc, name:"OP_UNUSED_BC", num_operands:0, validity:CONTEXT_NONE, },
    &OpCodeInfo{ code:0xbd, name:"OP_UNUSED_BD", num_operands:0, validity:CONTEXT_NONE, },
@@ -502,3 +513,16 @@ lazy_static! {
 fn test_infoarray() {
    assert_eq!(256, OPCODE_INFO.len());
 }
+
+#[test]
+fn test_is_op_success() {
+   assert_eq!(true,  is_op_success(OP_RESERVED));
+   assert_eq!(true,  is_op_success(OP_CAT));
+   assert_eq!(true,  is_op_success(0xbb));
+   assert_eq!(true,  is_op_success(0xfe));
+   assert_eq!(false, is_op_success(OP_CHECKSIGADD));
+   assert_eq!(false, is_op_success(OP_INVALIDOPCODE));
+   assert_eq!(true,  is_op_success(OP_VER));
+   assert_eq!(false, is_op_success(OP_VERIF));
+   assert_eq!(false, is_op_success(OP_CHECKSIG));
+}
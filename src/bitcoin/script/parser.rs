@@ -71,15 +71,111 @@ impl Parser {
    }
    pub fn is_push_only(bytecode: &[u8]) -> bool {
       Parser::iter(bytecode).all(|r| {
-         r.is_ok() && r.unwrap().opcode < OP_16
+         r.is_ok() && r.unwrap().opcode <= OP_16
       })
    }
+   /// (script.cpp: CScript::GetSigOpCount)
+   /// If not accurate, a multisig is counted as MAX_PUBKEYS_PER_MULTISIG.
+   /// Counting stops at the first unparsable instruction.
+   pub fn get_sig_op_count(bytecode: &[u8], accurate: bool) -> usize {
+      use super::apriori::MAX_PUBKEYS_PER_MULTISIG;
+      let mut n = 0usize;
+      let mut last = OP_INVALIDOPCODE;
+      for r in Parser::iter(bytecode) {
+         let op = match r {
+            Ok(p) => p.opcode,
+            Err(_) => break,
+         };
+         match op {
+            OP_CHECKSIG | OP_CHECKSIGVERIFY => n += 1,
+            OP_CHECKMULTISIG | OP_CHECKMULTISIGVERIFY => {
+               n += if accurate && OP_1 <= last && last <= OP_16 {
+                  (last - OP_1 + 1) as usize
+               } else {
+                  MAX_PUBKEYS_PER_MULTISIG
+               };
+            },
+            _ => (),
+         }
+         last = op;
+      }
+      n
+   }
+   /// (script.cpp: CScript::GetSigOpCount(const CScript& scriptSig))
+   /// Accurate count of the redeem script pushed at last by the scriptSig if the scriptPubKey is P2SH.
+   pub fn get_p2sh_sig_op_count(script_pubkey: &[u8], script_sig: &[u8]) -> usize {
+      if !Self::is_pay_to_script_hash(script_pubkey) {
+         return Self::get_sig_op_count(script_pubkey, true);
+      }
+      match Self::get_last_push_data(script_sig) {
+         Some(redeem_script) => Self::get_sig_op_count(redeem_script, true),
+         None => 0,
+      }
+   }
+   /// (interpreter.cpp: CountWitnessSigOps)
+   /// witness is the stack of the input, the P2SH wrapped witness program is also counted.
+   pub fn get_witness_sig_op_count(script_sig: &[u8], script_pubkey: &[u8], witness: &[Vec<u8>], flags: &super::flags::ScriptVerify) -> usize {
+      if !flags.is_witness() {
+         return 0;
+      }
+      if let Some((version, program)) = Self::parse_witness_program(script_pubkey) {
+         return Self::witness_sig_ops(version, program, witness);
+      }
+      if Self::is_pay_to_script_hash(script_pubkey) && Self::is_push_only(script_sig) {
+         if let Some(redeem_script) = Self::get_last_push_data(script_sig) {
+            if let Some((version, program)) = Self::parse_witness_program(redeem_script) {
+               return Self::witness_sig_ops(version, program, witness);
+            }
+         }
+      }
+      0
+   }
+   fn witness_sig_ops(version: u8, program: &[u8], witness: &[Vec<u8>]) -> usize {
+      match (version, program.len(), witness.last()) {
+         (0, 20, _) => 1,
+         (0, 32, Some(witness_script)) => Self::get_sig_op_count(&witness_script[..], true),
+         _ => 0,
+      }
+   }
+   /// The data of the last instruction. OP_0..OP_16 push no data here as in bitcoin core.
+   /// None if the script is unparsable or contains a non push opcode.
+   fn get_last_push_data<'x>(script_sig: &'x [u8]) -> Option<&'x [u8]> {
+      let mut last:&[u8] = &[];
+      for r in Parser::iter(script_sig) {
+         match r {
+            Ok(ref p) if p.opcode <= OP_16 => {
+               let len = p.instruction.data().map_or(0, |d| d.len());
+               last = &script_sig[p.offset + p.size - len .. p.offset + p.size];
+            },
+            _ => return None,
+         }
+      }
+      Some(last)
+   }
    pub fn is_pay_to_script_hash(bytecode: &[u8]) -> bool {
       bytecode.len() == 23
          && bytecode[0] == OP_HASH160
          && bytecode[1] == 0x14
          && bytecode[22] == OP_EQUAL
    }
+   pub fn parse_witness_program<'x>(bytecode: &'x [u8]) -> Option<(u8, &'x [u8])> {
+      let len = bytecode.len();
+      if len < 4 || 42 < len {
+         return None;
+      }
+      let version = match bytecode[0] {
+         OP_0 => 0u8,
+         x if OP_1 <= x && x <= OP_16 => x - OP_1 + 1,
+         _ => return None,
+      };
+      if (bytecode[1] as usize) + 2 != len {
+         return None;
+      }
+      Some((version, &bytecode[2..]))
+   }
+   pub fn is_witness_program(bytecode: &[u8]) -> bool {
+      Self::parse_witness_program(bytecode).is_some()
+   }
 }
 
 impl <'a> Iter<'a> {
@@ -252,3 +348,76 @@ fn test_parse() {
    assert_eq!("[72] [65]", format!("{}", FmtVec(instructions)));
 }
    
+
+#[test]
+fn test_parse_witness_program() {
+   let p2wpkh = crate::utils::h2b("00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
+   assert_matches!(Parser::parse_witness_program(&p2wpkh), Some((0, prog)) if prog == &p2wpkh[2..]);
+
+   let v1 = crate::utils::h2b("5102abcd").unwrap();
+   assert_matches!(Parser::parse_witness_program(&v1), Some((1, _)));
+
+   let short = crate::utils::h2b("0001ab").unwrap();
+   assert_eq!(Parser::is_witness_program(&short), false);
+   let mismatch = crate::utils::h2b("0003abcd").unwrap();
+   assert_eq!(Parser::is_witness_program(&mismatch), false);
+   let not_version = crate::utils::h2b("4f02abcd").unwrap();
+   assert_eq!(Parser::is_witness_program(&not_version), false);
+}
+
+#[test]
+fn test_is_push_only() {
+   use crate::utils::h2b;
+   // OP_0, push 1 byte, OP_1NEGATE, OP_1 and OP_16
+   assert_eq!(Parser::is_push_only(&h2b("00010a4f5160").unwrap()), true);
+   assert_eq!(Parser::is_push_only(&h2b("60").unwrap()), true);
+   assert_eq!(Parser::is_push_only(&[]), true);
+   // OP_NOP
+   assert_eq!(Parser::is_push_only(&h2b("0061").unwrap()), false);
+   // truncated push
+   assert_eq!(Parser::is_push_only(&h2b("51024c").unwrap()), false);
+}
+
+#[test]
+fn test_get_sig_op_count() {
+   use crate::utils::h2b;
+   let pk = "210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+   let multisig = h2b(format!("52{}{}52ae", pk, pk)).unwrap();
+   assert_eq!(Parser::get_sig_op_count(&multisig[..], true), 2);
+   assert_eq!(Parser::get_sig_op_count(&multisig[..], false), 20);
+   let checksigs = h2b("ac00adaf").unwrap();
+   assert_eq!(Parser::get_sig_op_count(&checksigs[..], true), 22);
+   // stops at the truncated push
+   let truncated = h2b("acac4c").unwrap();
+   assert_eq!(Parser::get_sig_op_count(&truncated[..], true), 2);
+}
+
+#[test]
+fn test_get_p2sh_and_witness_sig_op_count() {
+   use crate::utils::h2b;
+   use super::flags::ScriptVerify;
+   let pk = "210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+   let redeem = h2b(format!("52{}{}52ae", pk, pk)).unwrap();
+   let p2sh = h2b("a9141018853670f9f3b0582c5b9ee8ce93764ac32b9387").unwrap();
+   let mut script_sig = vec![0x00, 0x4c, redeem.len() as u8];
+   script_sig.extend_from_slice(&redeem[..]);
+   assert_eq!(Parser::get_p2sh_sig_op_count(&p2sh[..], &script_sig[..]), 2);
+   assert_eq!(Parser::get_p2sh_sig_op_count(&redeem[..], &[]), 2);
+   assert_eq!(Parser::get_p2sh_sig_op_count(&p2sh[..], &[0x51]), 0);
+   // non push opcode in the scriptSig
+   assert_eq!(Parser::get_p2sh_sig_op_count(&p2sh[..], &[0x61, 0x01, 0xac]), 0);
+
+   let flags = ScriptVerify::default().p2sh(true).witness(true);
+   let p2wpkh = h2b("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+   let p2wsh = h2b("00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262").unwrap();
+   let witness = vec![vec![], redeem.to_vec()];
+   assert_eq!(Parser::get_witness_sig_op_count(&[], &p2wpkh[..], &[], &flags), 1);
+   assert_eq!(Parser::get_witness_sig_op_count(&[], &p2wsh[..], &witness[..], &flags), 2);
+   assert_eq!(Parser::get_witness_sig_op_count(&[], &p2wsh[..], &[], &flags), 0);
+   assert_eq!(Parser::get_witness_sig_op_count(&[], &p2wpkh[..], &[], &ScriptVerify::default()), 0);
+   // p2sh-p2wsh
+   let mut script_sig = vec![p2wsh.len() as u8];
+   script_sig.extend_from_slice(&p2wsh[..]);
+   assert_eq!(Parser::get_witness_sig_op_count(&script_sig[..], &p2sh[..], &witness[..], &flags), 2);
+   assert_eq!(Parser::get_witness_sig_op_count(&script_sig[..], &p2wpkh[..], &witness[..], &flags), 1);
+}
@@ -0,0 +1,184 @@
+use super::opcode::*;
+use super::parser::Parser;
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum TxOutType {
+   NonStandard,
+   PubKey,
+   PubKeyHash,
+   ScriptHash,
+   Multisig,
+   NullData,
+   WitnessV0KeyHash,
+   WitnessV0ScriptHash,
+   WitnessV1Taproot,
+   WitnessUnknown,
+}
+
+impl TxOutType {
+   /// same as GetTxnOutputType of bitcoin core
+   pub fn name(&self) -> &'static str {
+      match *self {
+         TxOutType::NonStandard         => "nonstandard",
+         TxOutType::PubKey              => "pubkey",
+         TxOutType::PubKeyHash          => "pubkeyhash",
+         TxOutType::ScriptHash          => "scripthash",
+         TxOutType::Multisig            => "multisig",
+         TxOutType::NullData            => "nulldata",
+         TxOutType::WitnessV0KeyHash    => "witness_v0_keyhash",
+         TxOutType::WitnessV0ScriptHash => "witness_v0_scripthash",
+         TxOutType::WitnessV1Taproot    => "witness_v1_taproot",
+         TxOutType::WitnessUnknown      => "witness_unknown",
+      }
+   }
+}
+
+impl std::fmt::Display for TxOutType {
+   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+      write!(f, "{}", self.name())
+   }
+}
+
+/// CPubKey::ValidSize
+pub fn is_valid_pubkey_size(data:&[u8]) -> bool {
+   match data.len() {
+      33 => data[0] == 0x02 || data[0] == 0x03,
+      65 => data[0] == 0x04 || data[0] == 0x06 || data[0] == 0x07,
+      _ => false,
+   }
+}
+
+pub struct Solver;
+
+impl Solver {
+   /// Classifies the scriptPubKey and returns its solutions as bitcoin core does.
+   ///  - PubKey: [pubkey]
+   ///  - PubKeyHash, ScriptHash, WitnessV0KeyHash, WitnessV0ScriptHash, WitnessV1Taproot: [hash or key]
+   ///  - Multisig: [[m], pubkeys..., [n]]
+   ///  - WitnessUnknown: [[version], program]
+   ///  - NullData, NonStandard: []
+   pub fn solve(script:&[u8]) -> (TxOutType, Vec<Vec<u8>>) {
+      if Parser::is_pay_to_script_hash(script) {
+         return (TxOutType::ScriptHash, vec![script[2..22].to_vec()]);
+      }
+      if let Some((version, program)) = Parser::parse_witness_program(script) {
+         return match (version, program.len()) {
+            (0, 20) => (TxOutType::WitnessV0KeyHash, vec![program.to_vec()]),
+            (0, 32) => (TxOutType::WitnessV0ScriptHash, vec![program.to_vec()]),
+            (0, _)  => (TxOutType::NonStandard, vec![]),
+            (1, 32) => (TxOutType::WitnessV1Taproot, vec![program.to_vec()]),
+            (v, _)  => (TxOutType::WitnessUnknown, vec![vec![v], program.to_vec()]),
+         };
+      }
+      if 1 <= script.len() && script[0] == OP_RETURN && Parser::is_push_only(&script[1..]) {
+         return (TxOutType::NullData, vec![]);
+      }
+      if let Some(pk) = Self::match_pay_to_pubkey(script) {
+         return (TxOutType::PubKey, vec![pk.to_vec()]);
+      }
+      if crate::bitcoin::p2pkh::Parser::check(script).is_ok() {
+         return (TxOutType::PubKeyHash, vec![script[3..23].to_vec()]);
+      }
+      if let Some((m, pubkeys)) = Self::match_multisig(script) {
+         let n = pubkeys.len() as u8;
+         let mut v = Vec::with_capacity(pubkeys.len() + 2);
+         v.push(vec![m]);
+         v.extend(pubkeys.into_iter().map(|pk| pk.to_vec()));
+         v.push(vec![n]);
+         return (TxOutType::Multisig, v);
+      }
+      (TxOutType::NonStandard, vec![])
+   }
+
+   pub fn get_type(script:&[u8]) -> TxOutType {
+      Self::solve(script).0
+   }
+
+   fn match_pay_to_pubkey(script:&[u8]) -> Option<&[u8]> {
+      let len = script.len();
+      if (len == 35 || len == 67) && script[0] as usize == len - 2 && script[len-1] == OP_CHECKSIG {
+         let pk = &script[1..len-1];
+         if is_valid_pubkey_size(pk) {
+            return Some(pk);
+         }
+      }
+      None
+   }
+
+   /// returns m and the pubkeys
+   fn match_multisig(script:&[u8]) -> Option<(u8, Vec<&[u8]>)> {
+      if script.is_empty() || script[script.len()-1] != OP_CHECKMULTISIG {
+         return None;
+      }
+      let small_int = |op:u8| if OP_1 <= op && op <= OP_16 { Some(op - OP_1 + 1) } else { None };
+      let mut iter = Parser::iter(script);
+      let m = match iter.next() {
+         Some(Ok(p)) => small_int(p.opcode)?,
+         _ => return None,
+      };
+      let mut pubkeys = Vec::new();
+      let n = loop {
+         match iter.next() {
+            Some(Ok(p)) => match p.instruction.data() {
+               Some(d) if is_valid_pubkey_size(d) => pubkeys.push(&script[p.offset + p.size - d.len() .. p.offset + p.size]),
+               _ => break small_int(p.opcode)?,
+            },
+            _ => return None,
+         }
+      };
+      if pubkeys.len() != n as usize || n < m {
+         return None;
+      }
+      // followed by only OP_CHECKMULTISIG
+      match (iter.next(), iter.next()) {
+         (Some(Ok(ref p)), None) if p.opcode == OP_CHECKMULTISIG => Some((m, pubkeys)),
+         _ => None,
+      }
+   }
+}
+
+#[cfg(test)]
+mod tests {
+   use super::{Solver, TxOutType};
+   use crate::utils::h2b;
+
+   const PK:&str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+   #[test]
+   fn test_solve() {
+      let pk = h2b(PK).unwrap();
+      let cases:&[(String, TxOutType, usize)] = &[
+         (format!("21{}ac", PK), TxOutType::PubKey, 1),
+         ("76a9141018853670f9f3b0582c5b9ee8ce93764ac32b9388ac".to_string(), TxOutType::PubKeyHash, 1),
+         ("a9141018853670f9f3b0582c5b9ee8ce93764ac32b9387".to_string(), TxOutType::ScriptHash, 1),
+         (format!("5121{}21{}52ae", PK, PK), TxOutType::Multisig, 4),
+         ("6a0568656c6c6f".to_string(), TxOutType::NullData, 0),
+         ("6a60".to_string(), TxOutType::NullData, 0),
+         ("6a".to_string(), TxOutType::NullData, 0),
+         ("0014751e76e8199196d454941c45d1b3a323f1433bd6".to_string(), TxOutType::WitnessV0KeyHash, 1),
+         ("00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262".to_string(), TxOutType::WitnessV0ScriptHash, 1),
+         ("512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798".to_string(), TxOutType::WitnessV1Taproot, 1),
+         ("5210751e76e8199196d454941c45d1b3a323".to_string(), TxOutType::WitnessUnknown, 2),
+         ("0010751e76e8199196d454941c45d1b3a323".to_string(), TxOutType::NonStandard, 0),
+         ("6a76".to_string(), TxOutType::NonStandard, 0),
+         (format!("5121{}52ae", PK), TxOutType::NonStandard, 0),
+         (format!("5221{}51ae", PK), TxOutType::NonStandard, 0),
+         (format!("5121{}51ae51", PK), TxOutType::NonStandard, 0),
+         ("".to_string(), TxOutType::NonStandard, 0),
+      ];
+      for &(ref script, t, len) in cases.iter() {
+         let script = h2b(script.as_str()).unwrap();
+         let (t1, solutions) = Solver::solve(&script[..]);
+         assert_eq!(t1, t, "{}", crate::utils::b2h(&script[..]));
+         assert_eq!(solutions.len(), len);
+      }
+
+      let (_, solutions) = Solver::solve(&h2b(format!("5121{}21{}52ae", PK, PK)).unwrap()[..]);
+      assert_eq!(solutions[0], vec![1]);
+      assert_eq!(&solutions[1][..], &pk[..]);
+      assert_eq!(solutions[3], vec![2]);
+      let (_, solutions) = Solver::solve(&h2b("5210751e76e8199196d454941c45d1b3a323").unwrap()[..]);
+      assert_eq!(solutions[0], vec![2]);
+      assert_eq!(TxOutType::WitnessV0KeyHash.name(), "witness_v0_keyhash");
+   }
+}
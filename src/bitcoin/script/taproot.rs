@@ -0,0 +1,103 @@
+use super::apriori::{
+   TAPROOT_LEAF_MASK, TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_NODE_SIZE, TAPROOT_CONTROL_MAX_SIZE,
+};
+use crate::crypto::digest::tagged_hash;
+use crate::bitcoin::serialize::Serializer as BitcoinSerializer;
+
+fn with_size_prefix(head:&[u8], data:&[u8]) -> crate::Result<Vec<u8>> {
+   let med = crate::bitcoin::serialize::Medium::default().set_net();
+   let e = BitcoinSerializer::new(&med);
+   let mut buf = Vec::<u8>::with_capacity(head.len() + data.len() + 9);
+   buf.extend_from_slice(head);
+   let _ = e.serialize_var_octets(&mut buf, data, std::usize::MAX)?;
+   Ok(buf)
+}
+
+pub fn tapleaf_hash(leaf_version:u8, script:&[u8]) -> crate::Result<[u8;32]> {
+   let buf = with_size_prefix(&[leaf_version], script)?;
+   Ok(tagged_hash("TapLeaf", buf.as_slice()))
+}
+
+pub fn annex_hash(annex:&[u8]) -> crate::Result<[u8;32]> {
+   let buf = with_size_prefix(&[], annex)?;
+   let mut out = [0u8;32];
+   out.copy_from_slice(&crate::ui::create_sha256().u8_to_u8(buf.as_slice())[..]);
+   Ok(out)
+}
+
+pub fn tapbranch_hash(a:&[u8], b:&[u8]) -> [u8;32] {
+   let mut buf = [0u8;64];
+   if a < b {
+      buf[0..32].copy_from_slice(a);
+      buf[32..64].copy_from_slice(b);
+   } else {
+      buf[0..32].copy_from_slice(b);
+      buf[32..64].copy_from_slice(a);
+   }
+   tagged_hash("TapBranch", &buf[..])
+}
+
+pub fn taptweak_hash(internal_xonly:&[u8], merkle_root:Option<&[u8;32]>) -> [u8;32] {
+   let mut buf = Vec::<u8>::with_capacity(64);
+   buf.extend_from_slice(internal_xonly);
+   if let Some(root) = merkle_root {
+      buf.extend_from_slice(&root[..]);
+   }
+   tagged_hash("TapTweak", buf.as_slice())
+}
+
+pub fn is_valid_control_size(control:&[u8]) -> bool {
+   TAPROOT_CONTROL_BASE_SIZE <= control.len()
+      && control.len() <= TAPROOT_CONTROL_MAX_SIZE
+      && (control.len() - TAPROOT_CONTROL_BASE_SIZE) % TAPROOT_CONTROL_NODE_SIZE == 0
+}
+
+pub fn get_leaf_version(control:&[u8]) -> u8 {
+   control[0] & TAPROOT_LEAF_MASK
+}
+
+pub fn compute_merkle_root(control:&[u8], tapleaf_hash:&[u8;32]) -> [u8;32] {
+   let mut k = *tapleaf_hash;
+   for node in control[TAPROOT_CONTROL_BASE_SIZE..].chunks(TAPROOT_CONTROL_NODE_SIZE) {
+      k = tapbranch_hash(&k[..], node);
+   }
+   k
+}
+
+/// Checks that the witness program (output key) commits to the script of the leaf through the control block.
+pub fn verify_commitment(control:&[u8], program:&[u8], tapleaf_hash:&[u8;32]) -> crate::Result<bool> {
+   use crate::ui::secp256k1::XOnlyPublicKeyUi;
+   if !is_valid_control_size(control) {
+      raise_script_error!(format!("invalid control block size: {}", control.len()));
+   }
+   let internal = XOnlyPublicKeyUi::s_decode(&control[1..TAPROOT_CONTROL_BASE_SIZE])?;
+   let root  = compute_merkle_root(control, tapleaf_hash);
+   let tweak = taptweak_hash(&internal.xonly[..], Some(&root));
+   internal.check_tweak(&tweak, program, (control[0] & 1) == 1)
+}
+
+#[test]
+fn test_tapbranch_hash() {
+   let a = [1u8;32];
+   let b = [2u8;32];
+   assert_eq!(tapbranch_hash(&a, &b), tapbranch_hash(&b, &a));
+}
+
+#[test]
+fn test_verify_commitment() {
+   use crate::utils::{h2b, b2h};
+   // a tree of single leaf "OP_TRUE" under the internal key G
+   let internal = h2b("79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798").unwrap();
+   let leaf = tapleaf_hash(0xc0, &[0x51]).unwrap();
+   assert_eq!(b2h(&leaf[..]), "a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675");
+   let program = h2b("9b6ce0db0707e29f92bf8893ed1911d397e3d2d76bbc68110c49da2ceec8be23").unwrap();
+
+   let mut control = vec![0xc0u8];
+   control.extend_from_slice(&internal[..]);
+   assert_matches!(verify_commitment(control.as_slice(), &program[..], &leaf), Ok(true));
+
+   control[0] ^= 1;
+   assert_matches!(verify_commitment(control.as_slice(), &program[..], &leaf), Ok(false));
+   control.push(0);
+   assert_matches!(verify_commitment(control.as_slice(), &program[..], &leaf), Err(_));
+}
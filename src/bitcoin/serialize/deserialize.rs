@@ -1,5 +1,10 @@
 use super::Medium;
 
+/// upper bound of the sizes read by deserialize_var_int (serialize.h: MAX_SIZE)
+pub const MAX_SIZE:u64 = 0x02000000;
+/// the buffers are allocated by this bytes as they are read (serialize.h: MAX_VECTOR_ALLOCATE)
+const MAX_VECTOR_ALLOCATE:usize = 5000000;
+
 pub struct Deserializer {
    medium: Medium,
 }
@@ -151,7 +156,16 @@ impl Deserializer {
       *v = x == 1;
       Ok(r)
    }
+   /// reads the size, which must be MAX_SIZE or less (serialize.h: ReadCompactSize)
    pub fn deserialize_var_int<R: std::io::Read>(&self, rs: &mut R, v:&mut u64) -> crate::Result<usize> {
+      let r = self.deserialize_var_int_unchecked(rs, v)?;
+      if MAX_SIZE < *v {
+         raise_deserialize_error!(format!("size is too large: {}", *v));
+      }
+      Ok(r)
+   }
+   /// reads the value of any range, such as the services of addrv2
+   pub fn deserialize_var_int_unchecked<R: std::io::Read>(&self, rs: &mut R, v:&mut u64) -> crate::Result<usize> {
       let mut x:u8 = 0;
       let mut r = self.deserialize_u8(rs, &mut x)?;
       if x < 253 {
@@ -172,7 +186,7 @@ impl Deserializer {
    pub fn deserialize_octets<R: std::io::Read>(&self, rs: &mut R, v:&mut [u8]) -> crate::Result<usize> {
       let r = rs.read(v)?;
       if r != v.len() {
-         deserialize_error!(format!("length mismatch: {} but {}", v.len(), r));
+         raise_deserialize_error!(format!("length mismatch: {} but {}", v.len(), r));
       }
       Ok(r)
    }
@@ -184,10 +198,21 @@ impl Deserializer {
          r += self.deserialize_var_int(rs, &mut size)?;
          size as usize
       };
-      if lim < size { deserialize_error!("sequence is too long"); }
+      if lim < size { raise_deserialize_error!("sequence is too long") }
 
-      v.resize(size, 0);
-      r += rs.read(v.as_mut_slice())?;
+      r += self.deserialize_octets_chunked(rs, v, size)?;
+      Ok(r)
+   }
+   /// reads the size bytes growing the buffer only as the bytes are read
+   fn deserialize_octets_chunked<R: std::io::Read>(&self, rs: &mut R, v:&mut Vec<u8>, size:usize) -> crate::Result<usize> {
+      let mut r:usize = 0;
+      v.clear();
+      while v.len() < size {
+         let from = v.len();
+         let to = std::cmp::min(size, from + MAX_VECTOR_ALLOCATE);
+         v.resize(to, 0);
+         r += self.deserialize_octets(rs, &mut v[from..to])?;
+      }
       Ok(r)
    }
    pub fn deserialize_to_end<R: std::io::Read>(&self, rs: &mut R, v:&mut Vec<u8>) -> crate::Result<usize> {
@@ -204,8 +229,8 @@ impl Deserializer {
       };
       if lim < size { raise_deserialize_error!("string is too long") }
 
-      let mut tmp = vec![0u8; size];
-      r += self.deserialize_octets(rs, tmp.as_mut_slice())?;
+      let mut tmp = Vec::new();
+      r += self.deserialize_octets_chunked(rs, &mut tmp, size)?;
       *v = String::from_utf8(tmp)?;
 
       Ok(r)
@@ -220,9 +245,9 @@ impl Deserializer {
          r += self.deserialize_var_int(rs, &mut size)?;
          size as usize
       };
-      if lim < size { deserialize_error!("sequence is too long"); }
+      if lim < size { raise_deserialize_error!("sequence is too long") }
 
-      let mut v:Vec<T> = Vec::with_capacity(size);
+      let mut v:Vec<T> = Vec::with_capacity(std::cmp::min(size, MAX_VECTOR_ALLOCATE / std::cmp::max(1, std::mem::size_of::<T>())));
       for _i in 0..size {
          let mut item = T::default();
          r += item.deserialize(param, self, rs)?;
@@ -274,7 +299,7 @@ fn test_deserialize_var_int() {
       assert_eq!(v, 0x10000u64);
       assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Ok(5));
       assert_eq!(v, 0x01020304u64);
-      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Ok(5));
+      assert_matches!(d.deserialize_var_int_unchecked(&mut rs, &mut v), Ok(5));
       assert_eq!(v, 0xFFFFFFFFu64);
    }
    {
@@ -286,13 +311,38 @@ fn test_deserialize_var_int() {
       let mut rs = &buf[..];
       let d = Deserializer::new(&Medium::default().set_net());
       let mut v = 0u64;
-      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Ok(9));
+      assert_matches!(d.deserialize_var_int_unchecked(&mut rs, &mut v), Ok(9));
       assert_eq!(v, 0x100000000u64);
-      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Ok(9));
+      assert_matches!(d.deserialize_var_int_unchecked(&mut rs, &mut v), Ok(9));
       assert_eq!(v, 0x0102030405060708u64);
-      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Ok(9));
+      assert_matches!(d.deserialize_var_int_unchecked(&mut rs, &mut v), Ok(9));
       assert_eq!(v, 0xFFFFFFFFFFFFFFFFu64);
    }
+   {
+      // sizes are up to MAX_SIZE
+      let buf = [
+         254, 0x00, 0x00, 0x00, 0x02,
+         254, 0x01, 0x00, 0x00, 0x02,
+         255, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF
+      ];
+      let mut rs = &buf[..];
+      let d = Deserializer::new(&Medium::default().set_net());
+      let mut v = 0u64;
+      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Ok(5));
+      assert_eq!(v, MAX_SIZE);
+      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Err(_));
+      assert_matches!(d.deserialize_var_int(&mut rs, &mut v), Err(_));
+   }
+}
+
+#[test]
+fn test_deserialize_var_octets_short() {
+   // the size is in the range but the data is short
+   let buf = [254, 0x00, 0x00, 0x00, 0x02, 0x01, 0x02];
+   let d = Deserializer::new(&Medium::default().set_net());
+   let mut v = Vec::new();
+   assert_matches!(d.deserialize_var_octets(&mut &buf[..], &mut v, std::usize::MAX), Err(_));
+   assert!(v.len() <= MAX_VECTOR_ALLOCATE);
 }
 
 #[cfg(test)]
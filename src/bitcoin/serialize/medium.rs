@@ -10,6 +10,9 @@ const MEDIUM_NET:u32  = 0u32 << 0;
 const MEDIUM_DISK:u32 = 1u32 << 0;
 const MEDIUM_HASH:u32 = 2u32 << 0;
 
+// WITNESS: 1bit (set -> serialize tx without witness)
+const FLAG_NO_WITNESS:u32 = 1u32 << 2;
+
 impl Default for Medium {
    fn default() -> Self {
       Medium {
@@ -23,6 +26,7 @@ impl Medium {
    pub fn is_net(&self)  -> bool { (self.medium & MASK_IO) == MEDIUM_NET }
    pub fn is_disk(&self) -> bool { (self.medium & MASK_IO) == MEDIUM_DISK }
    pub fn is_hash(&self) -> bool { (self.medium & MASK_IO) == MEDIUM_HASH }
+   pub fn is_witness(&self) -> bool { (self.medium & FLAG_NO_WITNESS) == 0 }
 
    pub fn set_version(mut self, v:i32) -> Self { self.version = v; self }
    //pub fn set_version_latest(mut self) -> Self { self.version = ::protocol::PROTOCOL_VERSION; self }
@@ -30,6 +34,8 @@ impl Medium {
    pub fn set_net(mut self)  -> Self { self.medium = self.medium & !MASK_IO | MEDIUM_NET; self }
    pub fn set_disk(mut self) -> Self { self.medium = self.medium & !MASK_IO | MEDIUM_DISK; self }
    pub fn set_hash(mut self) -> Self { self.medium = self.medium & !MASK_IO | MEDIUM_HASH; self }
+   pub fn set_witness(mut self)    -> Self { self.medium = self.medium & !FLAG_NO_WITNESS; self }
+   pub fn set_no_witness(mut self) -> Self { self.medium = self.medium | FLAG_NO_WITNESS; self }
 
    pub fn new(line:&str) -> Result<Self, crate::ParseError> {
       let m0 = Medium { version:Medium::default().version, medium:0 };
@@ -39,6 +45,8 @@ impl Medium {
             (Ok(m), "disk") => Ok(m.set_disk()),
             (Ok(m), "net")  => Ok(m.set_net()),
             (Ok(m), "hash") => Ok(m.set_hash()),
+            (Ok(m), "witness")   => Ok(m.set_witness()),
+            (Ok(m), "nowitness") => Ok(m.set_no_witness()),
             (Ok(m), "")     => Ok(m),
             (Ok(_), _)      => Err(parse_error!(format!("unknown medium {:?}", s))),
          }
@@ -76,3 +84,18 @@ fn test_new() {
    assert_eq!(m.is_net(),  true);
    assert_eq!(m.is_disk(), false);
 }
+
+#[test]
+fn test_witness() {
+   use super::Medium;
+   let m = Medium::new("net").unwrap();
+   assert_eq!(m.is_witness(), true);
+
+   let m = Medium::new("net,nowitness").unwrap();
+   assert_eq!(m.is_net(),     true);
+   assert_eq!(m.is_witness(), false);
+
+   let m = m.set_disk().set_witness();
+   assert_eq!(m.is_disk(),    true);
+   assert_eq!(m.is_witness(), true);
+}
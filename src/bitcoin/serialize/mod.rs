@@ -9,7 +9,7 @@ pub mod serialize;
 pub use self::serialize::{Serializer, Serializee};
 
 pub mod deserialize;
-pub use self::deserialize::{Deserializer, Deserializee};
+pub use self::deserialize::{Deserializer, Deserializee, MAX_SIZE};
 
 //pub mod fromto;
 
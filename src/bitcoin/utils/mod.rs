@@ -6,4 +6,4 @@ pub use self::base58::{
 };
 
 pub mod payto;
-pub use self::payto::PayTo;
+pub use self::payto::{PayTo, PayToError, WitnessProgram};
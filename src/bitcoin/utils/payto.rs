@@ -1,29 +1,261 @@
-use crate::bitcoin::chainparams::Base58check as Base58checkParams;
+use crate::bitcoin::ChainParams;
+use crate::bitcoin::{P2PKH, P2SH, P2WPKH, P2WSH, P2TR, Multisig};
 
+def_error! { PayToError }
+macro_rules! raise_payto_error {
+   ($m:expr) => {
+      Err(crate::bitcoin::utils::PayToError::new($m, 0))?
+   }
+}
+
+#[derive(Debug,Clone)]
 pub enum PayTo {
-   P2PKH(crate::bitcoin::p2pkh::P2PKH),
+   P2PKH(P2PKH),
+   P2SH(P2SH),
+   P2WPKH(P2WPKH),
+   P2WSH(P2WSH),
+   P2TR(P2TR),
+   Multisig(Multisig),
+   /// witness program of the versions reserved for the future upgrades
+   WitnessUnknown(WitnessProgram),
 }
 
-fn _decode_address(addr:&str, table: &str, version: &[u8]) -> crate::Result<Box<[u8]>> {
-   let check = crate::utils::Base58check::new(table, version);
-   check.decode(addr)
+/// A witness version of 0 to 16 and a program of 2 to 40 bytes.
+#[derive(Debug,Clone)]
+pub struct WitnessProgram {
+   version: u8,
+   program: Box<[u8]>,
+}
+
+impl WitnessProgram {
+   pub fn new(version:u8, program:Box<[u8]>) -> crate::Result<Self> {
+      if 16 < version {
+         raise_payto_error!(format!("invalid witness version: {}", version));
+      }
+      if program.len() < 2 || 40 < program.len() {
+         raise_payto_error!(format!("invalid witness program length: {}", program.len()));
+      }
+      Ok(Self { version:version, program:program })
+   }
+   pub fn version(&self) -> u8 { self.version }
+   pub fn program(&self) -> &[u8] { &self.program }
 }
 
 impl PayTo {
-   pub fn parse_address<'a>(addr:&str, params: &'a Base58checkParams) -> Option<PayTo> {
-      if let Ok(pkh) = _decode_address(addr, params.table, params.versions.p2pkh) {
-         if let Ok(p2) = crate::bitcoin::p2pkh::P2PKH::new_with_pkh(pkh.as_ref()) {
-            return Some(PayTo::P2PKH(p2));
+   /// Accepts the base58check addresses of p2pkh and p2sh, and the segwit addresses of p2wpkh, p2wsh and p2tr.
+   pub fn parse_address(addr:&str, params:&ChainParams) -> crate::Result<PayTo> {
+      let is_bech32 = match addr.rfind('1') {
+         Some(pos) => addr[..pos].eq_ignore_ascii_case(params.bech32_hrp),
+         None => false,
+      };
+      if is_bech32 {
+         Self::parse_segwit_address(addr, params.bech32_hrp)
+      } else {
+         Self::parse_base58check_address(addr, params)
+      }
+   }
+
+   fn parse_base58check_address(addr:&str, params:&ChainParams) -> crate::Result<PayTo> {
+      let t = &params.base58check;
+      // decodes without the version to tell which one is used
+      let bytes = match crate::utils::Base58check::new(t.table, &[]).decode(addr) {
+         Ok(b) => b,
+         Err(e) => raise_payto_error!(format!("invalid base58check address: {}", e)),
+      };
+      let versions:[(&[u8], bool); 2] = [(t.versions.p2pkh, true), (t.versions.p2sh, false)];
+      for &(version, is_p2pkh) in versions.iter() {
+         if bytes.len() != version.len() + 20 || &bytes[..version.len()] != version {
+            continue;
          }
+         let hash = &bytes[version.len()..];
+         return Ok(if is_p2pkh {
+            PayTo::P2PKH(P2PKH::new_with_pkh(hash)?)
+         } else {
+            PayTo::P2SH(P2SH::new_with_script_hash(hash)?)
+         });
+      }
+      raise_payto_error!(format!("unknown version or length: {}", addr))
+   }
+
+   fn parse_segwit_address(addr:&str, hrp:&str) -> crate::Result<PayTo> {
+      let (version, program) = match crate::utils::segwit_address_decode(hrp, addr) {
+         Ok(r) => r,
+         Err(e) => raise_payto_error!(format!("invalid segwit address: {}", e)),
+      };
+      match (version, program.len()) {
+         (0, 20) => Ok(PayTo::P2WPKH(P2WPKH::new_with_pkh(&program[..])?)),
+         (0, 32) => Ok(PayTo::P2WSH(P2WSH::new_with_script_hash(&program[..])?)),
+         (0, l)  => raise_payto_error!(format!("invalid witness v0 program length: {}", l)),
+         (1, 32) => Ok(PayTo::P2TR(P2TR::new_with_output_key(&program[..])?)),
+         (v, _)  => Ok(PayTo::WitnessUnknown(WitnessProgram::new(v, program.into_boxed_slice())?)),
+      }
+   }
+
+   /// None if the script is not any of the standard types, or is a pay-to-pubkey or a null data.
+   pub fn from_script_pubkey(script:&[u8]) -> Option<PayTo> {
+      use crate::bitcoin::script::{Solver, TxOutType};
+      let (t, sols) = Solver::solve(script);
+      match t {
+         TxOutType::PubKeyHash          => P2PKH::new_with_pkh(&sols[0][..]).ok().map(PayTo::P2PKH),
+         TxOutType::ScriptHash          => P2SH::new_with_script_hash(&sols[0][..]).ok().map(PayTo::P2SH),
+         TxOutType::WitnessV0KeyHash    => P2WPKH::new_with_pkh(&sols[0][..]).ok().map(PayTo::P2WPKH),
+         TxOutType::WitnessV0ScriptHash => P2WSH::new_with_script_hash(&sols[0][..]).ok().map(PayTo::P2WSH),
+         TxOutType::WitnessV1Taproot    => P2TR::new_with_output_key(&sols[0][..]).ok().map(PayTo::P2TR),
+         TxOutType::Multisig => {
+            let pubkeys = sols[1..sols.len()-1].iter().map(|pk| pk.clone().into_boxed_slice()).collect();
+            Multisig::new(sols[0][0] as usize, pubkeys).ok().map(PayTo::Multisig)
+         },
+         TxOutType::WitnessUnknown => WitnessProgram::new(sols[0][0], sols[1].clone().into_boxed_slice()).ok().map(PayTo::WitnessUnknown),
+         TxOutType::PubKey | TxOutType::NullData | TxOutType::NonStandard => None,
       }
-      None
    }
+
+   /// Bare multisig has no address.
+   pub fn to_address(&self, params:&ChainParams) -> Option<String> {
+      let t = &params.base58check;
+      let hrp = params.bech32_hrp;
+      match self {
+         PayTo::P2PKH(p2) => Some(crate::utils::Base58check::new(t.table, t.versions.p2pkh).encode(p2.pkh())),
+         PayTo::P2SH(p2)  => Some(crate::utils::Base58check::new(t.table, t.versions.p2sh).encode(p2.script_hash())),
+         PayTo::P2WPKH(p2) => crate::utils::segwit_address_encode(hrp, 0, p2.pkh()).ok(),
+         PayTo::P2WSH(p2)  => crate::utils::segwit_address_encode(hrp, 0, p2.script_hash()).ok(),
+         PayTo::P2TR(p2)   => crate::utils::segwit_address_encode(hrp, 1, p2.output_key()).ok(),
+         PayTo::Multisig(_) => None,
+         PayTo::WitnessUnknown(w) => crate::utils::segwit_address_encode(hrp, w.version(), w.program()).ok(),
+      }
+   }
+
    pub fn compile(&self) -> Box<[u8]> {
       match self {
          PayTo::P2PKH(p2) => {
             crate::bitcoin::p2pkh::Compiler::compile(&p2)
          },
+         PayTo::P2SH(p2) => {
+            crate::bitcoin::p2sh::Compiler::compile(&p2)
+         },
+         PayTo::P2WPKH(p2) => {
+            crate::bitcoin::p2wpkh::Compiler::compile(&p2)
+         },
+         PayTo::P2WSH(p2) => {
+            crate::bitcoin::p2wsh::Compiler::compile(&p2)
+         },
+         PayTo::P2TR(p2) => {
+            crate::bitcoin::p2tr::Compiler::compile(&p2)
+         },
+         PayTo::Multisig(m) => {
+            crate::bitcoin::multisig::Compiler::compile(&m)
+         },
+         PayTo::WitnessUnknown(w) => {
+            use crate::bitcoin::script::opcode::{OP_0, OP_1};
+            let mut pk_script = Vec::with_capacity(2 + w.program().len());
+            pk_script.push(if w.version() == 0 { OP_0 } else { OP_1 + w.version() - 1 });
+            pk_script.push(w.program().len() as u8);
+            pk_script.extend_from_slice(w.program());
+            pk_script.into_boxed_slice()
+         },
       }
    }
 }
 
+#[cfg(test)]
+mod tests {
+   use super::{PayTo, WitnessProgram};
+   use crate::utils::h2b;
+
+   #[test]
+   fn test_address_roundtrip() {
+      let mainnet = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN;
+      let cases:&[(&str, &str)] = &[
+         ("1AGNa15ZQXAZUgFiqJ2i7Z2DPU2J6hW62i", "76a91465a16059864a2fdbc7c99a4723a8395bc6f188eb88ac"),
+         ("3CMNFxN1oHBc4R1EpboAL5yzHGgE611Xou", "a91474f209f6ea907e2ea48f74fae05782ae8a66525787"),
+         ("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", "0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+         ("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3", "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"),
+         ("bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+         ("bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", "5210751e76e8199196d454941c45d1b3a323"),
+      ];
+      for &(addr, spk) in cases.iter() {
+         let spk = h2b(spk).unwrap();
+         let payto = PayTo::parse_address(addr, mainnet).unwrap();
+         assert_eq!(payto.compile(), spk);
+         let payto = PayTo::from_script_pubkey(&spk[..]).unwrap();
+         assert_eq!(payto.to_address(mainnet).unwrap(), addr);
+      }
+   }
+
+   #[test]
+   fn test_witness_unknown_compile() {
+      let program = h2b("751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+      let spk = PayTo::WitnessUnknown(WitnessProgram::new(0, program.clone()).unwrap()).compile();
+      assert_eq!(&spk[..], &h2b("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap()[..]);
+      assert_matches!(PayTo::from_script_pubkey(&spk[..]), Some(PayTo::P2WPKH(_)));
+
+      let spk = PayTo::WitnessUnknown(WitnessProgram::new(16, program.clone()).unwrap()).compile();
+      assert_eq!(spk[0], 0x60);
+      match PayTo::from_script_pubkey(&spk[..]) {
+         Some(PayTo::WitnessUnknown(ref w)) => {
+            assert_eq!(w.version(), 16);
+            assert_eq!(w.program(), &program[..]);
+         },
+         r => panic!("unexpected: {:?}", r),
+      }
+   }
+
+   #[test]
+   fn test_witness_program_range() {
+      let program = |len:usize| vec![0u8; len].into_boxed_slice();
+      assert_matches!(WitnessProgram::new(17, program(20)), Err(crate::Error::BitcoinPayTo(_)));
+      assert_matches!(WitnessProgram::new(176, program(20)), Err(crate::Error::BitcoinPayTo(_)));
+      assert_matches!(WitnessProgram::new(1, program(1)), Err(crate::Error::BitcoinPayTo(_)));
+      assert_matches!(WitnessProgram::new(1, program(41)), Err(crate::Error::BitcoinPayTo(_)));
+      assert_matches!(WitnessProgram::new(16, program(2)), Ok(_));
+      assert_matches!(WitnessProgram::new(16, program(40)), Ok(_));
+   }
+
+   #[test]
+   fn test_agree_with_solver() {
+      use crate::bitcoin::script::{Solver, TxOutType};
+      let cases:&[&str] = &[
+         "76a91465a16059864a2fdbc7c99a4723a8395bc6f188eb88ac",
+         "a91474f209f6ea907e2ea48f74fae05782ae8a66525787",
+         "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+         "0015751e76e8199196d454941c45d1b3a323f1433bd600",
+         "5210751e76e8199196d454941c45d1b3a323",
+         "51210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f8179851ae",
+         "51210579be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f8179851ae",
+         "210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798ac",
+         "6a0102",
+      ];
+      for spk in cases.iter() {
+         let spk = h2b(*spk).unwrap();
+         let t = Solver::get_type(&spk[..]);
+         let payto = PayTo::from_script_pubkey(&spk[..]);
+         match t {
+            TxOutType::PubKey | TxOutType::NullData | TxOutType::NonStandard => assert_matches!(payto, None),
+            _ => assert_eq!(payto.unwrap().compile(), spk),
+         }
+      }
+   }
+
+   #[test]
+   fn test_parse_address_error() {
+      let mainnet = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN;
+      let testnet = &crate::bitcoin::presets::bitcoin_testnet::CHAIN;
+      let is_payto_error = |r:crate::Result<PayTo>, msg:&str| match r {
+         Err(crate::Error::BitcoinPayTo(ref e)) => format!("{}", e).contains(msg),
+         _ => false,
+      };
+      assert!(is_payto_error(PayTo::parse_address("1AGNa15ZQXAZUgFiqJ2i7Z2DPU2J6hW62i", testnet), "unknown version"));
+      assert!(is_payto_error(PayTo::parse_address("1AGNa15ZQXAZUgFiqJ2i7Z2DPU2J6hW62j", mainnet), "invalid base58check"));
+      assert!(is_payto_error(PayTo::parse_address("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t5", mainnet), "invalid segwit"));
+      assert!(is_payto_error(PayTo::parse_address("bc1qr508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4", testnet), "invalid base58check"));
+
+      let bare = h2b("51210279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f8179851ae").unwrap();
+      let payto = PayTo::from_script_pubkey(&bare[..]);
+      assert_matches!(payto, Some(PayTo::Multisig(_)));
+      assert_matches!(payto.unwrap().to_address(mainnet), None);
+      assert_matches!(PayTo::from_script_pubkey(&[0x6a]), None);
+      let mut bad = bare.to_vec();
+      bad[2] = 0x05;
+      assert_matches!(PayTo::from_script_pubkey(&bad[..]), None);
+   }
+}
@@ -0,0 +1,9 @@
+// consensus/consensus.h
+pub const MAX_BLOCK_SERIALIZED_SIZE:usize = 4000000;
+pub const MAX_BLOCK_WEIGHT:usize = 4000000;
+pub const MAX_BLOCK_SIGOPS_COST:usize = 80000;
+pub const COINBASE_MATURITY:i32 = 100;
+pub use crate::bitcoin::datatypes::WITNESS_SCALE_FACTOR;
+
+pub const MIN_COINBASE_SCRIPT_SIZE:usize = 2;
+pub const MAX_COINBASE_SCRIPT_SIZE:usize = 100;
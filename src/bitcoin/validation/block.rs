@@ -0,0 +1,271 @@
+use crate::bitcoin::datatypes::{Block, BlockHeader, TxOut, UInt256};
+use crate::bitcoin::datatypes::merkle::{dhash_pair, block_merkle_root, block_witness_merkle_root};
+use crate::bitcoin::script::flags::ScriptVerify;
+use crate::bitcoin::chainparams::Consensus;
+use super::apriori::{MAX_BLOCK_WEIGHT, MAX_BLOCK_SIGOPS_COST, WITNESS_SCALE_FACTOR};
+use super::tx::{check_transaction, get_serialize_size, get_legacy_sig_op_count, get_transaction_sig_op_cost};
+use super::pow::check_proof_of_work;
+
+pub const WITNESS_COMMITMENT_HEADER:[u8;4] = [0xaa, 0x21, 0xa9, 0xed];
+pub const MINIMUM_WITNESS_COMMITMENT:usize = 38;
+
+/// (validation.cpp: GetWitnessCommitmentIndex)
+pub fn get_witness_commitment_index(block:&Block) -> Option<usize> {
+   block.txs.get(0).and_then(|coinbase| {
+      coinbase.outs.iter().rposition(|o| {
+         let s = &o.script_pubkey.bytecode;
+         MINIMUM_WITNESS_COMMITMENT <= s.len()
+            && s[0] == crate::bitcoin::script::opcode::OP_RETURN
+            && s[1] == 0x24
+            && &s[2..6] == &WITNESS_COMMITMENT_HEADER[..]
+      })
+   })
+}
+
+pub fn get_block_weight(block:&Block) -> crate::Result<usize> {
+   Ok(get_serialize_size(block, false)? * (WITNESS_SCALE_FACTOR - 1) + get_serialize_size(block, true)?)
+}
+
+pub fn check_block_header(header:&BlockHeader, consensus:&Consensus, check_pow:bool) -> crate::Result<()> {
+   if check_pow {
+      check_proof_of_work(&header.get_hash()?, header.bits, consensus)?;
+   }
+   Ok(())
+}
+
+/// Context-free checks of the block (validation.cpp: CheckBlock).
+/// The result is cached in block.checked when all checks are enabled.
+pub fn check_block(block:&mut Block, consensus:&Consensus, check_pow:bool, check_merkle_root:bool) -> crate::Result<()> {
+   if block.checked {
+      return Ok(());
+   }
+   check_block_header(&block.header, consensus, check_pow)?;
+
+   if check_merkle_root {
+      let (root, mutated) = block_merkle_root(block)?;
+      if block.header.hash_merkle_root != root {
+         raise_validation_error!(BlockBadMerkleRoot);
+      }
+      if mutated {
+         raise_validation_error!(BlockDuplicateTx);
+      }
+   }
+
+   if block.txs.is_empty()
+      || MAX_BLOCK_WEIGHT < block.txs.len() * WITNESS_SCALE_FACTOR
+      || MAX_BLOCK_WEIGHT < get_serialize_size(block, false)? * WITNESS_SCALE_FACTOR
+   {
+      raise_validation_error!(BlockLength);
+   }
+
+   if !block.txs[0].is_coin_base() {
+      raise_validation_error!(BlockCoinbaseMissing);
+   }
+   if block.txs[1..].iter().any(|tx| tx.is_coin_base()) {
+      raise_validation_error!(BlockCoinbaseMultiple);
+   }
+
+   for tx in block.txs.iter() {
+      check_transaction(tx)?;
+   }
+
+   let sig_ops:usize = block.txs.iter().map(get_legacy_sig_op_count).sum();
+   if MAX_BLOCK_SIGOPS_COST < sig_ops * WITNESS_SCALE_FACTOR {
+      raise_validation_error!(BlockSigOps);
+   }
+
+   if check_pow && check_merkle_root {
+      block.checked = true;
+   }
+   Ok(())
+}
+
+/// Checks of the witness commitment and the block weight (validation.cpp: ContextualCheckBlock).
+/// witness_enabled is whether segwit is active at the height of the block.
+pub fn check_block_witness(block:&Block, witness_enabled:bool) -> crate::Result<()> {
+   let mut have_witness_commitment = false;
+   if witness_enabled {
+      if let Some(idx) = get_witness_commitment_index(block) {
+         let coinbase_witness = &block.txs[0].ins[0].witness;
+         if coinbase_witness.stack.len() != 1 || coinbase_witness.stack[0].len() != 32 {
+            raise_validation_error!(BlockWitnessNonceSize);
+         }
+         let (root, _) = block_witness_merkle_root(block)?;
+         let commitment = dhash_pair(&root, &UInt256::new(&coinbase_witness.stack[0][..]));
+         if &commitment.data[..] != &block.txs[0].outs[idx].script_pubkey.bytecode[6..38] {
+            raise_validation_error!(BlockWitnessMerkleMatch);
+         }
+         have_witness_commitment = true;
+      }
+   }
+
+   if !have_witness_commitment && block.txs.iter().any(|tx| tx.has_witness()) {
+      raise_validation_error!(BlockUnexpectedWitness);
+   }
+
+   if MAX_BLOCK_WEIGHT < get_block_weight(block)? {
+      raise_validation_error!(BlockWeight);
+   }
+   Ok(())
+}
+
+/// Sum of the sigop costs of the txs. prevouts[i] are the outputs spent by block.txs[i], empty for the coinbase.
+pub fn get_block_sig_op_cost(block:&Block, prevouts:&[Vec<TxOut>], flags:&ScriptVerify) -> crate::Result<usize> {
+   let mut cost = 0usize;
+   for (i, tx) in block.txs.iter().enumerate() {
+      let spent = prevouts.get(i).map_or(&[][..], |v| &v[..]);
+      cost += get_transaction_sig_op_cost(tx, spent, flags)?;
+   }
+   Ok(cost)
+}
+
+/// Checks the sigop cost of the block with the spent outputs (validation.cpp: ConnectBlock).
+pub fn check_block_sig_op_cost(block:&Block, prevouts:&[Vec<TxOut>], flags:&ScriptVerify) -> crate::Result<()> {
+   if block.txs.len() != prevouts.len() {
+      raise_validation_error!(BlockSigOps, format!("{} prevouts for {} txs", prevouts.len(), block.txs.len()));
+   }
+   if MAX_BLOCK_SIGOPS_COST < get_block_sig_op_cost(block, prevouts, flags)? {
+      raise_validation_error!(BlockSigOps);
+   }
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::datatypes::{TxOut, TxOutPoint, Script};
+   use crate::bitcoin::datatypes::test_utils::new_tx;
+   use crate::bitcoin::validation::ValidationErrorCode as C;
+
+   const GENESIS:&str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+   fn is_error(r:crate::Result<()>, code:C) -> bool {
+      match r {
+         Err(crate::Error::BitcoinValidation(ref e)) => e.is(code),
+         _ => false,
+      }
+   }
+
+   fn genesis() -> Block {
+      let mut block = Block::default();
+      let b = crate::utils::h2b(GENESIS).unwrap();
+      let _ = crate::ui::bitcoin::deserialize(&b[..], &(), &mut block).unwrap();
+      block
+   }
+
+   #[test]
+   fn test_check_block() {
+      let consensus = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN.consensus;
+      let mut block = genesis();
+      assert_eq!(block.header.get_hash().unwrap(), consensus.hash_genesis_block);
+      assert_matches!(check_block(&mut block, consensus, true, true), Ok(()));
+      assert!(block.checked);
+      assert_matches!(check_block_witness(&block, true), Ok(()));
+
+      let mut b = genesis();
+      b.header.nonce += 1;
+      assert!(is_error(check_block(&mut b, consensus, true, true), C::BlockHighHash));
+      assert!(!b.checked);
+      assert_matches!(check_block(&mut b, consensus, false, true), Ok(()));
+      assert!(!b.checked);
+
+      let mut b = genesis();
+      b.txs.push(new_tx(1));
+      assert!(is_error(check_block(&mut b, consensus, false, true), C::BlockBadMerkleRoot));
+      b.header.hash_merkle_root = block_merkle_root(&b).unwrap().0;
+      assert_matches!(check_block(&mut b, consensus, false, true), Ok(()));
+
+      let mut b = genesis();
+      b.txs.clear();
+      assert!(is_error(check_block(&mut b, consensus, false, false), C::BlockLength));
+
+      let mut b = genesis();
+      b.txs.insert(0, new_tx(1));
+      assert!(is_error(check_block(&mut b, consensus, false, false), C::BlockCoinbaseMissing));
+
+      let mut b = genesis();
+      let cb = b.txs[0].clone();
+      b.txs.push(cb);
+      assert!(is_error(check_block(&mut b, consensus, false, false), C::BlockCoinbaseMultiple));
+
+      let mut b = genesis();
+      let mut tx = new_tx(1);
+      tx.outs[0].value = -1;
+      b.txs.push(tx);
+      assert!(is_error(check_block(&mut b, consensus, false, false), C::TxVoutNegative));
+
+      // 1000 OP_CHECKMULTISIG are counted as 20000 sigops, plus 1 of the coinbase
+      let mut b = genesis();
+      let mut tx = new_tx(1);
+      tx.outs[0].script_pubkey = Script::new(vec![0xae; 1000]);
+      b.txs.push(tx);
+      assert!(is_error(check_block(&mut b, consensus, false, false), C::BlockSigOps));
+      b.txs[1].outs[0].script_pubkey = Script::new(vec![0xae; 999]);
+      assert_matches!(check_block(&mut b, consensus, false, false), Ok(()));
+   }
+
+   #[test]
+   fn test_check_block_sig_op_cost() {
+      let flags = ScriptVerify::default().p2sh(true).witness(true);
+      let p2wsh = Script::new(crate::utils::h2b("00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262").unwrap());
+      let mut b = genesis();
+      let mut tx = new_tx(1);
+      tx.ins[0].witness.stack.push(vec![0xac; 10000]);
+      b.txs.push(tx);
+      let mut prevouts = vec![vec![], vec![TxOut { value:1000, script_pubkey:p2wsh }]];
+      // genesis coinbase pays to pubkey
+      assert_eq!(get_block_sig_op_cost(&b, &prevouts, &flags).unwrap(), 4 + 10000);
+      assert_matches!(check_block_sig_op_cost(&b, &prevouts, &flags), Ok(()));
+
+      for i in 0..7 {
+         let mut tx = b.txs[1].clone();
+         tx.ins[0].prevout.n = i + 1;
+         b.txs.push(tx);
+         prevouts.push(prevouts[1].clone());
+      }
+      assert_eq!(get_block_sig_op_cost(&b, &prevouts, &flags).unwrap(), 4 + 80000);
+      assert!(is_error(check_block_sig_op_cost(&b, &prevouts, &flags), C::BlockSigOps));
+      assert!(is_error(check_block_sig_op_cost(&b, &prevouts[1..], &flags), C::BlockSigOps));
+      prevouts[1].clear();
+      assert!(is_error(check_block_sig_op_cost(&b, &prevouts, &flags), C::TxInputsMissingOrSpent));
+   }
+
+   #[test]
+   fn test_duplicate_tx() {
+      let consensus = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN.consensus;
+      let mut b = genesis();
+      b.txs.push(new_tx(1));
+      b.txs.push(new_tx(2));
+      b.header.hash_merkle_root = block_merkle_root(&b).unwrap().0;
+      // [cb, 1, 2] and [cb, 1, 2, 2] have the same merkle root
+      b.txs.push(new_tx(2));
+      assert_eq!(block_merkle_root(&b).unwrap(), (b.header.hash_merkle_root.clone(), true));
+      assert!(is_error(check_block(&mut b, consensus, false, true), C::BlockDuplicateTx));
+   }
+
+   #[test]
+   fn test_check_block_witness() {
+      let mut b = genesis();
+      let mut tx = new_tx(1);
+      tx.ins[0].witness.stack.push(vec![1u8]);
+      b.txs.push(tx);
+      assert!(is_error(check_block_witness(&b, true), C::BlockUnexpectedWitness));
+      assert!(is_error(check_block_witness(&b, false), C::BlockUnexpectedWitness));
+
+      let nonce = UInt256::new(&[7u8;32]);
+      let root = block_witness_merkle_root(&b).unwrap().0;
+      let mut script = vec![0x6a, 0x24];
+      script.extend_from_slice(&WITNESS_COMMITMENT_HEADER[..]);
+      script.extend_from_slice(&dhash_pair(&root, &nonce).data[..]);
+      b.txs[0].outs.push(TxOut { value:0, script_pubkey:Script::new(script) });
+      assert_eq!(get_witness_commitment_index(&b), Some(1));
+      assert!(is_error(check_block_witness(&b, true), C::BlockWitnessNonceSize));
+
+      b.txs[0].ins[0].witness.stack.push(nonce.data.to_vec());
+      assert_matches!(check_block_witness(&b, true), Ok(()));
+      assert!(is_error(check_block_witness(&b, false), C::BlockUnexpectedWitness));
+
+      b.txs[1].ins[0].witness.stack.push(vec![2u8]);
+      assert!(is_error(check_block_witness(&b, true), C::BlockWitnessMerkleMatch));
+   }
+}
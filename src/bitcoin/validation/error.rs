@@ -0,0 +1,108 @@
+use crate::bitcoin::protocol::message::reject_message::RejectCode;
+
+def_error! { ValidationError }
+
+macro_rules! def_validation_error_code {
+   ( $( ($c:ident, $reject:ident, $reason:expr) ),* ,) => {
+      #[derive(Debug,Clone,Copy,PartialEq,Eq)]
+      pub enum ValidationErrorCode {
+         $( $c, )*
+      }
+      impl ValidationErrorCode {
+         const ALL:&'static [ValidationErrorCode] = &[ $( ValidationErrorCode::$c, )* ];
+         pub fn from_u32(v:u32) -> Option<Self> {
+            Self::ALL.get(v as usize).cloned()
+         }
+         /// code of the reject message (BIP61)
+         pub fn reject_code(&self) -> RejectCode {
+            match *self { $( ValidationErrorCode::$c => RejectCode::$reject, )* }
+         }
+         /// reason string of the reject message, same as bitcoin core
+         pub fn reason(&self) -> &'static str {
+            match *self { $( ValidationErrorCode::$c => $reason, )* }
+         }
+      }
+   }
+}
+
+def_validation_error_code! {
+   /* CheckTransaction */
+   (TxVinEmpty,            Invalid,        "bad-txns-vin-empty"),
+   (TxVoutEmpty,           Invalid,        "bad-txns-vout-empty"),
+   (TxOversize,            Invalid,        "bad-txns-oversize"),
+   (TxVoutNegative,        Invalid,        "bad-txns-vout-negative"),
+   (TxVoutTooLarge,        Invalid,        "bad-txns-vout-toolarge"),
+   (TxOutTotalTooLarge,    Invalid,        "bad-txns-txouttotal-toolarge"),
+   (TxInputsDuplicate,     Invalid,        "bad-txns-inputs-duplicate"),
+   (TxCoinbaseLength,      Invalid,        "bad-cb-length"),
+   (TxPrevoutNull,         Invalid,        "bad-txns-prevout-null"),
+   /* CheckTxInputs */
+   (TxInputsMissingOrSpent,  Invalid,        "bad-txns-inputs-missingorspent"),
+   (TxInputValuesOutOfRange, Invalid,        "bad-txns-inputvalues-outofrange"),
+   (TxInBelowOut,            Invalid,        "bad-txns-in-belowout"),
+   (TxFeeOutOfRange,         Invalid,        "bad-txns-fee-outofrange"),
+   /* CheckBlockHeader, CheckBlock */
+   (BlockHighHash,         Invalid,        "high-hash"),
+   /* ContextualCheckBlockHeader */
+   (BlockPrevNotFound,     Invalid,        "prev-blk-not-found"),
+   (BlockBadDiffBits,      Invalid,        "bad-diffbits"),
+   (BlockTimeTooOld,       Invalid,        "time-too-old"),
+   (BlockBadMerkleRoot,    Invalid,        "bad-txnmrklroot"),
+   (BlockDuplicateTx,      Invalid,        "bad-txns-duplicate"),
+   (BlockLength,           Invalid,        "bad-blk-length"),
+   (BlockCoinbaseMissing,  Invalid,        "bad-cb-missing"),
+   (BlockCoinbaseMultiple, Invalid,        "bad-cb-multiple"),
+   (BlockSigOps,           Invalid,        "bad-blk-sigops"),
+   /* ContextualCheckBlock */
+   (BlockWitnessNonceSize,   Invalid,        "bad-witness-nonce-size"),
+   (BlockWitnessMerkleMatch, Invalid,        "bad-witness-merkle-match"),
+   (BlockUnexpectedWitness,  Invalid,        "unexpected-witness"),
+   (BlockWeight,             Invalid,        "bad-blk-weight"),
+   /* IsStandardTx, AreInputsStandard (policy) */
+   (TxVersion,              NonStandard, "version"),
+   (TxSize,                 NonStandard, "tx-size"),
+   (TxSizeSmall,            NonStandard, "tx-size-small"),
+   (TxScriptSigSize,        NonStandard, "scriptsig-size"),
+   (TxScriptSigNotPushOnly, NonStandard, "scriptsig-not-pushonly"),
+   (TxScriptPubKey,         NonStandard, "scriptpubkey"),
+   (TxBareMultisig,         NonStandard, "bare-multisig"),
+   (TxDust,                 NonStandard, "dust"),
+   (TxMultiOpReturn,        NonStandard, "multi-op-return"),
+   (TxNonStandardInputs,    NonStandard, "bad-txns-nonstandard-inputs"),
+   (TxTooManySigOps,        NonStandard, "bad-txns-too-many-sigops"),
+}
+
+impl ValidationError {
+   pub fn is(&self, code: ValidationErrorCode) -> bool {
+      self.code == code as u32
+   }
+   pub fn get_code(&self) -> Option<ValidationErrorCode> {
+      ValidationErrorCode::from_u32(self.code)
+   }
+   pub fn reject_code(&self) -> Option<RejectCode> {
+      self.get_code().map(|c| c.reject_code())
+   }
+}
+
+#[macro_export]
+macro_rules! validation_error {
+   ($c:tt) => {
+      crate::bitcoin::validation::ValidationError::new(
+         crate::bitcoin::validation::ValidationErrorCode::$c.reason(),
+         crate::bitcoin::validation::ValidationErrorCode::$c as u32)
+   };
+   ($c:tt, $msg:expr) => {
+      crate::bitcoin::validation::ValidationError::new(
+         format!("{}: {}", crate::bitcoin::validation::ValidationErrorCode::$c.reason(), $msg),
+         crate::bitcoin::validation::ValidationErrorCode::$c as u32)
+   };
+}
+#[macro_export]
+macro_rules! raise_validation_error {
+   ($c:tt) => {
+      Err( validation_error!($c) )?
+   };
+   ($c:tt, $msg:expr) => {
+      Err( validation_error!($c, $msg) )?
+   };
+}
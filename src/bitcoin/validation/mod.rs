@@ -0,0 +1,20 @@
+pub mod apriori;
+
+#[macro_use]
+pub mod error;
+pub use self::error::{ValidationError, ValidationErrorCode};
+
+pub mod tx;
+pub use self::tx::{
+   check_transaction, check_tx_inputs,
+   get_legacy_sig_op_count, get_p2sh_sig_op_count, get_transaction_sig_op_cost,
+};
+
+pub mod pow;
+pub use self::pow::{
+   HeaderChain, check_proof_of_work,
+   get_next_work_required, calculate_next_work_required, check_next_work_required,
+};
+
+pub mod block;
+pub use self::block::{check_block_header, check_block, check_block_witness, get_block_sig_op_cost, check_block_sig_op_cost};
@@ -0,0 +1,170 @@
+use std::cmp::Ordering;
+use crate::bitcoin::datatypes::{UInt256, BlockHeader};
+use crate::bitcoin::chainparams::Consensus;
+
+/// The headers of a chain indexed by the height, to look back the ancestors of a new block.
+pub trait HeaderChain {
+   fn get_header(&self, height:i32) -> Option<&BlockHeader>;
+}
+impl HeaderChain for [BlockHeader] {
+   fn get_header(&self, height:i32) -> Option<&BlockHeader> {
+      if height < 0 { None } else { self.get(height as usize) }
+   }
+}
+impl HeaderChain for Vec<BlockHeader> {
+   fn get_header(&self, height:i32) -> Option<&BlockHeader> {
+      self.as_slice().get_header(height)
+   }
+}
+
+fn get_ancestor<C:HeaderChain+?Sized>(chain:&C, height:i32) -> crate::Result<&BlockHeader> {
+   match chain.get_header(height) {
+      Some(h) => Ok(h),
+      None => raise_validation_error!(BlockPrevNotFound, format!("height={}", height)),
+   }
+}
+
+/// Returns the bits required for the block at the height whose time is block_time (pow.cpp: GetNextWorkRequired).
+/// The chain must contain the headers from the last retarget to height-1.
+pub fn get_next_work_required<C:HeaderChain+?Sized>(chain:&C, height:i32, block_time:u32, consensus:&Consensus) -> crate::Result<u32> {
+   let pow_limit = consensus.pow_limit.to_compact();
+   if height <= 0 {
+      return Ok(pow_limit);
+   }
+   let interval = consensus.get_difficulty_adjustment_interval() as i32;
+   let last = get_ancestor(chain, height - 1)?;
+
+   if height % interval != 0 {
+      if consensus.pow_allow_min_difficulty_blocks {
+         // testnet: a block may have the minimum difficulty if it is over 20 minutes after the previous one.
+         if (last.time as i64) + consensus.pow_target_spacing * 2 < (block_time as i64) {
+            return Ok(pow_limit);
+         }
+         // otherwise, the bits of the last block which is not a minimum difficulty one.
+         let mut h = height - 1;
+         let mut header = last;
+         while 0 < h && h % interval != 0 && header.bits == pow_limit {
+            h -= 1;
+            header = get_ancestor(chain, h)?;
+         }
+         return Ok(header.bits);
+      }
+      return Ok(last.bits);
+   }
+
+   let first = get_ancestor(chain, height - interval)?;
+   Ok(calculate_next_work_required(last, first.time as i64, consensus))
+}
+
+/// (pow.cpp: CalculateNextWorkRequired)
+pub fn calculate_next_work_required(last:&BlockHeader, first_block_time:i64, consensus:&Consensus) -> u32 {
+   if consensus.pow_no_retargeting {
+      return last.bits;
+   }
+   let timespan = consensus.pow_target_timespan;
+   let actual = (last.time as i64 - first_block_time).max(timespan / 4).min(timespan * 4);
+
+   let (target, _, _) = UInt256::from_compact(last.bits);
+   let target = &(&target * &UInt256::from(actual as u64)) / &UInt256::from(timespan as u64);
+   if target.cmp_as_uint(&consensus.pow_limit) == Ordering::Greater {
+      consensus.pow_limit.to_compact()
+   } else {
+      target.to_compact()
+   }
+}
+
+/// Checks that the bits of the header at the height is the required one (validation.cpp: ContextualCheckBlockHeader).
+pub fn check_next_work_required<C:HeaderChain+?Sized>(header:&BlockHeader, chain:&C, height:i32, consensus:&Consensus) -> crate::Result<()> {
+   let bits = get_next_work_required(chain, height, header.time, consensus)?;
+   if header.bits != bits {
+      raise_validation_error!(BlockBadDiffBits, format!("expected {:08x} but {:08x}", bits, header.bits));
+   }
+   Ok(())
+}
+
+/// Checks whether the hash satisfies the proof of work requirement specified by bits (pow.cpp: CheckProofOfWork).
+pub fn check_proof_of_work(hash:&UInt256, bits:u32, consensus:&Consensus) -> crate::Result<()> {
+   let (target, negative, overflow) = UInt256::from_compact(bits);
+   if negative || target.is_null() || overflow || target.cmp_as_uint(&consensus.pow_limit) == Ordering::Greater {
+      raise_validation_error!(BlockHighHash, format!("nBits below minimum work: {:08x}", bits));
+   }
+   if hash.cmp_as_uint(&target) == Ordering::Greater {
+      raise_validation_error!(BlockHighHash, "proof of work failed");
+   }
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+
+   fn header(time:u32, bits:u32) -> BlockHeader {
+      BlockHeader { time:time, bits:bits, ..Default::default() }
+   }
+
+   #[test]
+   fn test_calculate_next_work_required() {
+      // pow_tests.cpp
+      let consensus = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN.consensus;
+      assert_eq!(calculate_next_work_required(&header(1262152739, 0x1d00ffff), 1261130161, consensus), 0x1d00d86a);
+      // pow limit
+      assert_eq!(calculate_next_work_required(&header(1233061996, 0x1d00ffff), 1231006505, consensus), 0x1d00ffff);
+      // lower limit of the actual timespan
+      assert_eq!(calculate_next_work_required(&header(1279297671, 0x1c05a3f4), 1279008237, consensus), 0x1c0168fd);
+      // upper limit of the actual timespan
+      assert_eq!(calculate_next_work_required(&header(1269211443, 0x1c387f6f), 1263163443, consensus), 0x1d00e1fd);
+
+      let regtest = &crate::bitcoin::presets::bitcoin_regtest::CHAIN.consensus;
+      assert_eq!(calculate_next_work_required(&header(1269211443, 0x1c387f6f), 1263163443, regtest), 0x1c387f6f);
+   }
+
+   #[test]
+   fn test_get_next_work_required() {
+      let mainnet = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN.consensus;
+      let testnet = &crate::bitcoin::presets::bitcoin_testnet::CHAIN.consensus;
+      let interval = mainnet.get_difficulty_adjustment_interval() as u32;
+      assert_eq!(interval, 2016);
+
+      // blocks mined in half of the target spacing
+      let bits = 0x1c05a3f4;
+      let chain:Vec<BlockHeader> = (0..interval).map(|i| header(1279000000 + i * 300, bits)).collect();
+      assert_eq!(get_next_work_required(&chain, 0, 0, mainnet).unwrap(), 0x1d00ffff);
+      assert_eq!(get_next_work_required(&chain, 1000, 0, mainnet).unwrap(), bits);
+      let expect = calculate_next_work_required(&chain[2015], chain[0].time as i64, mainnet);
+      assert_eq!(get_next_work_required(&chain, 2016, 0, mainnet).unwrap(), expect);
+      assert_eq!(expect, 0x1c02d19e);
+      assert_matches!(get_next_work_required(&chain[..100], 2016, 0, mainnet), Err(_));
+
+      // testnet 20 minutes rule
+      let mut chain = chain;
+      let t = chain[999].time;
+      assert_eq!(get_next_work_required(&chain, 1000, t + 1200, testnet).unwrap(), bits);
+      assert_eq!(get_next_work_required(&chain, 1000, t + 1201, testnet).unwrap(), 0x1d00ffff);
+      // skips the minimum difficulty blocks
+      chain[998].bits = 0x1d00ffff;
+      chain[999].bits = 0x1d00ffff;
+      assert_eq!(get_next_work_required(&chain, 1000, t + 1, testnet).unwrap(), bits);
+      assert_eq!(get_next_work_required(&chain, 1000, t + 1, mainnet).unwrap(), 0x1d00ffff);
+
+      let h = header(t + 1, bits);
+      assert_matches!(check_next_work_required(&h, &chain, 1000, testnet), Ok(()));
+      assert_matches!(check_next_work_required(&h, &chain, 1000, mainnet),
+                      Err(crate::Error::BitcoinValidation(ref e)) if e.is(crate::bitcoin::validation::ValidationErrorCode::BlockBadDiffBits));
+   }
+
+   #[test]
+   fn test_check_proof_of_work() {
+      use crate::bitcoin::validation::ValidationErrorCode as C;
+      let consensus = &crate::bitcoin::presets::bitcoin_mainnet::CHAIN.consensus;
+      let genesis = consensus.hash_genesis_block.clone();
+      assert_matches!(check_proof_of_work(&genesis, 0x1d00ffff, consensus), Ok(()));
+      assert_matches!(check_proof_of_work(&genesis, 0x1b00ffff, consensus),
+                      Err(crate::Error::BitcoinValidation(ref e)) if e.is(C::BlockHighHash));
+      // easier than pow_limit
+      assert_matches!(check_proof_of_work(&genesis, 0x1d01ffff, consensus), Err(_));
+      // negative, zero, overflow
+      assert_matches!(check_proof_of_work(&genesis, 0x1d80ffff, consensus), Err(_));
+      assert_matches!(check_proof_of_work(&genesis, 0x1d000000, consensus), Err(_));
+      assert_matches!(check_proof_of_work(&genesis, 0xff00ffff, consensus), Err(_));
+   }
+}
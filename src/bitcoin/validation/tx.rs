@@ -0,0 +1,279 @@
+use crate::bitcoin::datatypes::{Tx, TxOut, Amount, tx_out::money_range};
+use crate::bitcoin::script::parser::Parser;
+use crate::bitcoin::script::flags::ScriptVerify;
+use super::apriori::{
+   MAX_BLOCK_WEIGHT, WITNESS_SCALE_FACTOR, MIN_COINBASE_SCRIPT_SIZE, MAX_COINBASE_SCRIPT_SIZE,
+};
+
+pub(crate) fn get_serialize_size<T>(v:&T, witness:bool) -> crate::Result<usize>
+   where T: crate::bitcoin::serialize::Serializee<P=()>
+{
+   use crate::bitcoin::serialize::{Medium, Serializer as BitcoinSerializer};
+   let med = Medium::default().set_net();
+   let med = if witness { med.set_witness() } else { med.set_no_witness() };
+   let e = BitcoinSerializer::new(&med);
+   v.serialize(&(), &e, &mut std::io::sink())
+}
+
+/// Context-free checks of the tx structure (consensus/tx_check.cpp: CheckTransaction).
+/// Scripts, inputs existence and amounts of inputs are not checked.
+pub fn check_transaction(tx:&Tx) -> crate::Result<()> {
+   if tx.ins.is_empty() {
+      raise_validation_error!(TxVinEmpty);
+   }
+   if tx.outs.is_empty() {
+      raise_validation_error!(TxVoutEmpty);
+   }
+   if MAX_BLOCK_WEIGHT < tx.base_size()? * WITNESS_SCALE_FACTOR {
+      raise_validation_error!(TxOversize);
+   }
+
+   let _ = get_value_out(tx)?;
+
+   {
+      let mut prevouts = std::collections::HashSet::with_capacity(tx.ins.len());
+      for txin in tx.ins.iter() {
+         if !prevouts.insert(&txin.prevout) {
+            raise_validation_error!(TxInputsDuplicate);
+         }
+      }
+   }
+
+   if tx.is_coin_base() {
+      let len = tx.ins[0].script_sig.bytecode.len();
+      if len < MIN_COINBASE_SCRIPT_SIZE || MAX_COINBASE_SCRIPT_SIZE < len {
+         raise_validation_error!(TxCoinbaseLength);
+      }
+   } else {
+      if tx.ins.iter().any(|txin| txin.prevout.is_null()) {
+         raise_validation_error!(TxPrevoutNull);
+      }
+   }
+   Ok(())
+}
+
+fn get_value_out(tx:&Tx) -> crate::Result<Amount> {
+   let mut value_out:Amount = 0;
+   for txout in tx.outs.iter() {
+      if txout.value < 0 {
+         raise_validation_error!(TxVoutNegative);
+      }
+      if !money_range(txout.value) {
+         raise_validation_error!(TxVoutTooLarge);
+      }
+      value_out += txout.value;
+      if !money_range(value_out) {
+         raise_validation_error!(TxOutTotalTooLarge);
+      }
+   }
+   Ok(value_out)
+}
+
+/// Checks the amounts of the spent outputs and returns the fee (tx_verify.cpp: CheckTxInputs).
+/// prevouts are the outputs spent by tx.ins in the same order. The coinbase maturity is not checked.
+pub fn check_tx_inputs(tx:&Tx, prevouts:&[TxOut]) -> crate::Result<Amount> {
+   check_prevouts_len(tx, prevouts)?;
+   let mut value_in:Amount = 0;
+   for prevout in prevouts.iter() {
+      if !money_range(prevout.value) {
+         raise_validation_error!(TxInputValuesOutOfRange);
+      }
+      value_in += prevout.value;
+      if !money_range(value_in) {
+         raise_validation_error!(TxInputValuesOutOfRange);
+      }
+   }
+   let value_out = get_value_out(tx)?;
+   if value_in < value_out {
+      raise_validation_error!(TxInBelowOut, format!("{} < {}", value_in, value_out));
+   }
+   let fee = value_in - value_out;
+   if !money_range(fee) {
+      raise_validation_error!(TxFeeOutOfRange);
+   }
+   Ok(fee)
+}
+
+/// Inaccurate sigop count of the scriptSigs and scriptPubKeys (tx_verify.cpp: GetLegacySigOpCount).
+pub fn get_legacy_sig_op_count(tx:&Tx) -> usize {
+   let ins = tx.ins.iter().map(|txin| Parser::get_sig_op_count(&txin.script_sig.bytecode[..], false));
+   let outs = tx.outs.iter().map(|txout| Parser::get_sig_op_count(&txout.script_pubkey.bytecode[..], false));
+   ins.chain(outs).sum()
+}
+
+fn check_prevouts_len(tx:&Tx, prevouts:&[TxOut]) -> crate::Result<()> {
+   if tx.ins.len() != prevouts.len() {
+      raise_validation_error!(TxInputsMissingOrSpent, format!("{} prevouts for {} inputs", prevouts.len(), tx.ins.len()));
+   }
+   Ok(())
+}
+
+/// Accurate sigop count of the P2SH redeem scripts (tx_verify.cpp: GetP2SHSigOpCount).
+/// prevouts are the outputs spent by tx.ins in the same order.
+pub fn get_p2sh_sig_op_count(tx:&Tx, prevouts:&[TxOut]) -> crate::Result<usize> {
+   if tx.is_coin_base() {
+      return Ok(0);
+   }
+   check_prevouts_len(tx, prevouts)?;
+   Ok(tx.ins.iter().zip(prevouts.iter()).filter(|&(_, prevout)| {
+      Parser::is_pay_to_script_hash(&prevout.script_pubkey.bytecode[..])
+   }).map(|(txin, prevout)| {
+      Parser::get_p2sh_sig_op_count(&prevout.script_pubkey.bytecode[..], &txin.script_sig.bytecode[..])
+   }).sum())
+}
+
+/// Sigop cost, the legacy and P2SH sigops are scaled by WITNESS_SCALE_FACTOR (tx_verify.cpp: GetTransactionSigOpCost).
+/// prevouts are the outputs spent by tx.ins in the same order, they are not used for the coinbase.
+pub fn get_transaction_sig_op_cost(tx:&Tx, prevouts:&[TxOut], flags:&ScriptVerify) -> crate::Result<usize> {
+   let mut cost = get_legacy_sig_op_count(tx) * WITNESS_SCALE_FACTOR;
+   if tx.is_coin_base() {
+      return Ok(cost);
+   }
+   check_prevouts_len(tx, prevouts)?;
+   if flags.is_p2sh() {
+      cost += get_p2sh_sig_op_count(tx, prevouts)? * WITNESS_SCALE_FACTOR;
+   }
+   for (txin, prevout) in tx.ins.iter().zip(prevouts.iter()) {
+      cost += Parser::get_witness_sig_op_count(&txin.script_sig.bytecode[..],
+                                                &prevout.script_pubkey.bytecode[..],
+                                                txin.witness.as_slice(),
+                                                flags);
+   }
+   Ok(cost)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::bitcoin::datatypes::{TxOut, TxOutPoint, Script, tx_out::MAX_MONEY};
+   use crate::bitcoin::datatypes::test_utils::new_txin;
+   use crate::bitcoin::validation::ValidationErrorCode as C;
+
+   fn is_error(r:crate::Result<()>, code:C) -> bool {
+      match r {
+         Err(crate::Error::BitcoinValidation(ref e)) => e.is(code),
+         _ => false,
+      }
+   }
+
+   fn new_tx() -> Tx {
+      let mut tx = Tx::new_null();
+      tx.ins.push(new_txin(1));
+      tx.outs.push(TxOut { value:1000, script_pubkey:Script::new(vec![0x51]) });
+      tx
+   }
+
+   #[test]
+   fn test_check_transaction() {
+      assert_matches!(check_transaction(&new_tx()), Ok(()));
+
+      let mut tx = new_tx();
+      tx.ins.clear();
+      assert!(is_error(check_transaction(&tx), C::TxVinEmpty));
+
+      let mut tx = new_tx();
+      tx.outs.clear();
+      assert!(is_error(check_transaction(&tx), C::TxVoutEmpty));
+
+      let mut tx = new_tx();
+      tx.outs[0].value = -1;
+      assert!(is_error(check_transaction(&tx), C::TxVoutNegative));
+
+      let mut tx = new_tx();
+      tx.outs[0].value = MAX_MONEY + 1;
+      assert!(is_error(check_transaction(&tx), C::TxVoutTooLarge));
+
+      let mut tx = new_tx();
+      tx.outs[0].value = MAX_MONEY;
+      tx.outs.push(tx.outs[0].clone());
+      assert!(is_error(check_transaction(&tx), C::TxOutTotalTooLarge));
+
+      let mut tx = new_tx();
+      tx.ins.push(tx.ins[0].clone());
+      assert!(is_error(check_transaction(&tx), C::TxInputsDuplicate));
+
+      let mut tx = new_tx();
+      tx.ins.push(tx.ins[0].clone());
+      tx.ins[1].prevout = TxOutPoint::new_null();
+      assert!(is_error(check_transaction(&tx), C::TxPrevoutNull));
+
+      let mut tx = new_tx();
+      tx.ins[0].prevout = TxOutPoint::new_null();
+      assert!(is_error(check_transaction(&tx), C::TxCoinbaseLength));
+      tx.ins[0].script_sig = Script::new(vec![0x51, 0x51]);
+      assert_matches!(check_transaction(&tx), Ok(()));
+      tx.ins[0].script_sig = Script::new(vec![0x51; 101]);
+      assert!(is_error(check_transaction(&tx), C::TxCoinbaseLength));
+   }
+
+   #[test]
+   fn test_check_tx_inputs() {
+      let is_error = |r:crate::Result<Amount>, code:C| is_error(r.map(|_| ()), code);
+      let mut tx = new_tx();
+      tx.ins.push(tx.ins[0].clone());
+      tx.ins[1].prevout.n = 1;
+      let prevouts = [
+         TxOut { value:700, script_pubkey:Script::new_null() },
+         TxOut { value:500, script_pubkey:Script::new_null() },
+      ];
+      assert_matches!(check_tx_inputs(&tx, &prevouts), Ok(200));
+      assert!(is_error(check_tx_inputs(&tx, &prevouts[..1]), C::TxInputsMissingOrSpent));
+
+      let mut low = prevouts.clone();
+      low[1].value = 1;
+      assert!(is_error(check_tx_inputs(&tx, &low), C::TxInBelowOut));
+      low[1].value = -1;
+      assert!(is_error(check_tx_inputs(&tx, &low), C::TxInputValuesOutOfRange));
+      low[0].value = MAX_MONEY;
+      low[1].value = 1;
+      assert!(is_error(check_tx_inputs(&tx, &low), C::TxInputValuesOutOfRange));
+
+      let mut neg = tx.clone();
+      neg.outs[0].value = -1;
+      assert!(is_error(check_tx_inputs(&neg, &prevouts), C::TxVoutNegative));
+      neg.outs[0].value = MAX_MONEY + 1;
+      assert!(is_error(check_tx_inputs(&neg, &prevouts), C::TxVoutTooLarge));
+   }
+
+   #[test]
+   fn test_sig_op_count() {
+      let flags = ScriptVerify::default().p2sh(true).witness(true);
+      let p2sh = Script::new(crate::utils::h2b("a9141018853670f9f3b0582c5b9ee8ce93764ac32b9387").unwrap());
+      let p2wpkh = Script::new(crate::utils::h2b("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap());
+      let mut tx = new_tx();
+      // redeem script: OP_CHECKSIG OP_CHECKMULTISIG
+      tx.ins[0].script_sig = Script::new(vec![0x02, 0xac, 0xae]);
+      tx.ins.push(tx.ins[0].clone());
+      tx.ins[1].prevout.n = 1;
+      tx.ins[1].script_sig = Script::new(vec![]);
+      tx.outs[0].script_pubkey = Script::new(vec![0xac]);
+      let prevouts = [
+         TxOut { value:1000, script_pubkey:p2sh },
+         TxOut { value:1000, script_pubkey:p2wpkh },
+      ];
+      assert_eq!(get_legacy_sig_op_count(&tx), 1);
+      assert_eq!(get_p2sh_sig_op_count(&tx, &prevouts).unwrap(), 21);
+      assert_eq!(get_transaction_sig_op_cost(&tx, &prevouts, &flags).unwrap(), 4 + 21 * 4 + 1);
+      assert_eq!(get_transaction_sig_op_cost(&tx, &prevouts, &ScriptVerify::default()).unwrap(), 4);
+
+      // all the spent outputs are needed
+      let is_missing = |r:crate::Result<usize>| is_error(r.map(|_| ()), C::TxInputsMissingOrSpent);
+      assert!(is_missing(get_p2sh_sig_op_count(&tx, &prevouts[..1])));
+      assert!(is_missing(get_transaction_sig_op_cost(&tx, &prevouts[..1], &flags)));
+      assert!(is_missing(get_transaction_sig_op_cost(&tx, &[], &ScriptVerify::default())));
+
+      tx.ins.truncate(1);
+      tx.ins[0].prevout = TxOutPoint::new_null();
+      assert_eq!(get_p2sh_sig_op_count(&tx, &prevouts).unwrap(), 0);
+      assert_eq!(get_transaction_sig_op_cost(&tx, &[], &flags).unwrap(), 4);
+   }
+
+   #[test]
+   fn test_reject_code() {
+      let e = validation_error!(TxVinEmpty);
+      assert_eq!(e.get_code(), Some(C::TxVinEmpty));
+      assert_eq!(e.reject_code(), Some(crate::bitcoin::protocol::RejectCode::Invalid));
+      assert_eq!(e.reject_code().unwrap().to_u8(), 0x10);
+      assert_eq!(format!("{}", e), "bad-txns-vin-empty");
+   }
+}
@@ -16,6 +16,11 @@ pub use self::double::{
    DHash256, Hash160,
 };
 
+pub mod tagged;
+pub use self::tagged::{
+   TaggedSha256, tagged_hash,
+};
+
 pub mod write;
 pub use self::write::DigestWrite;
 
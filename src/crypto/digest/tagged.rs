@@ -0,0 +1,60 @@
+use crypto::digest::Digest;
+use crypto::sha2::Sha256;
+
+// BIP340 tagged hash: SHA256(SHA256(tag) || SHA256(tag) || msg)
+pub struct TaggedSha256 {
+   tag_hash: [u8;32],
+   digest:   Sha256,
+}
+
+impl TaggedSha256 {
+   pub fn new(tag:&str) -> Self {
+      let mut tag_hash = [0u8;32];
+      let mut d = Sha256::new();
+      d.input(tag.as_bytes());
+      d.result(&mut tag_hash);
+      let mut r = Self { tag_hash:tag_hash, digest:Sha256::new() };
+      r.reset();
+      r
+   }
+}
+
+impl Digest for TaggedSha256 {
+   fn input(&mut self, input: &[u8])    { self.digest.input(input) }
+   fn result(&mut self, out: &mut [u8]) { self.digest.result(out) }
+   fn reset(&mut self) {
+      self.digest.reset();
+      self.digest.input(&self.tag_hash);
+      self.digest.input(&self.tag_hash);
+   }
+   fn output_bits(&self) -> usize       { self.digest.output_bits() }
+   fn output_bytes(&self) -> usize      { self.digest.output_bytes() }
+   fn block_size(&self) -> usize        { self.digest.block_size() }
+}
+
+pub fn tagged_hash(tag:&str, input:&[u8]) -> [u8;32] {
+   let mut d = TaggedSha256::new(tag);
+   d.input(input);
+   let mut out = [0u8;32];
+   d.result(&mut out);
+   out
+}
+
+#[test]
+fn test_tagged_hash() {
+   use crate::crypto::digest::helper;
+   let mut d = TaggedSha256::new("TapLeaf");
+   d.input(&[0xc0, 0x01, 0x51]);
+   let h1 = helper::result_hex(&mut d);
+   assert_eq!(h1, crate::utils::b2h(&tagged_hash("TapLeaf", &[0xc0, 0x01, 0x51])[..]));
+
+   let mut d = Sha256::new();
+   let mut t = [0u8;32];
+   d.input(b"TapLeaf");
+   d.result(&mut t);
+   let mut d = Sha256::new();
+   d.input(&t);
+   d.input(&t);
+   d.input(&[0xc0, 0x01, 0x51]);
+   assert_eq!(h1, d.result_str());
+}
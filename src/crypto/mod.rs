@@ -2,6 +2,12 @@ pub mod digest;
 
 pub mod hmac;
 
+pub mod siphash;
+pub use self::siphash::siphash24;
+
+pub mod murmur3;
+pub use self::murmur3::murmur3_32;
+
 #[macro_use]
 pub mod secp256k1;
    
@@ -0,0 +1,51 @@
+// MurmurHash3 x86_32 (hash.cpp), used by the bloom filter of BIP37
+
+pub fn murmur3_32(seed:u32, data:&[u8]) -> u32 {
+   const C1:u32 = 0xcc9e2d51;
+   const C2:u32 = 0x1b873593;
+   let mut h = seed;
+   let mut chunks = data.chunks_exact(4);
+   for c in &mut chunks {
+      let mut k = (c[0] as u32) | (c[1] as u32) << 8 | (c[2] as u32) << 16 | (c[3] as u32) << 24;
+      k = k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+      h ^= k;
+      h = h.rotate_left(13).wrapping_mul(5).wrapping_add(0xe6546b64);
+   }
+   let tail = chunks.remainder();
+   if !tail.is_empty() {
+      let k = tail.iter().rev().fold(0u32, |acc, &b| acc << 8 | b as u32);
+      h ^= k.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+   }
+   h ^= data.len() as u32;
+   h ^= h >> 16;
+   h = h.wrapping_mul(0x85ebca6b);
+   h ^= h >> 13;
+   h = h.wrapping_mul(0xc2b2ae35);
+   h ^= h >> 16;
+   h
+}
+
+#[test]
+fn test_murmur3_32() {
+   // hash_tests.cpp
+   use crate::utils::h2b;
+   let cases:&[(u32, u32, &str)] = &[
+      (0x00000000, 0x00000000, ""),
+      (0x6a396f08, 0xFBA4C795, ""),
+      (0x81f16f39, 0xffffffff, ""),
+      (0x514e28b7, 0x00000000, "00"),
+      (0xea3f0b17, 0xFBA4C795, "00"),
+      (0xfd6cf10d, 0x00000000, "ff"),
+      (0x16c6b7ab, 0x00000000, "0011"),
+      (0x8eb51c3d, 0x00000000, "001122"),
+      (0xb4471bf8, 0x00000000, "00112233"),
+      (0xe2301fa8, 0x00000000, "0011223344"),
+      (0xfc2e4a15, 0x00000000, "001122334455"),
+      (0xb074502c, 0x00000000, "00112233445566"),
+      (0x8034d2a0, 0x00000000, "0011223344556677"),
+      (0xb4698def, 0x00000000, "001122334455667788"),
+   ];
+   for &(expected, seed, data) in cases.iter() {
+      assert_eq!(murmur3_32(seed, &h2b(data).unwrap()[..]), expected);
+   }
+}
@@ -20,6 +20,8 @@ pub use self::secret_key::{
    RawDecoder as SecretKeyRawDecoder,
 };
 
+pub mod schnorr;
+
 pub mod signature;
 pub use self::signature::{
    DerEncoder, DerDecoder,
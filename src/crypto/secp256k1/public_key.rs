@@ -17,8 +17,8 @@ pub fn verify<T:Verification>(ctx: &Secp256k1<T>, pk: &PublicKey, msg: &[u8], si
    Ok(())
 }
 
-pub const SEC1_TAG_ODD:u8 = 0x02;
-pub const SEC1_TAG_EVEN:u8 = 0x03;
+pub const SEC1_TAG_EVEN:u8 = 0x02;
+pub const SEC1_TAG_ODD:u8 = 0x03;
 pub const SEC1_TAG_UNCOMPRESSED:u8 = 0x04;
 pub const SEC1_TAG_HYBRID_EVEN:u8 = 0x06;
 pub const SEC1_TAG_HYBRID_ODD:u8 = 0x07;
@@ -0,0 +1,221 @@
+use secp256k1::{Secp256k1, All};
+use secp256k1::key::{PublicKey, SecretKey};
+use num::bigint::BigUint;
+use num::Zero;
+use crate::crypto::digest::tagged_hash;
+
+lazy_static! {
+   static ref SECP256K1_P:BigUint = BigUint::from_bytes_be(&[
+      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+      0xFF, 0xFF, 0xFF, 0xFE, 0xFF, 0xFF, 0xFC, 0x2F,
+   ]);
+   static ref SECP256K1_N:BigUint = BigUint::from_bytes_be(&[
+      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+      0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE,
+      0xBA, 0xAE, 0xDC, 0xE6, 0xAF, 0x48, 0xA0, 0x3B,
+      0xBF, 0xD2, 0x5E, 0x8C, 0xD0, 0x36, 0x41, 0x41,
+   ]);
+}
+
+fn to_bytes32(v:&BigUint) -> [u8;32] {
+   let b = v.to_bytes_be();
+   let mut out = [0u8;32];
+   out[(32-b.len())..].copy_from_slice(&b);
+   out
+}
+
+// lift_x of BIP340: the point with the given x coordinate and an even y
+pub fn lift_x(xonly:&[u8]) -> crate::Result<PublicKey> {
+   if xonly.len() != 32 {
+      raise_secp256k1_error!(format!("x-only public key is not 32 bytes length: {}", xonly.len()));
+   }
+   let mut vch = [0u8;33];
+   vch[0] = super::public_key::SEC1_TAG_EVEN;
+   vch[1..].copy_from_slice(xonly);
+   let pk = PublicKey::from_slice(&vch[..])?;
+   Ok(pk)
+}
+
+// returns x-only encoding and whether y is odd
+pub fn to_xonly(pk:&PublicKey) -> ([u8;32], bool) {
+   let vch = pk.serialize();
+   let mut x = [0u8;32];
+   x.copy_from_slice(&vch[1..33]);
+   (x, vch[0] == super::public_key::SEC1_TAG_ODD)
+}
+
+pub fn verify(ctx:&Secp256k1<All>, xonly:&[u8], msg:&[u8], sig:&[u8]) -> crate::Result<()> {
+   if sig.len() != 64 {
+      raise_secp256k1_error!(format!("schnorr signature is not 64 bytes length: {}", sig.len()));
+   }
+   let pk = lift_x(xonly)?;
+   let r = BigUint::from_bytes_be(&sig[0..32]);
+   let s = BigUint::from_bytes_be(&sig[32..64]);
+   if *SECP256K1_P <= r {
+      raise_secp256k1_error!("r is out of range");
+   }
+   if *SECP256K1_N <= s {
+      raise_secp256k1_error!("s is out of range");
+   }
+
+   let e = {
+      let mut tmp = Vec::<u8>::with_capacity(64 + msg.len());
+      tmp.extend_from_slice(&sig[0..32]);
+      tmp.extend_from_slice(xonly);
+      tmp.extend_from_slice(msg);
+      BigUint::from_bytes_be(&tagged_hash("BIP0340/challenge", tmp.as_slice())) % &*SECP256K1_N
+   };
+   let neg_e = (&*SECP256K1_N - e) % &*SECP256K1_N;
+
+   // R = s*G - e*P
+   let sg = if s.is_zero() {
+      None
+   } else {
+      let sk = SecretKey::from_slice(&sig[32..64])?;
+      Some(PublicKey::from_secret_key(ctx, &sk))
+   };
+   let ep = if neg_e.is_zero() {
+      None
+   } else {
+      let mut tmp = pk.clone();
+      tmp.mul_assign(ctx, &to_bytes32(&neg_e))?;
+      Some(tmp)
+   };
+   let rp = match (sg, ep) {
+      (Some(a), Some(b)) => a.combine(&b).map_err(|_| secp256k1_error!("R is infinity"))?,
+      (Some(a), None) => a,
+      (None, Some(b)) => b,
+      (None, None) => raise_secp256k1_error!("R is infinity"),
+   };
+   let (rx, odd) = to_xonly(&rp);
+   if odd || &rx[..] != &sig[0..32] {
+      raise_secp256k1_error!("schnorr signature mismatch");
+   }
+   Ok(())
+}
+
+// whether output_xonly (with parity) == internal_xonly + tweak*G
+pub fn check_tweak(ctx:&Secp256k1<All>, internal_xonly:&[u8], tweak:&[u8;32], output_xonly:&[u8], parity:bool) -> crate::Result<bool> {
+   let mut pk = lift_x(internal_xonly)?;
+   pk.add_exp_assign(ctx, &tweak[..])?;
+   let (x, odd) = to_xonly(&pk);
+   Ok(&x[..] == output_xonly && odd == parity)
+}
+
+/// BIP340 signing with the auxiliary random data, only for the tests of the verifiers
+#[cfg(test)]
+pub fn sign(ctx:&Secp256k1<All>, seckey:&[u8], msg:&[u8], aux:&[u8;32]) -> crate::Result<[u8;64]> {
+   let pk = PublicKey::from_secret_key(ctx, &SecretKey::from_slice(seckey)?);
+   let (px, odd) = to_xonly(&pk);
+   let d = BigUint::from_bytes_be(seckey);
+   let d = if odd { &*SECP256K1_N - d } else { d };
+
+   let mut t = to_bytes32(&d);
+   for (a, b) in t.iter_mut().zip(tagged_hash("BIP0340/aux", &aux[..]).iter()) {
+      *a ^= *b;
+   }
+   let k = {
+      let mut tmp = Vec::<u8>::with_capacity(64 + msg.len());
+      tmp.extend_from_slice(&t);
+      tmp.extend_from_slice(&px);
+      tmp.extend_from_slice(msg);
+      BigUint::from_bytes_be(&tagged_hash("BIP0340/nonce", tmp.as_slice())) % &*SECP256K1_N
+   };
+   if k.is_zero() {
+      raise_secp256k1_error!("nonce is zero");
+   }
+   let rp = PublicKey::from_secret_key(ctx, &SecretKey::from_slice(&to_bytes32(&k))?);
+   let (rx, odd) = to_xonly(&rp);
+   let k = if odd { &*SECP256K1_N - k } else { k };
+
+   let e = {
+      let mut tmp = Vec::<u8>::with_capacity(64 + msg.len());
+      tmp.extend_from_slice(&rx);
+      tmp.extend_from_slice(&px);
+      tmp.extend_from_slice(msg);
+      BigUint::from_bytes_be(&tagged_hash("BIP0340/challenge", tmp.as_slice())) % &*SECP256K1_N
+   };
+   let s = (k + e * d) % &*SECP256K1_N;
+   let mut sig = [0u8;64];
+   sig[0..32].copy_from_slice(&rx);
+   sig[32..64].copy_from_slice(&to_bytes32(&s));
+   Ok(sig)
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::utils::h2b;
+
+   // BIP340 test vectors
+   const VECTORS:&[(&str, &str, &str, bool)] = &[
+      ("F9308A019258C31049344F85F89D5229B531C845836F99B08601F113BCE036F9",
+       "0000000000000000000000000000000000000000000000000000000000000000",
+       "E907831F80848D1069A5371B402410364BDF1C5F8307B0084C55F1CE2DCA821525F66A4A85EA8B71E482A74F382D2CE5EBEEE8FDB2172F477DF4900D310536C0",
+       true),
+      ("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+       "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+       "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+       true),
+      // message modified
+      ("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+       "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C8A",
+       "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+       false),
+      // public key is not on the curve
+      ("EEFDEA4CDB677750A420FEE807EACF21EB9898AE79B9768766E4FAA04A2D4A34",
+       "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+       "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE33418906D11AC976ABCCB20B091292BFF4EA897EFCB639EA871CFA95F6DE339E4B0A",
+       false),
+      // s is equal to the curve order
+      ("DFF1D77F2A671C5F36183726DB2341BE58FEAE1DA2DECED843240F7B502BA659",
+       "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89",
+       "6896BD60EEAE296DB48A229FF71DFE071BDE413E6D43F917DC8DCF8C78DE3341FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFEBAAEDCE6AF48A03BBFD25E8CD0364141",
+       false),
+   ];
+
+   #[test]
+   fn test_verify() {
+      let ctx = Secp256k1::new();
+      for &(pk, msg, sig, expect) in VECTORS.iter() {
+         let pk  = h2b(pk).unwrap();
+         let msg = h2b(msg).unwrap();
+         let sig = h2b(sig).unwrap();
+         assert_eq!(verify(&ctx, &pk, &msg, &sig).is_ok(), expect);
+      }
+   }
+
+   #[test]
+   fn test_sign() {
+      let ctx = Secp256k1::new();
+      // BIP340 test vectors 0 and 1
+      let cases:&[(&str, &str, &str, usize)] = &[
+         ("0000000000000000000000000000000000000000000000000000000000000003",
+          "0000000000000000000000000000000000000000000000000000000000000000", VECTORS[0].2, 0),
+         ("B7E151628AED2A6ABF7158809CF4F3C762E7160F38B4DA56A784D9045190CFEF",
+          "243F6A8885A308D313198A2E03707344A4093822299F31D0082EFA98EC4E6C89", VECTORS[1].2, 1),
+      ];
+      for &(sk, msg, sig, aux) in cases.iter() {
+         let mut aux32 = [0u8;32];
+         aux32[31] = aux as u8;
+         let sk  = h2b(sk).unwrap();
+         let msg = h2b(msg).unwrap();
+         assert_eq!(&sign(&ctx, &sk, &msg, &aux32).unwrap()[..], &h2b(sig).unwrap()[..]);
+      }
+   }
+
+   #[test]
+   fn test_check_tweak() {
+      let ctx = Secp256k1::new();
+      // G + 1*G = 2G
+      let g  = h2b("79BE667EF9DCBBAC55A06295CE870B07029BFCDB2DCE28D959F2815B16F81798").unwrap();
+      let g2 = h2b("C6047F9441ED7D6D3045406E95C07CD85C778E4B8CEF3CA7ABAC09B95C709EE5").unwrap();
+      let mut one = [0u8;32];
+      one[31] = 1;
+      assert_matches!(check_tweak(&ctx, &g, &one, &g2, false), Ok(true));
+      assert_matches!(check_tweak(&ctx, &g, &one, &g2, true), Ok(false));
+      assert_matches!(check_tweak(&ctx, &g, &one, &g, false), Ok(false));
+   }
+}
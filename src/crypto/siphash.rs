@@ -0,0 +1,55 @@
+// SipHash-2-4 (crypto/siphash.cpp)
+
+fn sip_round(v:&mut [u64;4]) {
+   v[0] = v[0].wrapping_add(v[1]); v[1] = v[1].rotate_left(13); v[1] ^= v[0]; v[0] = v[0].rotate_left(32);
+   v[2] = v[2].wrapping_add(v[3]); v[3] = v[3].rotate_left(16); v[3] ^= v[2];
+   v[0] = v[0].wrapping_add(v[3]); v[3] = v[3].rotate_left(21); v[3] ^= v[0];
+   v[2] = v[2].wrapping_add(v[1]); v[1] = v[1].rotate_left(17); v[1] ^= v[2]; v[2] = v[2].rotate_left(32);
+}
+
+fn read_u64le(b:&[u8]) -> u64 {
+   b.iter().enumerate().fold(0u64, |acc, (i, &x)| acc | (x as u64) << (8 * i))
+}
+
+pub fn siphash24(k0:u64, k1:u64, data:&[u8]) -> u64 {
+   let mut v = [
+      0x736f6d6570736575u64 ^ k0,
+      0x646f72616e646f6du64 ^ k1,
+      0x6c7967656e657261u64 ^ k0,
+      0x7465646279746573u64 ^ k1,
+   ];
+   let mut chunks = data.chunks_exact(8);
+   for c in &mut chunks {
+      let m = read_u64le(c);
+      v[3] ^= m;
+      sip_round(&mut v);
+      sip_round(&mut v);
+      v[0] ^= m;
+   }
+   let m = read_u64le(chunks.remainder()) | (data.len() as u64) << 56;
+   v[3] ^= m;
+   sip_round(&mut v);
+   sip_round(&mut v);
+   v[0] ^= m;
+
+   v[2] ^= 0xff;
+   for _ in 0..4 {
+      sip_round(&mut v);
+   }
+   v[0] ^ v[1] ^ v[2] ^ v[3]
+}
+
+#[test]
+fn test_siphash24() {
+   // hash_tests.cpp
+   let k0 = 0x0706050403020100u64;
+   let k1 = 0x0F0E0D0C0B0A0908u64;
+   assert_eq!(siphash24(k0, k1, &[]), 0x726fdb47dd0e0e31u64);
+   assert_eq!(siphash24(k0, k1, &[0]), 0x74f839c593dc67fdu64);
+   let data:Vec<u8> = (0u8..8).collect();
+   assert_eq!(siphash24(k0, k1, &data[..]), 0x93f5f5799a932462u64);
+   let data:Vec<u8> = (0u8..15).collect();
+   assert_eq!(siphash24(k0, k1, &data[..]), 0xa129ca6149be45e5u64);
+   let data:Vec<u8> = (0u8..32).collect();
+   assert_eq!(siphash24(k0, k1, &data[..]), 0x7127512f72f27cceu64);
+}
@@ -112,12 +112,18 @@ def_error_convert! {
    (Unknown,                UnknownError),
    (BaseNError,             crate::utils::BaseNError),
    (Base58checkError,       crate::utils::Base58checkError),
+   (Base32Error,            crate::utils::Base32Error),
+   (Bech32Error,            crate::utils::Bech32Error),
    (HexByte,                crate::utils::HexByteError),
    (BitcoinSerialize,       crate::bitcoin::serialize::SerializeError),
    (BitcoinDeserialize,     crate::bitcoin::serialize::DeserializeError),
+   (BitcoinProtocol,        crate::bitcoin::protocol::ProtocolError),
    (BitcoinScript,          crate::bitcoin::script::Error),
    (BitcoinParseScript,     crate::bitcoin::script::ParseError),
    (BitcoinInterpretScript, crate::bitcoin::script::InterpretError),
+   (BitcoinValidation,      crate::bitcoin::validation::ValidationError),
+   (BitcoinPayTo,           crate::bitcoin::utils::PayToError),
+   (BitcoinMerkle,          crate::bitcoin::datatypes::MerkleError),
 }
 
 impl From<std::io::Error> for Error {
@@ -49,8 +49,18 @@ impl Chain {
       crate::crypto::bip32::xprv::Decoder::new(b58c)
    }
    
-   pub fn parse_address(&self, addr:&str) -> Option<crate::bitcoin::utils::PayTo> {
-      crate::bitcoin::utils::PayTo::parse_address(addr, &self.params.base58check)
+   pub fn encode_segwit_address(&self, version:u8, program:&[u8]) -> crate::Result<String> {
+      crate::utils::segwit_address_encode(self.params.bech32_hrp, version, program)
+   }
+   pub fn decode_segwit_address(&self, addr:&str) -> crate::Result<(u8, Vec<u8>)> {
+      crate::utils::segwit_address_decode(self.params.bech32_hrp, addr)
+   }
+
+   pub fn parse_address(&self, addr:&str) -> crate::Result<crate::bitcoin::utils::PayTo> {
+      crate::bitcoin::utils::PayTo::parse_address(addr, self.params)
+   }
+   pub fn to_address(&self, payto:&crate::bitcoin::utils::PayTo) -> Option<String> {
+      payto.to_address(self.params)
    }
    
    pub fn parse_secret_key_base58check(&self, s:&str) -> crate::Result<secp256k1::key::SecretKey> {
@@ -25,9 +25,18 @@ pub fn tx_to_hex(data: &Tx) -> crate::Result<String> {
    Ok(h)
 }
 pub fn tx_to_txid(data: &Tx) -> crate::Result<String> {
-   let b = serialize(data, &())?;
-   let h = crate::ui::create_dhash256().u8_to_hex_rev(b);
-   Ok(h)
+   let h = tx_to_txid_uint256(data)?;
+   uint256_to_hex(&h)
+}
+pub fn tx_to_wtxid(data: &Tx) -> crate::Result<String> {
+   let h = tx_to_wtxid_uint256(data)?;
+   uint256_to_hex(&h)
+}
+pub fn tx_to_txid_uint256(data: &Tx) -> crate::Result<UInt256> {
+   data.txid()
+}
+pub fn tx_to_wtxid_uint256(data: &Tx) -> crate::Result<UInt256> {
+   data.wtxid()
 }
 
 
@@ -1,7 +1,7 @@
 use num::bigint::BigUint;
 use secp256k1::{Secp256k1, Signature, All};
 use secp256k1::key::{PublicKey, SecretKey};
-use crate::crypto::secp256k1::{signature, public_key, secret_key};
+use crate::crypto::secp256k1::{signature, public_key, secret_key, schnorr};
 
 #[derive(Clone)]
 pub struct SignatureUi {
@@ -99,6 +99,27 @@ impl PublicKeyUi {
 }
 
 
+#[derive(Clone)]
+pub struct XOnlyPublicKeyUi {
+   pub xonly: [u8;32],
+   pub ctx: Secp256k1<All>,
+}
+
+impl XOnlyPublicKeyUi {
+   pub fn s_decode(vch:&[u8]) -> crate::Result<Self> {
+      let _ = schnorr::lift_x(vch)?;
+      let mut xonly = [0u8;32];
+      xonly.copy_from_slice(vch);
+      Ok(Self { xonly:xonly, ctx: Secp256k1::new() })
+   }
+   pub fn verify_schnorr(&self, msg:&[u8], sig:&[u8]) -> crate::Result<()> {
+      schnorr::verify(&self.ctx, &self.xonly, msg, sig)
+   }
+   pub fn check_tweak(&self, tweak:&[u8;32], output_xonly:&[u8], parity:bool) -> crate::Result<bool> {
+      schnorr::check_tweak(&self.ctx, &self.xonly, tweak, output_xonly, parity)
+   }
+}
+
 
 #[derive(Clone)]
 pub struct SecretKeyUi {
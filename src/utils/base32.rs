@@ -0,0 +1,82 @@
+def_error! { Base32Error }
+macro_rules! raise_base32_error {
+   ($m:expr) => {
+      Err(crate::utils::Base32Error::new($m, 0))?
+   }
+}
+
+/// RFC4648 alphabet in lower case, which is used by the onion and the i2p addresses.
+pub const BASE32_TABLE:&'static [u8] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Regroups the bits of each value from `from` bits to `to` bits.
+/// The remaining bits are zero padded if `pad`, otherwise they must be less than `from` bits and zero.
+pub fn convert_bits(data:&[u8], from:u32, to:u32, pad:bool) -> Option<Vec<u8>> {
+   let mut acc:u32 = 0;
+   let mut bits:u32 = 0;
+   let maxv:u32 = (1 << to) - 1;
+   let mut ret = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+   for &v in data.iter() {
+      if (v as u32) >> from != 0 {
+         return None;
+      }
+      acc = (acc << from) | v as u32;
+      bits += from;
+      while to <= bits {
+         bits -= to;
+         ret.push(((acc >> bits) & maxv) as u8);
+      }
+      acc &= (1 << bits) - 1;
+   }
+   if pad {
+      if 0 < bits {
+         ret.push(((acc << (to - bits)) & maxv) as u8);
+      }
+   } else if from <= bits || acc != 0 {
+      return None;
+   }
+   Some(ret)
+}
+
+/// without the padding characters
+pub fn base32_encode(bytes:&[u8]) -> String {
+   convert_bits(bytes, 8, 5, true).unwrap().iter().map(|&i| BASE32_TABLE[i as usize] as char).collect()
+}
+
+/// Accepts either case. The padding characters are not allowed.
+pub fn base32_decode(s:&str) -> crate::Result<Box<[u8]>> {
+   let mut v = Vec::<u8>::with_capacity(s.len());
+   for c in s.bytes() {
+      let c = c.to_ascii_lowercase();
+      match BASE32_TABLE.iter().position(|&t| t == c) {
+         Some(i) => v.push(i as u8),
+         None => raise_base32_error!(format!("not a base32 char: {}", c as char)),
+      }
+   }
+   // the trailing bits of the last char are padding and must be zero
+   let ret = match convert_bits(&v[..], 5, 8, false) {
+      Some(r) => r,
+      None => raise_base32_error!(format!("non-zero padding: {}", s)),
+   };
+   Ok(ret.into_boxed_slice())
+}
+
+#[test]
+fn test_base32() {
+   let cases:&[(&str, &str)] = &[
+      ("", ""),
+      ("f", "my"),
+      ("fo", "mzxq"),
+      ("foo", "mzxw6"),
+      ("foob", "mzxw6yq"),
+      ("fooba", "mzxw6ytb"),
+      ("foobar", "mzxw6ytboi"),
+   ];
+   for &(plain, enc) in cases.iter() {
+      assert_eq!(base32_encode(plain.as_bytes()), enc);
+      assert_eq!(&base32_decode(enc).unwrap()[..], plain.as_bytes());
+      assert_eq!(&base32_decode(&enc.to_uppercase()).unwrap()[..], plain.as_bytes());
+   }
+   assert_matches!(base32_decode("mzxw6yq="), Err(crate::Error::Base32Error(_)));
+   assert_matches!(base32_decode("mzxw6yr"), Err(crate::Error::Base32Error(_)));
+   assert_matches!(base32_decode("m1"), Err(crate::Error::Base32Error(_)));
+}
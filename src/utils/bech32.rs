@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+use super::base32::convert_bits;
+
+def_error! { Bech32Error }
+macro_rules! raise_bech32_error {
+   ($m:expr) => {
+      Err(crate::utils::Bech32Error::new($m, 0))?
+   }
+}
+
+pub const BECH32_CHARSET:&'static [u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+pub const BECH32_SEPARATOR:char = '1';
+pub const BECH32_MAX_LENGTH:usize = 90;
+pub const BECH32_CHECKSUM_LENGTH:usize = 6;
+
+const GENERATOR:[u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum Bech32Variant {
+   /// BIP173
+   Bech32,
+   /// BIP350
+   Bech32m,
+}
+
+impl Bech32Variant {
+   fn constant(&self) -> u32 {
+      match *self {
+         Bech32Variant::Bech32  => 1,
+         Bech32Variant::Bech32m => 0x2bc830a3,
+      }
+   }
+   fn from_constant(c:u32) -> Option<Self> {
+      match c {
+         1          => Some(Bech32Variant::Bech32),
+         0x2bc830a3 => Some(Bech32Variant::Bech32m),
+         _ => None,
+      }
+   }
+}
+
+fn polymod_step(c:u32, v:u8) -> u32 {
+   let b = c >> 25;
+   let mut c = ((c & 0x1ffffff) << 5) ^ (v as u32);
+   for i in 0..5 {
+      if (b >> i) & 1 != 0 {
+         c ^= GENERATOR[i];
+      }
+   }
+   c
+}
+
+fn polymod(hrp:&[u8], data:&[u8]) -> u32 {
+   let mut c = 1u32;
+   for &h in hrp.iter() { c = polymod_step(c, h >> 5); }
+   c = polymod_step(c, 0);
+   for &h in hrp.iter() { c = polymod_step(c, h & 0x1f); }
+   for &v in data.iter() { c = polymod_step(c, v); }
+   c
+}
+
+/// Encodes 5 bit values with the lower case hrp.
+pub fn bech32_encode(hrp:&str, data:&[u8], variant:Bech32Variant) -> crate::Result<String> {
+   if hrp.is_empty() || hrp.bytes().any(|c| c < 33 || 126 < c || c.is_ascii_uppercase()) {
+      raise_bech32_error!(format!("invalid hrp: {}", hrp));
+   }
+   if BECH32_MAX_LENGTH < hrp.len() + 1 + data.len() + BECH32_CHECKSUM_LENGTH {
+      raise_bech32_error!(format!("too long: {}", hrp.len() + 1 + data.len() + BECH32_CHECKSUM_LENGTH));
+   }
+   if data.iter().any(|&v| 32 <= v) {
+      raise_bech32_error!("data is not 5 bit values");
+   }
+   let mut v = data.to_vec();
+   v.extend_from_slice(&[0u8; BECH32_CHECKSUM_LENGTH]);
+   let m = polymod(hrp.as_bytes(), &v[..]) ^ variant.constant();
+   for i in 0..BECH32_CHECKSUM_LENGTH {
+      v[data.len() + i] = ((m >> (5 * (5 - i))) & 0x1f) as u8;
+   }
+   let mut ret = String::with_capacity(hrp.len() + 1 + v.len());
+   ret.push_str(hrp);
+   ret.push(BECH32_SEPARATOR);
+   ret.extend(v.iter().map(|&i| BECH32_CHARSET[i as usize] as char));
+   Ok(ret)
+}
+
+/// Splits the string into the lower cased hrp and the 5 bit values including the checksum.
+fn bech32_split(s:&str) -> crate::Result<(String, Vec<u8>)> {
+   if BECH32_MAX_LENGTH < s.len() {
+      raise_bech32_error!(format!("too long: {}", s.len()));
+   }
+   if s.bytes().any(|c| c < 33 || 126 < c) {
+      raise_bech32_error!("invalid character");
+   }
+   if s.bytes().any(|c| c.is_ascii_lowercase()) && s.bytes().any(|c| c.is_ascii_uppercase()) {
+      raise_bech32_error!("mixed case");
+   }
+   let s = s.to_ascii_lowercase();
+   let pos = match s.rfind(BECH32_SEPARATOR) {
+      Some(p) => p,
+      None => raise_bech32_error!("no separator"),
+   };
+   if pos == 0 {
+      raise_bech32_error!("empty hrp");
+   }
+   if s.len() < pos + 1 + BECH32_CHECKSUM_LENGTH {
+      raise_bech32_error!("too short checksum");
+   }
+   let mut data = Vec::with_capacity(s.len() - pos - 1);
+   for (i, c) in s.bytes().enumerate().skip(pos + 1) {
+      match BECH32_CHARSET.iter().position(|&t| t == c) {
+         Some(v) => data.push(v as u8),
+         None => raise_bech32_error!(format!("invalid data character at {}: {}", i, c as char)),
+      }
+   }
+   Ok((s[..pos].to_string(), data))
+}
+
+/// Returns the lower cased hrp and the 5 bit values without the checksum.
+/// The message of the checksum error tells the positions of the errors if they can be located.
+pub fn bech32_decode(s:&str) -> crate::Result<(String, Vec<u8>, Bech32Variant)> {
+   let (hrp, mut data) = bech32_split(s)?;
+   let variant = match Bech32Variant::from_constant(polymod(hrp.as_bytes(), &data[..])) {
+      Some(v) => v,
+      None => match locate_errors(s) {
+         Some(ref p) if !p.is_empty() => raise_bech32_error!(format!("invalid checksum: errors at {:?}", p)),
+         _ => raise_bech32_error!("invalid checksum"),
+      },
+   };
+   let len = data.len() - BECH32_CHECKSUM_LENGTH;
+   data.truncate(len);
+   Ok((hrp, data, variant))
+}
+
+/// Locates up to 2 substitution errors in the data part and returns their positions in the string.
+/// Returns an empty vec for a valid string, None if the string is malformed or the errors cannot be located.
+pub fn locate_errors(s:&str) -> Option<Vec<usize>> {
+   let (hrp, data) = match bech32_split(s) {
+      Ok(r) => r,
+      Err(_) => return None,
+   };
+   let m = polymod(hrp.as_bytes(), &data[..]);
+   if Bech32Variant::from_constant(m).is_some() {
+      return Some(Vec::new());
+   }
+
+   // polymod is linear, so an error of value v at the distance d from the end shifts the residue by syndromes[d][v].
+   let len = data.len();
+   let mut syndromes = Vec::<[u32; 32]>::with_capacity(len);
+   let mut s0 = [0u32; 32];
+   for v in 1..32 { s0[v] = v as u32; }
+   syndromes.push(s0);
+   for d in 1..len {
+      let mut sd = [0u32; 32];
+      for v in 1..32 { sd[v] = polymod_step(syndromes[d-1][v], 0); }
+      syndromes.push(sd);
+   }
+   let mut singles = HashMap::<u32, usize>::with_capacity(len * 31);
+   for d in 0..len {
+      for v in 1..32 { singles.insert(syndromes[d][v], d); }
+   }
+   let offset = hrp.len() + 1;
+   let to_pos = |d:usize| offset + len - 1 - d;
+
+   let locate = |residue:u32| -> Option<Vec<usize>> {
+      if let Some(&d) = singles.get(&residue) {
+         return Some(vec![to_pos(d)]);
+      }
+      for d0 in 0..len {
+         for v in 1..32 {
+            if let Some(&d1) = singles.get(&(residue ^ syndromes[d0][v])) {
+               if d0 < d1 {
+                  return Some(vec![to_pos(d1), to_pos(d0)]);
+               }
+            }
+         }
+      }
+      None
+   };
+
+   // the encoding with the fewest errors, Bech32 on a tie
+   let mut best:Option<Vec<usize>> = None;
+   for variant in [Bech32Variant::Bech32, Bech32Variant::Bech32m].iter() {
+      if let Some(p) = locate(m ^ variant.constant()) {
+         if best.as_ref().map_or(true, |b| p.len() < b.len()) {
+            best = Some(p);
+         }
+      }
+   }
+   best
+}
+
+/// BIP350 segwit address
+pub fn segwit_address_encode(hrp:&str, version:u8, program:&[u8]) -> crate::Result<String> {
+   check_witness_program(version, program)?;
+   let variant = if version == 0 { Bech32Variant::Bech32 } else { Bech32Variant::Bech32m };
+   let mut data = vec![version];
+   data.extend(convert_bits(program, 8, 5, true).unwrap());
+   bech32_encode(hrp, &data[..], variant)
+}
+
+/// Returns the witness version and the program of the segwit address of the hrp.
+pub fn segwit_address_decode(hrp:&str, addr:&str) -> crate::Result<(u8, Vec<u8>)> {
+   let (h, data, variant) = bech32_decode(addr)?;
+   if h != hrp.to_ascii_lowercase() {
+      raise_bech32_error!(format!("hrp mismatch: expected {} but {}", hrp, h));
+   }
+   if data.is_empty() {
+      raise_bech32_error!("empty data");
+   }
+   let version = data[0];
+   let expected = if version == 0 { Bech32Variant::Bech32 } else { Bech32Variant::Bech32m };
+   if variant != expected {
+      raise_bech32_error!(format!("invalid checksum variant for witness version {}", version));
+   }
+   let program = match convert_bits(&data[1..], 5, 8, false) {
+      Some(p) => p,
+      None => raise_bech32_error!("invalid padding"),
+   };
+   check_witness_program(version, &program[..])?;
+   Ok((version, program))
+}
+
+fn check_witness_program(version:u8, program:&[u8]) -> crate::Result<()> {
+   if 16 < version {
+      raise_bech32_error!(format!("invalid witness version: {}", version));
+   }
+   if program.len() < 2 || 40 < program.len() {
+      raise_bech32_error!(format!("invalid program length: {}", program.len()));
+   }
+   if version == 0 && program.len() != 20 && program.len() != 32 {
+      raise_bech32_error!(format!("invalid program length for witness version 0: {}", program.len()));
+   }
+   Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+   use super::*;
+   use crate::utils::h2b;
+
+   #[test]
+   fn test_valid_checksum() {
+      let cases:&[(&str, Bech32Variant)] = &[
+         ("A12UEL5L", Bech32Variant::Bech32),
+         ("a12uel5l", Bech32Variant::Bech32),
+         ("an83characterlonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1tt5tgs", Bech32Variant::Bech32),
+         ("abcdef1qpzry9x8gf2tvdw0s3jn54khce6mua7lmqqqxw", Bech32Variant::Bech32),
+         ("split1checkupstagehandshakeupstreamerranterredcaperred2y9e3w", Bech32Variant::Bech32),
+         ("?1ezyfcl", Bech32Variant::Bech32),
+         ("A1LQFN3A", Bech32Variant::Bech32m),
+         ("abcdef1l7aum6echk45nj3s0wdvt2fg8x9yrzpqzd3ryx", Bech32Variant::Bech32m),
+         ("split1checkupstagehandshakeupstreamerranterredcaperredlc445v", Bech32Variant::Bech32m),
+         ("?1v759aa", Bech32Variant::Bech32m),
+      ];
+      for &(s, variant) in cases.iter() {
+         let (hrp, data, v) = bech32_decode(s).unwrap();
+         assert_eq!(v, variant);
+         assert_eq!(bech32_encode(&hrp, &data[..], v).unwrap(), s.to_ascii_lowercase());
+      }
+   }
+
+   #[test]
+   fn test_invalid_checksum() {
+      let cases:&[&str] = &[
+         "\x201nwldj5",
+         "an84characterslonghumanreadablepartthatcontainsthenumber1andtheexcludedcharactersbio1569pvx",
+         "pzry9x0s0muk",
+         "1pzry9x0s0muk",
+         "x1b4n0q5v",
+         "li1dgmt3",
+         "A1G7SGD8",
+         "10a06t8",
+         "1qzzfhee",
+         "M1VUXWEZ",
+         "qyrz8wqd2c9m",
+         "y1b0jsk6g",
+         "lt1igcx5c0",
+      ];
+      for s in cases.iter() {
+         assert_matches!(bech32_decode(s), Err(crate::Error::Bech32Error(_)));
+      }
+   }
+
+   #[test]
+   fn test_locate_errors() {
+      let s = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4";
+      assert_eq!(locate_errors(s), Some(vec![]));
+      let mut b = s.as_bytes().to_vec();
+      b[10] = b'x';
+      let e1 = String::from_utf8(b.clone()).unwrap();
+      assert_eq!(locate_errors(&e1), Some(vec![10]));
+      b[30] = b'q';
+      let e2 = String::from_utf8(b).unwrap();
+      assert_eq!(locate_errors(&e2), Some(vec![10, 30]));
+      assert_matches!(bech32_decode(&e2), Err(crate::Error::Bech32Error(ref e)) if format!("{}", e).contains("[10, 30]"));
+
+      // both encodings locate 2 errors, Bech32 is preferred
+      assert_eq!(locate_errors("bc1qw50pd6qejxtdg4y5r3zzrvary0c5xw7kv8f3t4"), Some(vec![7, 23]));
+      // the encoding with fewer errors is preferred
+      let s = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0";
+      let mut b = s.as_bytes().to_vec();
+      b[20] = if b[20] == b'q' { b'p' } else { b'q' };
+      assert_eq!(locate_errors(&String::from_utf8(b).unwrap()), Some(vec![20]));
+   }
+
+   #[test]
+   fn test_segwit_address() {
+      let cases:&[(&str, &str, &str)] = &[
+         ("bc", "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4", "0014751e76e8199196d454941c45d1b3a323f1433bd6"),
+         ("tb", "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7", "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262"),
+         ("bc", "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y", "5128751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6"),
+         ("bc", "BC1SW50QGDZ25J", "6002751e"),
+         ("bc", "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs", "5210751e76e8199196d454941c45d1b3a323"),
+         ("bc", "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0", "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"),
+      ];
+      for &(hrp, addr, spk) in cases.iter() {
+         let spk = h2b(spk).unwrap();
+         let (version, program) = segwit_address_decode(hrp, addr).unwrap();
+         let expected_version = if spk[0] == 0 { 0 } else { spk[0] - 0x50 };
+         assert_eq!(version, expected_version);
+         assert_eq!(&program[..], &spk[2..]);
+         assert_eq!(segwit_address_encode(hrp, version, &program[..]).unwrap(), addr.to_ascii_lowercase());
+      }
+
+      let invalids:&[&str] = &[
+         "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut",
+         "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+         "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+         "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kemeawh",
+         "bc1rw5uspcuh",
+         "bc10w508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kw5rljs90",
+         "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+         "bc1zw508d6qejxtdg4y5r3zarvaryvqyzf3du",
+         "bc1gmk9yu",
+      ];
+      for addr in invalids.iter() {
+         assert_matches!(segwit_address_decode("bc", addr), Err(crate::Error::Bech32Error(_)));
+      }
+   }
+}
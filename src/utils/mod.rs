@@ -14,4 +14,12 @@ pub use self::base_n::{BaseN, BaseNError};
 pub mod base58check;
 pub use self::base58check::{Base58check, Base58checkError};
 
+pub mod base32;
+pub use self::base32::{base32_encode, base32_decode, convert_bits, Base32Error};
 
+pub mod bech32;
+pub use self::bech32::{
+   bech32_encode, bech32_decode, Bech32Variant,
+   segwit_address_encode, segwit_address_decode,
+   Bech32Error,
+};
@@ -84,7 +84,7 @@ fn verify(t: &TestCase) {
       &::fiatproof::ui::bitcoin::REGTEST,
    ];
    for c in chains.iter() {
-      if c.parse_address(s).is_some() {
+      if c.parse_address(s).is_ok() {
          fail!("parse_address", t, c.params.network);
       }
       if c.parse_secret_key_base58check(s).is_ok() {
@@ -171,8 +171,8 @@ fn verify_privkey(t: &TestCase) {
 fn verify_pubkey(t: &TestCase) {
    let payto = {
       let tmp = t.key.chain.parse_address(t.base58.as_str());
-      if tmp.is_none() {
-         fail!("parse_address", t, "unknown format");
+      if let Err(ref e) = tmp {
+         fail!("parse_address", t, e.description());
       }
       tmp.unwrap()
    };
@@ -182,6 +182,7 @@ fn verify_pubkey(t: &TestCase) {
       println!("actual:   {:?}", script.as_ref());
       fail!("script", t, "script mismatch");
    }
+   assert_eq!(t.key.chain.to_address(&payto).as_ref().map(|s| s.as_str()), Some(t.base58.as_str()));
    let flip_base58:String = t.base58.chars().map(|c| {
       if c.is_uppercase() {
          c.to_ascii_lowercase()
@@ -192,7 +193,7 @@ fn verify_pubkey(t: &TestCase) {
       }
    }).collect();
    let flip_payto = t.key.chain.parse_address(flip_base58.as_str());
-   let _ = match (t.key.try_case_flip, flip_payto.is_none()) {
+   let _ = match (t.key.try_case_flip, flip_payto.is_ok()) {
       (Some(true),  false) => { fail!("flip", t, "flip failed"); },
       (Some(false), true)  => { fail!("flip", t, "flip succeeded"); },
       (Some(false), false) => (),
@@ -214,7 +215,8 @@ fn verify(t: &TestCase) {
    if t.key.is_privkey {
       verify_privkey(t);
    } else {
-      if t.base58.chars().nth(0).unwrap() != '1' {
+      // these vectors predate BIP350 and encode witness v1+ addresses with the bech32 checksum.
+      if 0x51 <= t.payload_bytes[0] && t.payload_bytes[0] <= 0x60 {
          return;
       }
       verify_pubkey(t);
@@ -148,7 +148,7 @@ use ::fiatproof::bitcoin::script::Flags;
 fn parse_flags(input:&str) -> Flags {
    let flags = Flags {
       script_verify: ::fiatproof::bitcoin::script::flags::ScriptVerify::default(),
-      sig_version:   ::fiatproof::bitcoin::script::flags::SigVersion::WitnessV0,
+      sig_version:   ::fiatproof::bitcoin::script::flags::SigVersion::Base,
    };
    input.split(',').fold(flags, |mut acc,s| {
       match s {
@@ -292,7 +292,7 @@ fn check_verify_result(result: ::fiatproof::Result<()>, t: &TestData, tx: &::fia
    assert!(true);
 }
 
-fn build_test_transaction(script_pubkey:&[u8], script_sig:&[u8]) -> (Vec<::fiatproof::bitcoin::Tx>, ::fiatproof::bitcoin::Tx) {
+fn build_test_transaction(script_pubkey:&[u8], script_sig:&[u8], witness:&[Vec<u8>], amount:i64) -> (Vec<::fiatproof::bitcoin::Tx>, ::fiatproof::bitcoin::Tx) {
    use ::fiatproof::bitcoin::datatypes::*;
    let utx = {
       let mut tx = Tx::new_null();
@@ -302,9 +302,10 @@ fn build_test_transaction(script_pubkey:&[u8], script_sig:&[u8]) -> (Vec<::fiatp
          prevout:    TxOutPoint::new_null(),
          script_sig: Script::new( ::fiatproof::bitcoin::script::assemble("0 0").unwrap() ),
          sequence:   TxIn::SEQUENCE_FINAL,
+         witness:    ::fiatproof::bitcoin::Witness::new_null(),
       });
       tx.outs.push(TxOut {
-         value: 0,
+         value: amount,
          script_pubkey: Script::new(script_pubkey),
       });
       tx
@@ -320,6 +321,7 @@ fn build_test_transaction(script_pubkey:&[u8], script_sig:&[u8]) -> (Vec<::fiatp
          },
          script_sig: Script::new(script_sig),
          sequence:   TxIn::SEQUENCE_FINAL,
+         witness:    ::fiatproof::bitcoin::Witness::new(witness.to_vec()),
       });
       tx.outs.push(TxOut {
          value: utx.outs[0].value,
@@ -345,13 +347,10 @@ fn test_bitcoin_script_tests() {
       }
       r.unwrap()
    };
-   let verify = |sig:&[u8], pk:&[u8], flags:&Flags, t: &TestData| {
-      if flags.script_verify.is_witness() {
-         return;
-      }
-      use ::fiatproof::bitcoin::script::verify;
-      let tx = build_test_transaction(pk, sig).1;
-      let r = verify(sig, pk, &tx, 0, flags);
+   let verify = |sig:&[u8], pk:&[u8], witness:&[Vec<u8>], amount:i64, flags:&Flags, t: &TestData| {
+      use ::fiatproof::bitcoin::script::verify_with_amount;
+      let tx = build_test_transaction(pk, sig, witness, amount).1;
+      let r = verify_with_amount(sig, pk, &tx, 0, amount, None, flags);
       check_verify_result(r, t, &tx);
    };
    let mut _last_comment = String::new();
@@ -360,16 +359,22 @@ fn test_bitcoin_script_tests() {
          TestCase::Comment(c) => {
             _last_comment = c.clone();
          },
-         TestCase::T(ref t) if t.witness.is_none() => {
+         TestCase::T(ref t) => {
             let script_sig = assemble(&t.script_sig);
             let script_pk  = assemble(&t.script_pubkey);
             let flags = parse_flags(&t.flags);
-            verify(script_sig.as_slice(), script_pk.as_slice(), &flags, &t);
+            let (witness, amount) = match t.witness {
+               None => (Vec::new(), 0i64),
+               Some(ref w) => {
+                  let witness:Vec<Vec<u8>> = w.witnesses.iter().map(|h| {
+                     ::fiatproof::utils::h2b(h.as_str()).unwrap().to_vec()
+                  }).collect();
+                  let amount = (w.amount.as_f64().unwrap() * 100_000_000f64).round() as i64;
+                  (witness, amount)
+               },
+            };
+            verify(script_sig.as_slice(), script_pk.as_slice(), witness.as_slice(), amount, &flags, &t);
          },
-         TestCase::T(ref t) if t.witness.is_some() => (),
-         _ => {
-            panic!("unknown testcase");
-         }
       }
    }
 }
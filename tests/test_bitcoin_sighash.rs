@@ -0,0 +1,25 @@
+extern crate serde_json;
+extern crate fiatproof;
+
+#[test]
+fn test_bitcoin_sighash() {
+   use ::fiatproof::bitcoin::script::checker::get_hash;
+   let path = "tests/bitcoin-test-data/sighash.json";
+   let f = ::std::fs::File::open(path).unwrap();
+   let lines:Vec< Vec<::serde_json::Value> > = ::serde_json::from_reader(f).unwrap();
+   let mut count = 0;
+   for (n, v) in lines.iter().enumerate() {
+      if v.len() == 1 { continue; } // comment
+      assert_eq!(v.len(), 5, "unexpected format at {}", n);
+      let tx        = ::fiatproof::ui::bitcoin::hex_to_tx(v[0].as_str().unwrap()).unwrap();
+      let script    = ::fiatproof::utils::h2b(v[1].as_str().unwrap()).unwrap();
+      let in_idx    = v[2].as_u64().unwrap() as usize;
+      let hash_type = v[3].as_i64().unwrap() as i32;
+      let expect    = v[4].as_str().unwrap();
+
+      let hash = get_hash(&tx, in_idx, &script, hash_type).unwrap();
+      assert_eq!(::fiatproof::ui::b2h_rev(&hash[..]), expect, "at line {}", n+1);
+      count += 1;
+   }
+   assert!(0 < count);
+}
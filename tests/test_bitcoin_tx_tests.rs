@@ -0,0 +1,142 @@
+extern crate serde_json;
+extern crate fiatproof;
+
+use std::collections::HashMap;
+use ::fiatproof::bitcoin::datatypes::{Tx, TxOut, TxOutPoint, Script, UInt256};
+use ::fiatproof::bitcoin::script::Flags;
+
+#[derive(Debug)]
+struct TestData {
+   pub lineno: usize,
+   pub prevouts: HashMap<TxOutPoint, TxOut>,
+   pub tx: String,
+   pub flags: String,
+}
+
+fn parse_prevout(v: &::serde_json::Value) -> (TxOutPoint, TxOut) {
+   let v = v.as_array().unwrap();
+   let txid = {
+      let mut txid = UInt256::default();
+      let b = ::fiatproof::utils::h2b_rev(v[0].as_str().unwrap()).unwrap();
+      txid.data.copy_from_slice(&b[..]);
+      txid
+   };
+   let n = v[1].as_i64().unwrap() as u32; // -1 means null prevout
+   let script_pubkey = ::fiatproof::bitcoin::script::assemble(v[2].as_str().unwrap()).unwrap();
+   let value = if 3 < v.len() { v[3].as_i64().unwrap() } else { 0 };
+   (TxOutPoint { txid:txid, n:n }, TxOut { value:value, script_pubkey:Script::new(script_pubkey) })
+}
+
+fn read_testcases(path:&str) -> Vec<TestData> {
+   let f = ::std::fs::File::open(path).unwrap();
+   let lines:Vec< Vec<::serde_json::Value> > = ::serde_json::from_reader(f).unwrap();
+   lines.iter().enumerate().filter_map(|(n, v)| {
+      match v[0] {
+         ::serde_json::Value::Array(ref prevouts) => {
+            Some(TestData {
+               lineno:   n+1,
+               prevouts: prevouts.iter().map(parse_prevout).collect(),
+               tx:       v[1].as_str().unwrap().to_string(),
+               flags:    v[2].as_str().unwrap().to_string(),
+            })
+         },
+         _ => None, // comment
+      }
+   }).collect()
+}
+
+fn parse_flags(input:&str) -> Flags {
+   let flags = Flags {
+      script_verify: ::fiatproof::bitcoin::script::flags::ScriptVerify::default(),
+      sig_version:   ::fiatproof::bitcoin::script::flags::SigVersion::Base,
+   };
+   input.split(',').fold(flags, |mut acc,s| {
+      match s {
+         "" | "NONE" => (),
+         "P2SH" => {
+            acc.script_verify = acc.script_verify.p2sh(true);
+         },
+         "STRICTENC" => {
+            acc.script_verify = acc.script_verify.strict_enc(true);
+         },
+         "DERSIG" => {
+            acc.script_verify = acc.script_verify.der_sig(true);
+         },
+         "LOW_S" => {
+            acc.script_verify = acc.script_verify.low_s(true);
+         },
+         "NULLDUMMY" => {
+            acc.script_verify = acc.script_verify.null_dummy(true);
+         },
+         "SIGPUSHONLY" => {
+            acc.script_verify = acc.script_verify.sig_push_only(true);
+         },
+         "MINIMALDATA" => {
+            acc.script_verify = acc.script_verify.minimal_data(true);
+         },
+         "DISCOURAGE_UPGRADABLE_NOPS" => {
+            acc.script_verify = acc.script_verify.discourage_upgradable_nops(true);
+         },
+         "CLEANSTACK" => {
+            acc.script_verify = acc.script_verify.clean_stack(true);
+         },
+         "CHECKLOCKTIMEVERIFY" => {
+            acc.script_verify = acc.script_verify.check_locktime_verify(true);
+         },
+         "CHECKSEQUENCEVERIFY" => {
+            acc.script_verify = acc.script_verify.check_sequence_verify(true);
+         },
+         "WITNESS" => {
+            acc.script_verify = acc.script_verify.witness(true);
+         },
+         "DISCOURAGE_UPGRADABLE_WITNESS_PROGRAM" => {
+            acc.script_verify = acc.script_verify.discourage_upgradable_witness_program(true);
+         },
+         "MINIMALIF" => {
+            acc.script_verify = acc.script_verify.minimal_if(true);
+         },
+         "NULLFAIL" => {
+            acc.script_verify = acc.script_verify.null_fail(true);
+         },
+         "WITNESS_PUBKEYTYPE" => {
+            acc.script_verify = acc.script_verify.witness_pubkey_type(true);
+         },
+         _ => {
+            assert!(false, format!("  unknown flags {}", s));
+         }
+      }
+      acc
+   })
+}
+
+fn verify(t:&TestData) -> Result<(), String> {
+   use std::error::Error;
+   let tx:Tx = ::fiatproof::ui::bitcoin::hex_to_tx(t.tx.as_str()).map_err(|e| format!("deserialize: {}", e.description()))?;
+   ::fiatproof::bitcoin::validation::check_transaction(&tx).map_err(|e| format!("check_transaction: {}", e.description()))?;
+   let flags = parse_flags(t.flags.as_str());
+   let results = ::fiatproof::bitcoin::script::verify_tx(&tx, &t.prevouts, &flags).map_err(|e| e.description().to_string())?;
+   for (i, r) in results.into_iter().enumerate() {
+      r.map_err(|e| format!("input {}: {}", i, e.description()))?;
+   }
+   Ok(())
+}
+
+#[test]
+fn test_bitcoin_tx_valid() {
+   let tests = read_testcases("tests/bitcoin-test-data/tx_valid.json");
+   assert!(0 < tests.len());
+   let fails:Vec<String> = tests.iter().filter_map(|t| {
+      verify(t).err().map(|e| format!("line {}: expect valid but {}", t.lineno, e))
+   }).collect();
+   assert!(fails.is_empty(), format!("{} failures\n{}", fails.len(), fails.join("\n")));
+}
+
+#[test]
+fn test_bitcoin_tx_invalid() {
+   let tests = read_testcases("tests/bitcoin-test-data/tx_invalid.json");
+   assert!(0 < tests.len());
+   let fails:Vec<String> = tests.iter().filter_map(|t| {
+      verify(t).ok().map(|_| format!("line {}: expect invalid but valid", t.lineno))
+   }).collect();
+   assert!(fails.is_empty(), format!("{} failures\n{}", fails.len(), fails.join("\n")));
+}